@@ -24,7 +24,7 @@ fn emit_target_info() {
 /// Make the current git revision hash available to the build.
 fn emit_git_rev() {
     let git_output = process::Command::new("git")
-        .args(&["rev-parse", "HEAD"])
+        .args(["rev-parse", "HEAD"])
         .output();
     match git_output {
         Ok(git_output) => match String::from_utf8(git_output.stdout) {
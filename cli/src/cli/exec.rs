@@ -41,7 +41,7 @@ pub fn run_external(
     // TODO: Turn into a custom error with a better error message.
     let error = command.spawn_replace();
 
-    return Err(error.into());
+    Err(error.into())
 }
 
 #[cfg(unix)]
@@ -1,13 +1,17 @@
 use crate::cmd;
-use oceanpkg::Config;
-use std::ffi::OsStr;
+use oceanpkg::{
+    config::{file::NotFoundReason, ConfigFile, UserConfig},
+    install::InstallTarget,
+    Config,
+};
+use std::{ffi::OsStr, io};
 
 mod exec;
 
 pub fn main(config: &mut Config) -> crate::Result {
+    load_user_config(config);
     let args = cli().get_matches_safe()?;
     let args = resolve_aliases(config, args)?;
-
     match args.subcommand() {
         (subcommand, Some(args)) => {
             if let Some(run) = cmd::builtin_run_fn(subcommand) {
@@ -20,6 +24,31 @@ pub fn main(config: &mut Config) -> crate::Result {
     }
 }
 
+/// Reads the current user's configuration file, if there is one.
+fn load_user_config(config: &mut Config) {
+    let dir = match InstallTarget::CurrentUser.cfg_dir() {
+        Ok(dir) => dir,
+        Err(_) => return,
+    };
+    let file = match ConfigFile::find(&dir) {
+        Ok(file) => file,
+        Err(error) => {
+            let missing = match &error.reason {
+                NotFoundReason::NoMatch => true,
+                NotFoundReason::Io(io) => io.kind() == io::ErrorKind::NotFound,
+            };
+            if !missing {
+                eprintln!("warning: {}", error);
+            }
+            return;
+        }
+    };
+    match UserConfig::read(&file) {
+        Ok(user) => config.user = user,
+        Err(error) => eprintln!("warning: ignoring configuration: {}", error),
+    }
+}
+
 fn cli() -> clap::App<'static, 'static> {
     clap::App::new("ocean")
         .version(env!("CARGO_PKG_VERSION"))
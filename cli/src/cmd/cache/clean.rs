@@ -0,0 +1,137 @@
+use super::super::{dir_size, prelude::*};
+use oceanpkg::{
+    cache::CacheIndex,
+    config::ByteSize,
+    drop::{name::Query, version::Version},
+};
+use std::{fs, io, path::Path};
+
+pub const NAME: &str = "clean";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Removes cached tarballs")
+        .arg(
+            Arg::with_name("older-than")
+                .help("Only remove tarballs unused for this long, such as 30d")
+                .long("older-than")
+                .takes_value(true)
+                .value_name("age"),
+        )
+        .arg(
+            Arg::with_name("drop")
+                .help("Only remove tarballs of this drop, optionally @version"),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let older_than =
+        matches.value_of("older-than").map(parse_age).transpose()?;
+    let drop = matches.value_of("drop").map(Query::<&str>::parse_liberal);
+    let version = drop.and_then(|drop| drop.version).map(Version::parse);
+
+    let mut cache = CacheIndex::open(config.rt.cache_dir())?;
+    let now = super::now();
+    let stale: Vec<String> = cache
+        .entries()
+        .filter(|(_, entry)| {
+            older_than
+                .is_none_or(|age| now.saturating_sub(entry.last_used) >= age)
+        })
+        .filter(|(_, entry)| {
+            drop.is_none_or(|drop| {
+                entry.name.scope == drop.scope.unwrap_or("core")
+                    && entry.name.name == drop.name
+                    && version.as_ref().is_none_or(|v| entry.version == *v)
+            })
+        })
+        .map(|(checksum, _)| checksum.to_owned())
+        .collect();
+
+    let mut count = 0;
+    let mut freed = 0;
+    for checksum in stale {
+        if let Some(entry) = cache.delete(&checksum)? {
+            count += 1;
+            freed += entry.size;
+        }
+    }
+    cache.save()?;
+
+    // Without filters, downloads that never made it into the index go too,
+    // along with tarballs packaged from local sources.
+    if older_than.is_none() && drop.is_none() {
+        freed += remove_strays(&cache)?;
+        freed += remove_local(&config.rt.local_cache_dir())?;
+    }
+
+    println!("Removed {} tarball(s), freeing {}", count, ByteSize(freed));
+    Ok(())
+}
+
+/// Removes interrupted downloads and tarballs that the index doesn't know
+/// about, returning the number of bytes freed.
+fn remove_strays(cache: &CacheIndex) -> io::Result<u64> {
+    let known: Vec<&str> = cache
+        .entries()
+        .map(|(_, entry)| entry.file.as_str())
+        .collect();
+    let mut freed = 0;
+    for entry in fs::read_dir(cache.dir())? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = match name.to_str() {
+            Some(name) => name,
+            None => continue,
+        };
+        let tarball = name
+            .strip_suffix(".partial.etag")
+            .or_else(|| name.strip_suffix(".partial"))
+            .or_else(|| name.strip_suffix(".etag"))
+            .unwrap_or(name);
+        if !tarball.ends_with(".tar.gz") || known.contains(&tarball) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            fs::remove_file(entry.path())?;
+            freed += metadata.len();
+        }
+    }
+    Ok(freed)
+}
+
+/// Removes the tarballs packaged from local sources in `dir`, returning the
+/// number of bytes freed.
+fn remove_local(dir: &Path) -> io::Result<u64> {
+    let size = dir_size(dir)?;
+    match fs::remove_dir_all(dir) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(size),
+    }
+}
+
+/// Parses an age such as `30d` into seconds.
+fn parse_age(age: &str) -> crate::Result<u64> {
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (count, unit) = age.split_at(split);
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => failure::bail!(
+            "invalid age {:?}; expected a number with s, m, h, d, or w",
+            age,
+        ),
+    };
+    match count
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit_secs))
+    {
+        Some(secs) => Ok(secs),
+        None => failure::bail!("invalid age {:?}", age),
+    }
+}
@@ -0,0 +1,61 @@
+use super::super::prelude::*;
+use oceanpkg::{cache::CacheIndex, config::ByteSize};
+
+pub const NAME: &str = "list";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Lists cached tarballs, most recently used first")
+}
+
+pub fn run(config: &mut Config, _matches: &ArgMatches) -> crate::Result {
+    let cache = CacheIndex::open(config.rt.cache_dir())?;
+    let mut entries: Vec<_> = cache.entries().collect();
+    if entries.is_empty() {
+        println!("The cache is empty");
+        return Ok(());
+    }
+    entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_used));
+
+    let now = super::now();
+    for (checksum, entry) in entries {
+        println!(
+            "- {} ({}): {}, used {}",
+            entry.name,
+            entry.version,
+            ByteSize(entry.size),
+            age(now.saturating_sub(entry.last_used)),
+        );
+        println!("    sha256: {}", checksum);
+        if let Some(url) = &entry.url {
+            println!("    url: {}", url);
+        }
+    }
+
+    println!();
+    println!(
+        "{} tarball(s), {}",
+        cache.entries().count(),
+        ByteSize(cache.size()),
+    );
+    Ok(())
+}
+
+/// Formats `secs` as a rough age, such as "3 days ago".
+fn age(secs: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [
+        (86400, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ];
+
+    for &(unit_secs, unit) in &UNITS {
+        let count = secs / unit_secs;
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{} {}{} ago", count, unit, plural);
+        }
+    }
+    "just now".to_owned()
+}
@@ -0,0 +1,43 @@
+use super::prelude::*;
+
+mod clean;
+mod list;
+mod size;
+
+pub const NAME: &str = "cache";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Manage downloaded drop tarballs")
+        .settings(&[
+            AppSettings::SubcommandRequiredElseHelp,
+            AppSettings::DeriveDisplayOrder,
+        ])
+        .subcommands(vec![list::cmd(), clean::cmd(), size::cmd()])
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    if let (command, Some(matches)) = matches.subcommand() {
+        let run = match command {
+            list::NAME => list::run,
+            clean::NAME => clean::run,
+            size::NAME => size::run,
+            _ => unreachable!("could not match command {:?}", command),
+        };
+        run(config, matches)
+    } else {
+        // SubcommandRequiredElseHelp
+        unreachable!()
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch, which is how
+/// `CacheEntry::last_used` is recorded.
+fn now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
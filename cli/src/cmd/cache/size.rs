@@ -0,0 +1,29 @@
+use super::super::{dir_size, prelude::*};
+use oceanpkg::{cache::CacheIndex, config::ByteSize};
+
+pub const NAME: &str = "size";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME).about("Shows how much space the cache takes")
+}
+
+pub fn run(config: &mut Config, _matches: &ArgMatches) -> crate::Result {
+    let cache = CacheIndex::open(config.rt.cache_dir())?;
+    let total = dir_size(cache.dir())?;
+
+    println!(
+        "Tarballs: {} ({})",
+        cache.entries().count(),
+        ByteSize(cache.size()),
+    );
+    println!(
+        "Total: {} in \"{}\"",
+        ByteSize(total),
+        cache.dir().display()
+    );
+    match config.user.cache_max_size {
+        ByteSize(0) => println!("Tarball limit: none"),
+        max_size => println!("Tarball limit: {}", max_size),
+    }
+    Ok(())
+}
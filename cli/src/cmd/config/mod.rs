@@ -16,27 +16,8 @@ pub fn cmd() -> App {
 pub fn run(_state: &mut Config, matches: &ArgMatches) -> crate::Result {
     let install_target = matches.install_target();
 
-    let config_dir = match install_target.cfg_dir() {
-        Ok(dir) => dir,
-        Err(err) => {
-            unimplemented!("{}", err);
-        }
-    };
-
-    if !config_dir.exists() {
-        if let Err(err) = fs::create_dir(&config_dir) {
-            match err.kind() {
-                io::ErrorKind::PermissionDenied => {
-                    panic!("Permission to create {:?} denied", config_dir);
-                }
-                _ => panic!("{}", err),
-            }
-        }
-    }
-
-    if !config_dir.is_dir() {
-        panic!("Found non-directory file at {:?}", config_dir);
-    }
+    let config_dir = install_target.cfg_dir()?;
+    install_target.check_access(&config_dir)?;
 
     let config_file = match ConfigFile::find(&config_dir) {
         Ok(file) => file,
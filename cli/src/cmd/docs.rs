@@ -25,9 +25,8 @@ pub fn run(_state: &mut Config, matches: &ArgMatches) -> crate::Result {
     let print_docs = matches.is_present("print");
 
     if let Some(drops) = matches.values_of("drops") {
-        for drop in drops {
-            unimplemented!("TODO: Open the documentation page for {:?}", drop);
-        }
+        let drops: Vec<&str> = drops.collect();
+        unimplemented!("TODO: Open the documentation pages for {:?}", drops);
     } else if print_docs {
         println!("{}", OCEAN_DOCS);
     } else {
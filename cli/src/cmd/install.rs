@@ -1,17 +1,28 @@
 use super::prelude::*;
 use oceanpkg::{
-    api,
+    api::{self, v1::DownloadScheduler},
+    auth::{
+        signing::{VerifyError, VerifyMode},
+        Keyring, Signature,
+    },
+    cache::{CacheEntry, CacheIndex, CacheRegistry},
     drop::{
-        name::{Name, Query},
-        Manifest,
+        lock::{self, LockedDrop},
+        manifest::{DepInfo, Deps},
+        name::{Name, Query, ScopedName},
+        source,
+        version::VersionReq,
+        Lockfile, Manifest, Package, Source,
     },
+    install::{self, InstallReason, Receipt, Receipts, Transaction},
+    resolve::{self, Resolution, SourcedDrop},
+    system::Platform,
 };
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     fs::{self, File},
-    io::{BufWriter, Write},
-    mem,
+    io::{Seek, SeekFrom},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
 };
 
 pub const NAME: &str = "install";
@@ -29,10 +40,31 @@ pub fn cmd() -> App {
         )
         .arg(
             Arg::with_name("drop")
-                .help("The package(s) to install")
-                .multiple(true)
-                .required(true),
+                .help("The package(s) to install instead of Ocean.toml deps")
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("manifest")
+                .help("Path to Ocean.toml")
+                .long("manifest")
+                .takes_value(true)
+                .conflicts_with("drop"),
+        )
+        .arg(
+            Arg::with_name("locked")
+                .help("Require Ocean.lock to be up-to-date")
+                .long("locked"),
+        )
+        .arg(
+            Arg::with_name("frozen")
+                .help("Like `--locked`, but without network access")
+                .long("frozen"),
+        )
+        .arg(
+            Arg::offline_flag()
+                .help("Install only from drops that were already downloaded"),
         )
+        .arg(Arg::jobs_flag().help("The number of drops to download at once"))
         .arg(
             Arg::with_name("with")
                 .help("Include optional dependencies")
@@ -66,133 +98,721 @@ pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
 
     let install_target = matches.install_target();
     println!("Installing for {:?}", install_target);
+    check_access(config, &install_target)?;
+    recover(config, &install_target)?;
+
+    let frozen = matches.is_present("frozen");
+    let offline = matches.offline(config);
+    let jobs = matches.jobs()?;
+    let locked = frozen || matches.is_present("locked");
+
+    let (root, deps, base_dir, lock_path) = match matches.values_of("drop") {
+        Some(drops) => {
+            if locked {
+                failure::bail!("`--locked` and `--frozen` require Ocean.toml");
+            }
+            let base_dir = config.rt.current_dir().to_path_buf();
+            (
+                "your request".to_owned(),
+                query_deps(drops)?,
+                base_dir,
+                None,
+            )
+        }
+        None => {
+            let manifest_path = match matches.value_of_os("manifest") {
+                Some(path) => PathBuf::from(path),
+                None => config.rt.current_dir().join(Manifest::FILE_NAME),
+            };
+            let manifest = Manifest::read_toml_file(&manifest_path)?;
+            let lock_path = manifest_path.with_file_name(Lockfile::FILE_NAME);
+            let deps = manifest.deps.unwrap_or_default();
+            let base_dir = match manifest_path.parent() {
+                Some(dir) => dir.to_path_buf(),
+                None => config.rt.current_dir().to_path_buf(),
+            };
+            (manifest.meta.name, deps, base_dir, Some(lock_path))
+        }
+    };
+
+    let lockfile = match &lock_path {
+        Some(path) if path.exists() => Some(Lockfile::read_toml_file(path)?),
+        _ if locked => failure::bail!("`--locked` requires Ocean.lock"),
+        _ => None,
+    };
+
+    println!("Resolving dependencies...");
+    let registry_source = Source::from_registry(api::url()?);
+    let mut options = match &lockfile {
+        Some(lockfile) => resolve::Options::locked(lockfile),
+        None => resolve::Options::default(),
+    };
+    let drops_dir = config.rt.drops_dir(&install_target)?;
+    options.installed = install::installed_manifests(&drops_dir)?;
+    options.with = with_deps.iter().map(|name| name.to_string()).collect();
+    options.without =
+        without_deps.iter().map(|name| name.to_string()).collect();
+    options.platform = Some(Platform::new(
+        env!("OCEAN_TARGET_OS"),
+        env!("OCEAN_TARGET_ARCH"),
+    ));
+
+    let mut cache = CacheIndex::open(config.rt.cache_dir())?;
+
+    // Frozen and offline installs don't contact the registry, so they rely on
+    // the digests in Ocean.lock or the cache, which were checked when the
+    // drops were first downloaded, and on the signatures that the cache
+    // recorded alongside them.
+    let (resolution, published, signatures, sourced) =
+        if let (true, Some(lockfile)) = (frozen, &lockfile) {
+            let mut lockfile = lockfile.clone();
+            let resolution = resolve::resolve_deps_with(
+                &root,
+                &deps,
+                &mut lockfile,
+                &options,
+            )?;
+            (resolution, BTreeMap::new(), None, BTreeMap::new())
+        } else if offline {
+            let (resolution, sourced) = resolve_offline(
+                config,
+                &cache,
+                &root,
+                &deps,
+                &base_dir,
+                &options,
+                lockfile.as_ref(),
+            )?;
+            (resolution, BTreeMap::new(), None, sourced)
+        } else {
+            let registry = api::v1::Registry::new()?;
+            let mut registry =
+                resolve::SourceRegistry::new(registry, config.rt.cache_dir());
+            if let Some(lockfile) = &lockfile {
+                registry.set_locked(lockfile);
+            }
+            registry.add_deps(&deps, &base_dir)?;
+            let resolution = resolve::resolve_deps_with(
+                &root,
+                &deps,
+                &mut registry,
+                &options,
+            )?;
+            let published =
+                published_checksums(registry.registry(), &resolution);
+            let signatures =
+                published_signatures(registry.registry(), &resolution);
+            (
+                resolution,
+                published,
+                Some(signatures),
+                registry.into_sourced(),
+            )
+        };
+
+    let mut locked_drops: Vec<LockedDrop> = resolution
+        .drops
+        .iter()
+        .map(|(name, drop)| {
+            let previous = lockfile
+                .as_ref()
+                .and_then(|lockfile| lockfile.get(name.to_ref()))
+                .filter(|previous| previous.version == drop.version);
+            LockedDrop {
+                name: name.clone(),
+                version: drop.version.clone(),
+                source: match (sourced.get(name), previous) {
+                    (Some(sourced), _) => sourced.source.clone(),
+                    (None, Some(previous)) => previous.source.clone(),
+                    (None, None) => registry_source.clone(),
+                },
+                checksum: previous.and_then(|p| p.checksum.clone()),
+                dependencies: drop.deps.clone(),
+            }
+        })
+        .collect();
+
+    if let Some(lockfile) = &lockfile {
+        if locked && *lockfile != Lockfile::new(locked_drops.clone()) {
+            failure::bail!(
+                "Ocean.lock needs to be updated but `--locked` was passed"
+            );
+        }
+    }
+    add_published_checksums(&mut locked_drops, &published)?;
+    if offline {
+        let drops = locked_drops
+            .iter_mut()
+            .filter(|drop| !sourced.contains_key(&drop.name));
+        use_cached_tarballs(&cache, drops)?;
+    }
+    let signatures =
+        signatures.unwrap_or_else(|| cached_signatures(&cache, &locked_drops));
+    verify_signatures(config, &signatures, &locked_drops)?;
+
+    let receipts = Receipts::new(config.rt.receipts_dir(&install_target)?);
+    let bin_dir = config.rt.target_bin_dir(&install_target)?;
+    let mut transaction =
+        Transaction::begin(&drops_dir, bin_dir, receipts.clone())?;
+    let failed_downloads = if frozen || offline {
+        HashSet::new()
+    } else {
+        let downloads = locked_drops
+            .iter()
+            .filter(|drop| !sourced.contains_key(&drop.name));
+        download_drops(config, &cache, jobs, downloads)?
+    };
+    let mut failed = !failed_downloads.is_empty();
+
+    // Every drop is staged before any of them are installed, so that a
+    // failure leaves the installed drops untouched.
+    for (locked_drop, (name, drop)) in
+        locked_drops.iter_mut().zip(&resolution.drops)
+    {
+        if failed_downloads.contains(name) {
+            continue;
+        }
+        println!("Fetching \"{}\" ({})...", name, drop.version);
+
+        let requirement = requirement(&deps, name);
+        let previous = match receipts.get(name.to_ref(), &drop.version) {
+            Ok(previous) => previous,
+            Err(error) => {
+                error!("failed to install \"{}\": {}", name, error);
+                failed = true;
+                continue;
+            }
+        };
+        let explicit = requirement.is_some()
+            || previous.is_some_and(|receipt| receipt.is_explicit());
+        let reason = if explicit {
+            InstallReason::Explicit
+        } else {
+            InstallReason::Dependency
+        };
+
+        let staged = stage_drop(
+            config,
+            &mut cache,
+            &mut transaction,
+            locked_drop,
+            StageOptions {
+                sourced: sourced.get(name),
+                frozen,
+                requirement,
+                reason,
+                signature: signatures.get(name).copied(),
+            },
+        );
+        if let Err(error) = staged {
+            error!("failed to install \"{}\": {}", name, error);
+            failed = true;
+        }
+    }
 
-    struct VersionedQuery<S> {
-        scope: Option<S>,
-        name: S,
-        version: S,
+    cache.save()?;
+    if failed {
+        failure::bail!("nothing was installed because of the errors above");
     }
 
-    impl<'a> VersionedQuery<&'a str> {
-        fn file_name(&self) -> String {
-            format!("{}@{}", self.name, self.version)
+    println!("Installing...");
+    commit(transaction)?;
+    evict_cache(config, &mut cache);
+
+    if let Some(lock_path) = &lock_path {
+        let new_lockfile = Lockfile::new(locked_drops);
+        if lockfile.as_ref() != Some(&new_lockfile) {
+            new_lockfile.write_toml_file(lock_path)?;
         }
     }
 
-    let mut successes = Vec::<VersionedQuery<&str>>::new();
-
-    if let Some(drops) = matches.values_of("drop") {
-        for drop in drops {
-            // TODO: Parse name
-            println!("Installing \"{}\"...", drop);
-
-            macro_rules! fail {
-                ($error:expr) => {{
-                    error!(
-                        "failed to install \"{}\": {} (line {})",
-                        drop,
-                        $error,
-                        line!()
-                    );
-                    continue;
-                }};
-            }
+    // Get duration immediately after installing finishes.
+    let elapsed = config.rt.time_elapsed();
 
-            let query = Query::<&str>::parse_liberal(drop);
-            let download = match download(config, query) {
-                Ok(dl) => dl,
-                Err(error) => fail!(error),
-            };
+    if !resolution.drops.is_empty() {
+        println!();
+        println!("Successfully installed:");
+        for (name, drop) in &resolution.drops {
+            println!("- {} ({})", name, drop.version);
+        }
+    }
 
-            let drops_dir = config.rt.drops_dir(&install_target);
-            let unpack_dir = {
-                let mut dir = drops_dir.into_owned();
-                dir.push(query.scope.unwrap_or("core"));
-                dir
-            };
+    println!();
+    println!("Finished in {:?}", elapsed);
+    Ok(())
+}
 
-            let manifest_path = match unpack(&download.path, &unpack_dir) {
-                Ok(path) => unpack_dir.join(path),
-                Err(error) => fail!(error),
-            };
+/// Resolves `deps` using only the drops whose tarballs are in `cache` and the
+/// git repositories already cloned into the cache directory.
+///
+/// If resolution fails, the drops that are missing from the cache are listed.
+pub(super) fn resolve_offline(
+    config: &Config,
+    cache: &CacheIndex,
+    root: &str,
+    deps: &Deps,
+    base_dir: &Path,
+    options: &resolve::Options,
+    lockfile: Option<&Lockfile>,
+) -> crate::Result<(Resolution, BTreeMap<ScopedName, SourcedDrop>)> {
+    let registry = CacheRegistry::new(cache)?;
+    let mut registry =
+        resolve::SourceRegistry::new(registry, config.rt.cache_dir());
+    registry.set_offline(true);
+    if let Some(lockfile) = lockfile {
+        registry.set_locked(lockfile);
+    }
+    registry.add_deps(deps, base_dir)?;
+    match resolve::resolve_deps_with(root, deps, &mut registry, options) {
+        Ok(resolution) => Ok((resolution, registry.into_sourced())),
+        Err(error) => {
+            let missing: Vec<String> = registry
+                .registry()
+                .missing()
+                .map(|name| format!("- {}", name))
+                .collect();
+            if missing.is_empty() {
+                return Err(error.into());
+            }
+            failure::bail!(
+                "{}\n\nThese drops are missing from the cache:\n{}",
+                error,
+                missing.join("\n"),
+            );
+        }
+    }
+}
 
-            let manifest = match Manifest::read_toml_file(&manifest_path) {
-                Ok(m) => m,
-                Err(error) => fail!(error),
-            };
+/// Expects the tarballs of `drops` to be in `cache`, filling in their digests
+/// from it and failing with a list of those that are missing.
+///
+/// A drop locked to a digest is only found if the cached tarball matches it.
+pub(super) fn use_cached_tarballs<'a, I>(
+    cache: &CacheIndex,
+    drops: I,
+) -> crate::Result
+where
+    I: IntoIterator<Item = &'a mut LockedDrop>,
+{
+    let mut missing = Vec::new();
+    for drop in drops {
+        let cached = match &drop.checksum {
+            Some(checksum) => {
+                cache.get(checksum).map(|entry| (checksum.as_str(), entry))
+            }
+            None => cache.find(drop.name.to_ref(), &drop.version),
+        };
+        match cached.filter(|(_, entry)| cache.path(entry).is_file()) {
+            Some((checksum, _)) => drop.checksum = Some(checksum.to_owned()),
+            None => missing.push(format!("- {} ({})", drop.name, drop.version)),
+        }
+    }
+    if !missing.is_empty() {
+        failure::bail!(
+            "These drops are missing from the cache:\n{}",
+            missing.join("\n"),
+        );
+    }
+    Ok(())
+}
 
-            let query = VersionedQuery {
-                scope: query.scope,
-                name: query.name,
-                version: match query.version {
-                    Some(v) => v,
-                    None => {
-                        fn leak(string: String) -> &'static str {
-                            let slice = string.as_str() as *const str;
-                            mem::forget(string);
-                            unsafe { &*slice }
-                        }
-                        leak(manifest.meta.version.to_string())
-                    }
-                },
-            };
+/// Returns the digests that the registry published for the tarballs of the
+/// drops in `resolution`.
+pub(super) fn published_checksums(
+    registry: &api::v1::Registry,
+    resolution: &Resolution,
+) -> BTreeMap<ScopedName, String> {
+    resolution
+        .drops
+        .iter()
+        .filter_map(|(name, drop)| {
+            let checksum = registry.checksum(name.to_ref(), &drop.version)?;
+            Some((name.clone(), checksum.to_owned()))
+        })
+        .collect()
+}
 
-            let drop_path = {
-                let mut path = unpack_dir;
-                path.push(query.file_name());
-                path
-            };
+/// Returns the signatures that the registry published for the digests of the
+/// drops in `resolution`.
+pub(super) fn published_signatures(
+    registry: &api::v1::Registry,
+    resolution: &Resolution,
+) -> BTreeMap<ScopedName, Signature> {
+    resolution
+        .drops
+        .iter()
+        .filter_map(|(name, drop)| {
+            let signature = registry.signature(name.to_ref(), &drop.version)?;
+            Some((name.clone(), *signature))
+        })
+        .collect()
+}
 
-            let exe_path = drop_path.join(manifest.meta.exe_path());
-            if !exe_path.is_file() {
-                fail!(failure::format_err!(
-                    "file not found: \"{}\"",
-                    exe_path.display(),
-                ));
-            }
+/// Returns the signatures recorded in `cache` for the digests of `drops`,
+/// which the registry published when their tarballs were downloaded.
+pub(super) fn cached_signatures<'a, I>(
+    cache: &CacheIndex,
+    drops: I,
+) -> BTreeMap<ScopedName, Signature>
+where
+    I: IntoIterator<Item = &'a LockedDrop>,
+{
+    drops
+        .into_iter()
+        .filter_map(|drop| {
+            let entry = cache.get(drop.checksum.as_ref()?)?;
+            Some((drop.name.clone(), entry.signature?))
+        })
+        .collect()
+}
 
-            let chmod_status =
-                Command::new("chmod").arg("+x").arg(&exe_path).status();
-            match chmod_status {
-                Ok(status) => {
-                    if !status.success() {
-                        fail!(failure::format_err!(
-                            "failed to make \"{}\" executable",
-                            exe_path.display(),
-                        ));
-                    }
-                }
-                Err(error) => fail!(error),
+/// Checks that the digest of each of `drops` was signed by a key trusted for
+/// its scope.
+///
+/// Only drops from a registry are signed, so others are skipped. Depending on
+/// the configured `VerifyMode`, drops without a trusted signature are either
+/// warned about or fail the installation.
+pub(super) fn verify_signatures<'a, I>(
+    config: &Config,
+    signatures: &BTreeMap<ScopedName, Signature>,
+    drops: I,
+) -> crate::Result
+where
+    I: IntoIterator<Item = &'a LockedDrop>,
+{
+    let trust = Keyring::new(config.rt.keys_dir()).trust_store()?;
+    let mut unverified = 0;
+    let drops = drops
+        .into_iter()
+        .filter(|drop| matches!(drop.source.kind(), source::Kind::Registry));
+    for drop in drops {
+        let result = match &drop.checksum {
+            Some(checksum) => trust.verify(
+                &drop.name.scope,
+                checksum.as_bytes(),
+                signatures.get(&drop.name),
+            ),
+            None => Err(VerifyError::Unsigned),
+        };
+        let error = match result {
+            Ok(_) => continue,
+            Err(error) => error,
+        };
+        match trust.mode {
+            VerifyMode::Warn => eprintln!(
+                "warning: could not verify \"{}\" ({}): {}",
+                drop.name, drop.version, error,
+            ),
+            VerifyMode::Require => {
+                error!(
+                    "could not verify \"{}\" ({}): {}",
+                    drop.name, drop.version, error,
+                );
+                unverified += 1;
             }
+        }
+    }
+    if unverified > 0 {
+        failure::bail!(
+            "refusing to install {} drop(s) without a trusted signature",
+            unverified,
+        );
+    }
+    Ok(())
+}
 
-            successes.push(query);
+/// Expects the tarballs of `drops` to have the digests in `published`,
+/// failing if they were locked to a different digest.
+pub(super) fn add_published_checksums(
+    drops: &mut [LockedDrop],
+    published: &BTreeMap<ScopedName, String>,
+) -> crate::Result {
+    for drop in drops {
+        let published = match published.get(&drop.name) {
+            Some(published) => published,
+            None => continue,
+        };
+        match &drop.checksum {
+            Some(locked) if locked != published => failure::bail!(
+                "checksum {} of \"{}\" ({}) in Ocean.lock does not match {} \
+                 published by the registry",
+                locked,
+                drop.name,
+                drop.version,
+                published,
+            ),
+            _ => drop.checksum = Some(published.clone()),
         }
     }
+    Ok(())
+}
 
-    // Get duration immediately after shipping finishes.
-    let elapsed = config.rt.time_elapsed();
+/// Downloads the tarballs of `drops` from the registry into the cache, at most
+/// `jobs` at a time, returning the names of those that failed.
+///
+/// Tarballs in `cache` with the expected digest aren't downloaded again.
+pub(super) fn download_drops<'a, I>(
+    config: &Config,
+    cache: &CacheIndex,
+    jobs: usize,
+    drops: I,
+) -> crate::Result<HashSet<ScopedName>>
+where
+    I: IntoIterator<Item = &'a LockedDrop>,
+{
+    let mut scheduler = DownloadScheduler::new(jobs);
+    let mut names = HashMap::new();
+    for drop in drops {
+        let cached = drop.checksum.as_ref().and_then(|c| cache.get(c));
+        if cached.is_some_and(|entry| cache.path(entry).is_file()) {
+            continue;
+        }
+        let version = drop.version.to_string();
+        let query = drop.name.to_ref().into_query_with(&*version);
+        let path = config.rt.tarball_cache_path(query);
+        if scheduler.add_drop(query, &path)? {
+            names.insert(path, &drop.name);
+        }
+    }
+    let mut failed = HashSet::new();
+    if scheduler.is_empty() {
+        return Ok(failed);
+    }
 
-    if !successes.is_empty() {
-        println!();
-        println!("Successfully installed:");
-        for query in successes {
-            print!("- ");
-            if let Some(scope) = query.scope {
-                print!("{}/", scope);
-            }
-            print!("{} ({})", query.name, query.version);
-            println!();
+    println!("Downloading {} drop(s)...", scheduler.len());
+    for (path, result) in scheduler.run() {
+        if let Err(error) = result {
+            let name = names[&path];
+            error!("failed to download \"{}\": {}", name, error);
+            failed.insert(name.clone());
         }
     }
+    Ok(failed)
+}
 
-    println!();
-    println!("Finished in {:?}", elapsed);
+/// Options for how [`stage_drop`] fetches a drop and records its receipt.
+pub(super) struct StageOptions<'a> {
+    /// The local source to package the drop from, if it has one.
+    pub sourced: Option<&'a SourcedDrop>,
+    /// Whether the drop must come from the cache as locked, even if it has a
+    /// local source.
+    pub frozen: bool,
+    /// The requirement that the drop was explicitly installed with.
+    pub requirement: Option<VersionReq>,
+    /// Why the drop is being installed.
+    pub reason: InstallReason,
+    /// The registry's signature of the drop's digest, to be recorded in the
+    /// cache for installs that don't contact the registry.
+    pub signature: Option<Signature>,
+}
+
+/// Fetches `drop` at its locked version and stages it in `transaction`, to
+/// be installed as the active version when it's committed.
+///
+/// The SHA-256 digest of the fetched tarball is checked against and then
+/// stored in `drop`. A downloaded tarball that doesn't match is deleted.
+pub(super) fn stage_drop(
+    config: &Config,
+    cache: &mut CacheIndex,
+    transaction: &mut Transaction,
+    drop: &mut LockedDrop,
+    options: StageOptions,
+) -> crate::Result {
+    let StageOptions {
+        sourced,
+        frozen,
+        requirement,
+        reason,
+        signature,
+    } = options;
+    let name = &drop.name;
+    let version = drop.version.to_string();
+    let query = name.as_ref().map(String::as_str).into_query_with(&*version);
+    // Drops from local sources are packaged into their own part of the cache,
+    // since they may differ from the registry's drop of the same version.
+    let local = !matches!(drop.source.kind(), source::Kind::Registry);
+    let (path, download) = match sourced {
+        Some(sourced) if !frozen => {
+            let path = config.rt.local_tarball_cache_path(query);
+            let download = package(&path, &sourced.path)?;
+            (path, download)
+        }
+        _ if local => {
+            let path = config.rt.local_tarball_cache_path(query);
+            let download = cached(&path, frozen)?;
+            (path, download)
+        }
+        _ => {
+            let indexed = drop.checksum.as_ref().and_then(|c| cache.get(c));
+            let path = match indexed {
+                Some(entry) => cache.path(entry),
+                None => config.rt.tarball_cache_path(query),
+            };
+            let download = cached(&path, frozen)?;
+            (path, download)
+        }
+    };
+
+    let checksum = lock::checksum(&download.file)?;
+    match &drop.checksum {
+        Some(expected) if *expected != checksum && local => {
+            failure::bail!(
+                "checksum {} does not match {} in Ocean.lock",
+                checksum,
+                expected,
+            );
+        }
+        Some(expected) if *expected != checksum => {
+            // The tarball is corrupt or was tampered with, so don't reuse it.
+            drop_tarball(cache, &path, expected);
+            failure::bail!(
+                "checksum {} of \"{}\" does not match the expected {}, so it \
+                 was deleted",
+                checksum,
+                path.display(),
+                expected,
+            );
+        }
+        _ => drop.checksum = Some(checksum.clone()),
+    }
+
+    // Only registry tarballs are indexed, so that they can be found by digest.
+    let file = path.file_name().and_then(|file| file.to_str());
+    if let Some(file) = file.filter(|_| !local) {
+        let indexed = cache.get(&checksum).filter(|entry| {
+            entry.file == file
+                && (signature.is_none() || entry.signature == signature)
+        });
+        // A cache hit only needs to be marked as used.
+        if indexed.is_some() {
+            cache.touch(&checksum);
+        } else {
+            let size = download.file.metadata()?.len();
+            let mut entry = CacheEntry::new(
+                name.clone(),
+                drop.version.clone(),
+                file.into(),
+                size,
+            );
+            entry.url = api::v1::drop_url(query).ok();
+            entry.signature = signature.or_else(|| {
+                cache.get(&checksum).and_then(|entry| entry.signature)
+            });
+            cache.insert(checksum, entry);
+        }
+    }
+
+    let receipt = Receipt {
+        name: name.clone(),
+        version: drop.version.clone(),
+        requirement,
+        source: drop.source.clone(),
+        installed_at: Receipt::now(),
+        reason,
+        dependencies: drop.dependencies.clone(),
+        binaries: Vec::new(),
+        files: Vec::new(),
+    };
+    let mut tarball = &download.file;
+    tarball.seek(SeekFrom::Start(0))?;
+    transaction.stage(tarball, receipt)?;
     Ok(())
 }
 
+/// Checks that drops can be installed for `target` before changing anything,
+/// so that missing permissions are reported up front.
+pub(super) fn check_access(
+    config: &Config,
+    target: &InstallTarget,
+) -> crate::Result {
+    let home = config.rt.target_home(target)?;
+    target.check_access(&home)?;
+    Ok(())
+}
+
+/// Rolls back an installation for `target` that was interrupted, such as by a
+/// crash or power loss, so that installed drops are never left half-updated.
+///
+/// This needs the access checked by [`check_access`], and should happen before
+/// reading which drops are installed.
+pub(super) fn recover(
+    config: &Config,
+    target: &InstallTarget,
+) -> crate::Result {
+    let drops_dir = config.rt.drops_dir(target)?;
+    let bin_dir = config.rt.target_bin_dir(target)?;
+    let receipts = Receipts::new(config.rt.receipts_dir(target)?);
+    match Transaction::recover(&drops_dir, &bin_dir, &receipts) {
+        Ok(true) => eprintln!("Rolled back an interrupted installation"),
+        Ok(false) => {}
+        Err(error) => eprintln!(
+            "warning: failed to roll back an interrupted installation: {}",
+            error,
+        ),
+    }
+    Ok(())
+}
+
+/// Commits `transaction`, warning about binaries that couldn't be added to
+/// `$PATH`.
+pub(super) fn commit(transaction: Transaction) -> crate::Result {
+    for error in transaction.commit()? {
+        eprintln!("warning: not adding to PATH: {}", error);
+    }
+    Ok(())
+}
+
+/// Trims the cache to the configured maximum size by evicting the least
+/// recently used tarballs, which doesn't affect installed drops.
+pub(super) fn evict_cache(config: &Config, cache: &mut CacheIndex) {
+    let max_size = config.user.cache_max_size;
+    if max_size.0 == 0 {
+        return;
+    }
+    let result = cache
+        .evict(max_size.0)
+        .and_then(|evicted| cache.save().map(|()| evicted));
+    match result {
+        Ok(evicted) if !evicted.is_empty() => println!(
+            "Evicted {} tarball(s) to keep the cache under {}",
+            evicted.len(),
+            max_size,
+        ),
+        Ok(_) => {}
+        Err(error) => eprintln!("warning: failed to trim the cache: {}", error),
+    }
+}
+
+/// Creates root dependencies from queries, which default to any version.
+fn query_deps(queries: clap::Values<'_>) -> crate::Result<Deps> {
+    queries
+        .map(|query| {
+            let query: Query<&Name, VersionReq> = Query::parse(query)
+                .map_err(|error| failure::format_err!("{}", error))?;
+            let name = Query {
+                scope: query.scope.map(ToString::to_string),
+                name: query.name.to_string(),
+                version: None,
+            };
+            let version = query.version.unwrap_or_else(VersionReq::any);
+            Ok((name, DepInfo::from(version)))
+        })
+        .collect()
+}
+
+/// Returns the version requirement of `drop` if it is in `deps`.
+fn requirement(deps: &Deps, drop: &ScopedName) -> Option<VersionReq> {
+    deps.iter()
+        .find(|(query, _)| {
+            query.scope.as_deref().unwrap_or("core") == drop.scope
+                && query.name == drop.name
+        })
+        .map(|(_, info)| info.version.clone())
+}
+
 /// Converts `values` to a vector of `Name`s if they're all valid, or exits with
 /// an error code if any are not.
-fn name_values(values: clap::Values) -> Vec<&Name> {
+fn name_values(values: clap::Values<'_>) -> Vec<&Name> {
     values
         .map(Name::new)
         .collect::<Result<Vec<_>, _>>()
@@ -215,85 +835,96 @@ fn handle_conflicts(with: &[&Name], without: &[&Name]) {
 }
 
 struct Download {
-    #[allow(unused)]
     file: File,
-    path: PathBuf,
 }
 
-fn download(config: &Config, drop: Query<&str>) -> crate::Result<Download> {
-    let tarball_path = config.rt.tarball_cache_path(drop);
+/// Packages the drop in `dir` into a tarball at `tarball_path`, as if it were
+/// downloaded.
+fn package(tarball_path: &Path, dir: &Path) -> crate::Result<Download> {
+    let output_dir = tarball_path.parent().unwrap_or(tarball_path);
+    let package = Package::create(dir, None::<&Path>, Some(output_dir))?;
+    fs::rename(&package.path, tarball_path)?;
 
-    if let Some(parent) = tarball_path.parent() {
-        fs::DirBuilder::new().recursive(true).create(parent)?;
-    }
-
-    let mut tarball_file = fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .read(true)
-        .open(&tarball_path)?;
+    Ok(Download { file: package.file })
+}
 
-    {
-        let mut buf = BufWriter::new(&mut tarball_file);
-        api::v1::download_drop(drop, &mut buf)?;
-        buf.flush()?;
+/// Removes the cached tarball at `path`, which was expected to have the digest
+/// `checksum`.
+fn drop_tarball(cache: &mut CacheIndex, path: &Path, checksum: &str) {
+    let _ = fs::remove_file(path);
+    let file = path.file_name().and_then(|file| file.to_str());
+    let stale: Vec<String> = cache
+        .entries()
+        .filter(|(c, entry)| *c == checksum || Some(&*entry.file) == file)
+        .map(|(c, _)| c.to_owned())
+        .collect();
+    for checksum in stale {
+        cache.remove(&checksum);
     }
+}
 
-    Ok(Download {
-        file: tarball_file,
-        path: tarball_path,
-    })
+/// Opens the tarball at `path` from a previous download.
+fn cached(path: &Path, frozen: bool) -> crate::Result<Download> {
+    match File::open(path) {
+        Ok(file) => Ok(Download { file }),
+        Err(_) if frozen => failure::bail!(
+            "\"{}\" is not cached and `--frozen` prevents downloading it",
+            path.display(),
+        ),
+        Err(error) => {
+            failure::bail!("failed to open \"{}\": {}", path.display(), error)
+        }
+    }
 }
 
-// FIXME: Non-command unpacking fails.
-// fn unpack(tarball: &mut File, destination: &Path) -> crate::Result {
-//     oceanpkg::archive::unpack_tarball(tarball, destination)?;
-//     Ok(())
-// }
-
-fn unpack(tarball: &Path, dir: &Path) -> crate::Result<String> {
-    assert!(tarball.exists(), "{:?} does not exist", tarball);
-
-    fs::DirBuilder::new().recursive(true).create(dir)?;
-
-    // Runs `gunzip -c $download_path | tar xopf -`
-    let mut gunzip = Command::new("gunzip")
-        .arg("-c")
-        .arg(tarball)
-        .current_dir(dir)
-        .stdout(Stdio::piped())
-        .spawn()?;
-    let gunzip_stdout = gunzip.stdout.take().ok_or_else(|| {
-        failure::err_msg("Could not get stdout handle for `gunzip`")
-    })?;
-    let tar_status = Command::new("tar")
-        .args(&["xopf", "-"])
-        .current_dir(dir)
-        .stdin(gunzip_stdout)
-        .status()?;
-
-    failure::ensure!(tar_status.success(), "Failed to unpack download");
-
-    // Runs `gunzip -c $download_path | tar -t */Ocean.toml -`
-    let mut gunzip = Command::new("gunzip")
-        .arg("-c")
-        .arg(tarball)
-        .current_dir(dir)
-        .stdout(Stdio::piped())
-        .spawn()?;
-    let gunzip_stdout = gunzip.stdout.take().ok_or_else(|| {
-        failure::err_msg("Could not get stdout handle for `gunzip`")
-    })?;
-    let tar_output = Command::new("tar")
-        .args(&["-t", "*/Ocean.toml", "-"])
-        .current_dir(dir)
-        .stdin(gunzip_stdout)
-        .output()?;
-
-    let mut manifest_path = String::from_utf8(tar_output.stdout)?;
-    while manifest_path.ends_with(|c| c == '\n' || c == '\r') {
-        manifest_path.pop();
+#[cfg(test)]
+mod tests {
+    use super::super::{key, test_config};
+    use super::*;
+    use oceanpkg::{auth::SecretKey, drop::version::Version};
+    use url::Url;
+
+    fn run_key(config: &mut Config, args: &[&str]) {
+        let args = [key::NAME].iter().chain(args);
+        let matches = key::cmd().get_matches_from_safe(args).unwrap();
+        key::run(config, &matches).unwrap();
     }
 
-    Ok(manifest_path)
+    fn locked(source: Source) -> LockedDrop {
+        LockedDrop {
+            name: ScopedName::new("core", "wget"),
+            version: Version::parse("1.0.0"),
+            source,
+            checksum: Some("ab".repeat(32)),
+            dependencies: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn require_mode_rejects_unsigned_drops() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        let secret_key = SecretKey::generate();
+        let public_key = secret_key.public_key().to_string();
+        run_key(&mut config, &["trust", "core", &public_key]);
+
+        let drop = locked(Source::main_registry().clone());
+        let checksum = drop.checksum.as_ref().unwrap();
+        let signature = secret_key.sign(checksum.as_bytes());
+        let signed = vec![(drop.name.clone(), signature)].into_iter().collect();
+        let unsigned = BTreeMap::new();
+        let drops = [drop];
+
+        // Unsigned drops are only warned about by default.
+        assert!(verify_signatures(&config, &unsigned, &drops).is_ok());
+
+        run_key(&mut config, &["mode", "require"]);
+        assert!(verify_signatures(&config, &unsigned, &drops).is_err());
+        assert!(verify_signatures(&config, &signed, &drops).is_ok());
+
+        // Drops from other sources are never signed.
+        let url = Url::parse("https://example.com/wget.git").unwrap();
+        let git = [locked(Source::from_git(url))];
+        assert!(verify_signatures(&config, &unsigned, &git).is_ok());
+    }
 }
@@ -0,0 +1,32 @@
+use super::super::prelude::*;
+use oceanpkg::auth::Keyring;
+
+pub const NAME: &str = "generate";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Generates a key for signing shipped drops")
+        .arg(
+            Arg::with_name("name")
+                .help("The name of the key")
+                .default_value("default"),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let name = matches.value_of("name").unwrap_or("default");
+    let keyring = Keyring::new(config.rt.keys_dir());
+    let public_key = match keyring.generate(name) {
+        Ok(public_key) => public_key,
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+            failure::bail!("a key called \"{}\" already exists", name)
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    println!("Generated key \"{}\": {}", name, public_key);
+    println!();
+    println!("Installers can trust drops signed with it by running:");
+    println!("    ocean key trust <scope> {}", public_key);
+    Ok(())
+}
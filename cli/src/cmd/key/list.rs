@@ -0,0 +1,48 @@
+use super::super::prelude::*;
+use oceanpkg::auth::Keyring;
+
+pub const NAME: &str = "list";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Lists signing keys and the keys trusted for each scope")
+}
+
+pub fn run(config: &mut Config, _matches: &ArgMatches) -> crate::Result {
+    let keyring = Keyring::new(config.rt.keys_dir());
+    let keys = keyring.keys()?;
+    let trust = keyring.trust_store()?;
+
+    println!("Signing keys:");
+    if keys.is_empty() {
+        println!("  (none)");
+    }
+    for (name, public_key) in &keys {
+        println!("- {}: {}", name, public_key);
+    }
+
+    println!();
+    println!("Trusted keys:");
+    let mut scopes = trust.scopes().peekable();
+    if scopes.peek().is_none() {
+        println!("  (none)");
+    }
+    for (scope, keys) in scopes {
+        for public_key in keys {
+            println!("- {}: {}", scope, public_key);
+        }
+    }
+
+    let mut revoked = trust.revoked().peekable();
+    if revoked.peek().is_some() {
+        println!();
+        println!("Revoked keys:");
+        for public_key in revoked {
+            println!("- {}", public_key);
+        }
+    }
+
+    println!();
+    println!("Unverified drops: {}", trust.mode);
+    Ok(())
+}
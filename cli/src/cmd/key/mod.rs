@@ -0,0 +1,94 @@
+use super::prelude::*;
+
+mod generate;
+mod list;
+mod mode;
+mod revoke;
+mod trust;
+
+pub const NAME: &str = "key";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Manage keys for signing and verifying drops")
+        .settings(&[
+            AppSettings::SubcommandRequiredElseHelp,
+            AppSettings::DeriveDisplayOrder,
+        ])
+        .subcommands(vec![
+            generate::cmd(),
+            list::cmd(),
+            trust::cmd(),
+            revoke::cmd(),
+            mode::cmd(),
+        ])
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    if let (command, Some(matches)) = matches.subcommand() {
+        let run = match command {
+            generate::NAME => generate::run,
+            list::NAME => list::run,
+            trust::NAME => trust::run,
+            revoke::NAME => revoke::run,
+            mode::NAME => mode::run,
+            _ => unreachable!("could not match command {:?}", command),
+        };
+        run(config, matches)
+    } else {
+        // SubcommandRequiredElseHelp
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::test_config, *};
+    use oceanpkg::auth::{signing::VerifyMode, Keyring};
+
+    fn key(config: &mut Config, args: &[&str]) -> crate::Result {
+        let args = [NAME].iter().chain(args);
+        run(config, &cmd().get_matches_from_safe(args).unwrap())
+    }
+
+    #[test]
+    fn trust_and_revoke_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        let keyring = Keyring::new(config.rt.keys_dir());
+
+        key(&mut config, &["generate", "test"]).unwrap();
+        assert!(key(&mut config, &["generate", "test"]).is_err());
+        let public_key = keyring.keys().unwrap()[0].1;
+        let hex = public_key.to_string();
+
+        key(&mut config, &["trust", "core", &hex]).unwrap();
+        let trust = keyring.trust_store().unwrap();
+        assert_eq!(trust.keys("core").collect::<Vec<_>>(), [&public_key]);
+
+        key(&mut config, &["revoke", &hex]).unwrap();
+        let trust = keyring.trust_store().unwrap();
+        assert_eq!(trust.keys("core").count(), 0);
+        assert!(trust.is_revoked(&public_key));
+
+        // Revoked keys can't be trusted again.
+        assert!(key(&mut config, &["trust", "core", &hex]).is_err());
+        assert_eq!(keyring.trust_store().unwrap(), trust);
+    }
+
+    #[test]
+    fn mode_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        let keyring = Keyring::new(config.rt.keys_dir());
+        assert_eq!(keyring.trust_store().unwrap().mode, VerifyMode::Warn);
+
+        key(&mut config, &["mode", "require"]).unwrap();
+        assert_eq!(keyring.trust_store().unwrap().mode, VerifyMode::Require);
+        key(&mut config, &["mode"]).unwrap();
+        assert_eq!(keyring.trust_store().unwrap().mode, VerifyMode::Require);
+
+        key(&mut config, &["mode", "warn"]).unwrap();
+        assert_eq!(keyring.trust_store().unwrap().mode, VerifyMode::Warn);
+    }
+}
@@ -0,0 +1,36 @@
+use super::super::prelude::*;
+use oceanpkg::auth::{signing::VerifyMode, Keyring};
+
+pub const NAME: &str = "mode";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Sets what happens to drops without a trusted signature")
+        .long_about(
+            "Sets what happens to drops without a trusted signature: \
+             `warn` installs them with a warning and `require` refuses to \
+             install them",
+        )
+        .arg(
+            Arg::with_name("mode")
+                .help("Prints the current mode if not provided")
+                .possible_values(&["warn", "require"]),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let keyring = Keyring::new(config.rt.keys_dir());
+    let mut trust = keyring.trust_store()?;
+
+    let mode: VerifyMode = match matches.value_of("mode") {
+        Some(mode) => mode.parse().map_err(failure::err_msg)?,
+        None => {
+            println!("{}", trust.mode);
+            return Ok(());
+        }
+    };
+    trust.mode = mode;
+    keyring.save_trust_store(&trust)?;
+    println!("Unverified drops: {}", mode);
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use super::super::prelude::*;
+use oceanpkg::auth::{Keyring, PublicKey};
+
+pub const NAME: &str = "revoke";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Stops trusting a key for every scope")
+        .arg(
+            Arg::with_name("key")
+                .help("The public key, in hexadecimal")
+                .required(true),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let key: PublicKey = matches.value_of("key").unwrap_or_default().parse()?;
+
+    let keyring = Keyring::new(config.rt.keys_dir());
+    let mut trust = keyring.trust_store()?;
+    if trust.revoke(key) {
+        keyring.save_trust_store(&trust)?;
+        println!("Revoked {}", key);
+    } else {
+        println!("{} is already revoked", key);
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use super::super::prelude::*;
+use oceanpkg::auth::{Keyring, PublicKey};
+
+pub const NAME: &str = "trust";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Trusts a key to sign drops of a scope")
+        .arg(
+            Arg::with_name("scope")
+                .help("The scope whose drops the key signs")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("key")
+                .help("The public key, in hexadecimal")
+                .required(true),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let scope = matches.value_of("scope").unwrap_or_default();
+    let key: PublicKey = matches.value_of("key").unwrap_or_default().parse()?;
+
+    let keyring = Keyring::new(config.rt.keys_dir());
+    let mut trust = keyring.trust_store()?;
+    if trust.is_revoked(&key) {
+        failure::bail!("{} was revoked and can't be trusted again", key);
+    }
+    if trust.trust(scope, key) {
+        keyring.save_trust_store(&trust)?;
+        println!("Trusted {} for \"{}\"", key, scope);
+    } else {
+        println!("{} is already trusted for \"{}\"", key, scope);
+    }
+    Ok(())
+}
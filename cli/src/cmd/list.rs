@@ -1,4 +1,8 @@
-use super::prelude::*;
+use super::{dir_size, prelude::*};
+use oceanpkg::{
+    config::ByteSize,
+    install::{self, InstallReason, InstalledDrop, Receipts},
+};
 
 pub const NAME: &str = "list";
 
@@ -13,9 +17,236 @@ pub fn cmd() -> App {
             Arg::global_flag()
                 .help("List drops globally available to all users"),
         )
+        .arg(
+            Arg::with_name("pattern")
+                .help("Only list drops whose names match, e.g. `lib*`"),
+        )
+        .arg(
+            Arg::with_name("explicit")
+                .help("Only list drops that were explicitly installed")
+                .long("explicit")
+                .short("e"),
+        )
+        .arg(
+            Arg::with_name("deps")
+                .help("Only list drops that were installed as dependencies")
+                .long("deps")
+                .short("d")
+                .conflicts_with("explicit"),
+        )
+        .arg(
+            Arg::with_name("scope")
+                .help("Only list drops within a scope")
+                .long("scope")
+                .short("s")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("The output format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["table", "json", "plain"])
+                .default_value("table"),
+        )
 }
 
-pub fn run(_state: &mut Config, matches: &ArgMatches) -> crate::Result {
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
     let install_target = matches.install_target();
-    unimplemented!("TODO: List for {:?}", install_target);
+    let drops_dir = config.rt.drops_dir(&install_target)?;
+    let receipts = Receipts::new(config.rt.receipts_dir(&install_target)?);
+
+    let reason = if matches.is_present("explicit") {
+        Some(InstallReason::Explicit)
+    } else if matches.is_present("deps") {
+        Some(InstallReason::Dependency)
+    } else {
+        None
+    };
+    let scope = matches.value_of("scope");
+    let pattern = matches.value_of("pattern");
+
+    let mut listed = Vec::new();
+    for drop in install::installed_drops(&drops_dir)? {
+        if scope.is_some_and(|scope| scope != drop.name.scope) {
+            continue;
+        }
+        if let Some(pattern) = pattern {
+            // Patterns with a scope match against the full name.
+            let matched = if pattern.contains('/') {
+                glob_matches(pattern, &drop.name.to_string())
+            } else {
+                glob_matches(pattern, &drop.name.name)
+            };
+            if !matched {
+                continue;
+            }
+        }
+
+        let version = &drop.manifest.meta.version;
+        let drop_reason = receipts
+            .get(drop.name.to_ref(), version)?
+            .map(|receipt| receipt.reason);
+        if reason.is_some() && drop_reason != reason {
+            continue;
+        }
+
+        let size = dir_size(&drop.path)?;
+        listed.push(Listed {
+            drop,
+            reason: drop_reason,
+            size,
+        });
+    }
+
+    match matches.value_of("format") {
+        Some("json") => println!("{:#}", json(&listed)),
+        Some("plain") => {
+            for listed in &listed {
+                let meta = &listed.drop.manifest.meta;
+                println!("{}@{}", listed.drop.name, meta.version);
+            }
+        }
+        _ => print_table(&listed),
+    }
+    Ok(())
+}
+
+/// An installed drop that passed the filters.
+struct Listed {
+    drop: InstalledDrop,
+    reason: Option<InstallReason>,
+    size: u64,
+}
+
+impl Listed {
+    fn reason(&self) -> &'static str {
+        match self.reason {
+            Some(InstallReason::Explicit) => "explicit",
+            Some(InstallReason::Dependency) => "dependency",
+            None => "unknown",
+        }
+    }
+}
+
+fn print_table(listed: &[Listed]) {
+    if listed.is_empty() {
+        println!("No drops found");
+        return;
+    }
+
+    let header = ["SCOPE", "NAME", "VERSION", "KIND", "SIZE", "REASON"];
+    let rows: Vec<[String; 6]> = listed
+        .iter()
+        .map(|listed| {
+            let meta = &listed.drop.manifest.meta;
+            [
+                listed.drop.name.scope.clone(),
+                listed.drop.name.name.clone(),
+                meta.version.to_string(),
+                meta.kind.map_or("-", |kind| kind.as_str()).to_owned(),
+                ByteSize(listed.size).to_string(),
+                listed.reason().to_owned(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |row: &[&str]| {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i + 1 == row.len() {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:width$}  ", cell, width = width));
+            }
+        }
+        println!("{}", line);
+    };
+
+    print_row(&header);
+    for row in &rows {
+        let row: Vec<&str> = row.iter().map(String::as_str).collect();
+        print_row(&row);
+    }
+}
+
+fn json(listed: &[Listed]) -> serde_json::Value {
+    listed
+        .iter()
+        .map(|listed| {
+            let meta = &listed.drop.manifest.meta;
+            serde_json::json!({
+                "scope": listed.drop.name.scope,
+                "name": listed.drop.name.name,
+                "version": meta.version.to_string(),
+                "kind": meta.kind.map(|kind| kind.as_str()),
+                "size": listed.size,
+                "reason": listed.reason.map(|_| listed.reason()),
+            })
+        })
+        .collect()
+}
+
+/// Returns whether `text` matches `pattern`, where `*` matches any number of
+/// characters and `?` matches exactly one.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Backtrack to just after the last `*` whenever a match fails.
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches("wget", "wget"));
+        assert!(!glob_matches("wget", "wgets"));
+        assert!(!glob_matches("wget", "get"));
+
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "ocean"));
+        assert!(glob_matches("oc*", "ocean"));
+        assert!(glob_matches("*an", "ocean"));
+        assert!(glob_matches("o*e*n", "ocean"));
+        assert!(!glob_matches("*x*", "ocean"));
+
+        assert!(glob_matches("w?et", "wget"));
+        assert!(glob_matches("????", "wget"));
+        assert!(!glob_matches("?", ""));
+        assert!(!glob_matches("w?et", "wet"));
+        assert!(glob_matches("?*", "w"));
+    }
 }
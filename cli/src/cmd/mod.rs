@@ -1,10 +1,13 @@
 pub mod prelude;
 use prelude::{App, ArgMatches, Config};
+use std::{fs, io, path::Path};
 
+mod cache;
 mod config;
 mod docs;
 mod home;
 mod install;
+mod key;
 mod list;
 mod login;
 mod new;
@@ -16,8 +19,11 @@ mod self_; // `self` is a keyword
 mod ship;
 mod source;
 mod submit;
+mod tree;
 mod uninstall;
 mod update;
+#[path = "use.rs"]
+mod use_; // `use` is a keyword
 
 /// Returns all of Ocean's subcommands to pass into `App::subcommands`.
 pub fn all() -> Vec<App> {
@@ -30,7 +36,9 @@ pub fn all() -> Vec<App> {
         update::cmd(),
         run::cmd(),
         config::cmd(),
+        cache::cmd(),
         self_::cmd(),
+        key::cmd(),
         login::cmd(),
         ship::cmd(),
         package::cmd(),
@@ -38,6 +46,8 @@ pub fn all() -> Vec<App> {
         docs::cmd(),
         source::cmd(),
         submit::cmd(),
+        tree::cmd(),
+        use_::cmd(),
     ]
 }
 
@@ -56,7 +66,9 @@ pub fn builtin_run_fn(subcommand: &str) -> Option<RunFn> {
         update::NAME    => update::run,
         run::NAME       => run::run,
         config::NAME    => config::run,
+        cache::NAME     => cache::run,
         self_::NAME     => self_::run,
+        key::NAME       => key::run,
         login::NAME     => login::run,
         ship::NAME      => ship::run,
         package::NAME   => package::run,
@@ -64,6 +76,8 @@ pub fn builtin_run_fn(subcommand: &str) -> Option<RunFn> {
         docs::NAME      => docs::run,
         source::NAME    => source::run,
         submit::NAME    => submit::run,
+        tree::NAME      => tree::run,
+        use_::NAME      => use_::run,
         _               => return None,
     };
     Some(run)
@@ -83,3 +97,33 @@ pub fn builtin_alias(alias: &str) -> Option<&'static str> {
     };
     Some(alias)
 }
+
+/// Returns the total size of the files within `dir`, which is zero if it
+/// doesn't exist.
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(error) => return Err(error),
+    };
+    let mut size = 0;
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Returns a config whose Ocean home is `dir`, for running subcommands in
+/// tests.
+#[cfg(test)]
+fn test_config(dir: &Path) -> Config {
+    let config = Config::create().unwrap();
+    config.rt.ocean_home.fill(dir.to_owned()).unwrap();
+    config
+}
@@ -32,7 +32,7 @@ pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
     let tarball = &package.path;
     let tarball = match tarball.strip_prefix(config.rt.current_dir()) {
         Ok(suffix) => suffix,
-        Err(_) => &tarball,
+        Err(_) => tarball,
     };
 
     println!("Successfully packaged \"{}\"!", tarball.display());
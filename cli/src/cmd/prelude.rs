@@ -1,6 +1,8 @@
 pub use clap::{AppSettings, ArgMatches, SubCommand};
 pub use oceanpkg::{install::InstallTarget, Config};
 
+use oceanpkg::api::v1::DownloadScheduler;
+
 pub type App = clap::App<'static, 'static>;
 pub type Arg = clap::Arg<'static, 'static>;
 
@@ -9,6 +11,14 @@ pub trait ArgMatchesExt {
     /// Returns how drops get installed by checking whether a `"global"`
     /// argument was used.
     fn install_target(&self) -> InstallTarget;
+
+    /// Returns the number of parallel downloads given by a `"jobs"` argument,
+    /// or the default.
+    fn jobs(&self) -> crate::Result<usize>;
+
+    /// Returns whether drops should only come from the tarball cache, either
+    /// because an `"offline"` argument was used or because `config` says so.
+    fn offline(&self, config: &Config) -> bool;
 }
 
 impl ArgMatchesExt for ArgMatches<'_> {
@@ -21,6 +31,20 @@ impl ArgMatchesExt for ArgMatches<'_> {
             InstallTarget::CurrentUser
         }
     }
+
+    fn jobs(&self) -> crate::Result<usize> {
+        match self.value_of("jobs") {
+            Some(jobs) => match jobs.parse() {
+                Ok(jobs) if jobs > 0 => Ok(jobs),
+                _ => failure::bail!("invalid number of jobs: {:?}", jobs),
+            },
+            None => Ok(DownloadScheduler::DEFAULT_JOBS),
+        }
+    }
+
+    fn offline(&self, config: &Config) -> bool {
+        self.is_present("offline") || config.user.offline
+    }
 }
 
 pub trait ArgExt {
@@ -32,6 +56,13 @@ pub trait ArgExt {
 
     /// The common `--user`/`-u` flag that takes a username value.
     fn user_flag() -> Self;
+
+    /// The common `--jobs`/`-j` flag that takes the number of parallel
+    /// downloads.
+    fn jobs_flag() -> Self;
+
+    /// The common `--offline` flag.
+    fn offline_flag() -> Self;
 }
 
 impl ArgExt for clap::Arg<'_, '_> {
@@ -50,4 +81,16 @@ impl ArgExt for clap::Arg<'_, '_> {
             .takes_value(true)
             .number_of_values(1)
     }
+
+    fn jobs_flag() -> Self {
+        Arg::with_name("jobs")
+            .short("j")
+            .long("jobs")
+            .takes_value(true)
+            .value_name("N")
+    }
+
+    fn offline_flag() -> Self {
+        Arg::with_name("offline").long("offline")
+    }
 }
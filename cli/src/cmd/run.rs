@@ -1,7 +1,10 @@
 use super::prelude::*;
 use oceanpkg::{
-    drop::{name::Query, Manifest},
-    install::InstallTarget,
+    drop::{
+        name::{Query, ScopedName},
+        Manifest,
+    },
+    install::{self, InstallTarget},
 };
 use std::process::{exit, Command};
 
@@ -48,14 +51,21 @@ pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
 
         let scope = query.scope.unwrap_or("core");
         let name = query.name;
-        let version = query.version.ok_or_else(|| {
-            failure::err_msg("Provided query must include a version")
-        })?;
 
-        let query_string = format!("{}/{}@{}", scope, name, version);
+        let drops_dir = config.rt.drops_dir(&InstallTarget::CurrentUser)?;
 
-        let drops_dir = config.rt.drops_dir(&InstallTarget::CurrentUser);
-        let drop_path = drops_dir.join(&query_string);
+        // Without a version, run whichever version is active.
+        let (query_string, drop_path) = match query.version {
+            Some(version) => {
+                let query_string = format!("{}/{}@{}", scope, name, version);
+                let drop_path = drops_dir.join(&query_string);
+                (query_string, drop_path)
+            }
+            None => {
+                let drop = ScopedName::new(scope, name);
+                (drop.to_string(), install::current_path(&drops_dir, drop))
+            }
+        };
 
         let manifest_path = drop_path.join(Manifest::FILE_NAME);
         if !manifest_path.exists() {
@@ -1,5 +1,5 @@
 use super::prelude::*;
-use oceanpkg::auth::Credentials;
+use oceanpkg::auth::{Credentials, Keyring};
 use std::{
     fs::File,
     io::{self, Read},
@@ -23,15 +23,42 @@ pub fn cmd() -> App {
                 .long("manifest")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("key")
+                .help("Name of the key to sign with [default: default]")
+                .long("key")
+                .takes_value(true),
+        )
 }
 
 pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
-    let package = oceanpkg::drop::Package::create(
+    let mut package = oceanpkg::drop::Package::create(
         config.rt.current_dir(),
         matches.value_of_os("manifest"),
         None::<&Path>,
     )?;
 
+    let keyring = Keyring::new(config.rt.keys_dir());
+    match matches.value_of("key") {
+        Some(name) => match keyring.secret_key(name) {
+            Ok(key) => package.sign(&key),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                failure::bail!("no key called \"{}\" was generated", name)
+            }
+            Err(error) => return Err(error.into()),
+        },
+        None => match keyring.secret_key("default") {
+            Ok(key) => package.sign(&key),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                eprintln!(
+                    "warning: shipping an unsigned drop; run `ocean key \
+                     generate` to sign drops"
+                );
+            }
+            Err(error) => return Err(error.into()),
+        },
+    }
+
     let credentials: String;
     let token = match matches.value_of("token") {
         Some(token) => token,
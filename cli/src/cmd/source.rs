@@ -25,9 +25,8 @@ pub fn run(_state: &mut Config, matches: &ArgMatches) -> crate::Result {
     let print_repo = matches.is_present("print");
 
     if let Some(drops) = matches.values_of("drops") {
-        for drop in drops {
-            unimplemented!("TODO: Open the repository page for {:?}", drop);
-        }
+        let drops: Vec<&str> = drops.collect();
+        unimplemented!("TODO: Open the repository pages for {:?}", drops);
     } else if print_repo {
         println!("{}", OCEAN_REPO);
     } else {
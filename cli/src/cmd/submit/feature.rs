@@ -86,7 +86,7 @@ pub fn url(_config: &Config) -> String {
                 ";
     iter::once(base)
         .chain(percent_encoding::utf8_percent_encode(
-            &body,
+            body,
             percent_encoding::NON_ALPHANUMERIC,
         ))
         .collect()
@@ -0,0 +1,521 @@
+use super::prelude::*;
+use oceanpkg::{
+    api,
+    drop::{
+        name::{Query, ScopedName},
+        Lockfile, Manifest,
+    },
+    install,
+    resolve::{self, MemoryRegistry, Resolution},
+    system::Platform,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    path::PathBuf,
+};
+
+pub const NAME: &str = "tree";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Displays the dependency graph of a drop")
+        .arg(
+            Arg::user_flag()
+                .help("Use a drop locally available to a specific user"),
+        )
+        .arg(
+            Arg::global_flag()
+                .help("Use a drop globally available to all users"),
+        )
+        .arg(
+            Arg::with_name("drop")
+                .help("The installed drop to use instead of Ocean.toml"),
+        )
+        .arg(
+            Arg::with_name("manifest")
+                .help("Path to Ocean.toml")
+                .long("manifest")
+                .takes_value(true)
+                .conflicts_with("drop"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .help("The maximum number of levels to display")
+                .long("depth")
+                .takes_value(true)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("invert")
+                .help("Display the drops that depend on a drop")
+                .long("invert")
+                .short("i")
+                .takes_value(true)
+                .value_name("drop"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("The output format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["tree", "dot", "json"])
+                .default_value("tree"),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let depth = match matches.value_of("depth") {
+        Some(depth) => match depth.parse::<usize>() {
+            Ok(depth) => Some(depth),
+            Err(_) => failure::bail!("`--depth` must be a number: {}", depth),
+        },
+        None => None,
+    };
+
+    let (root, resolution) = match matches.value_of("drop") {
+        Some(drop) => {
+            resolve_installed(config, &matches.install_target(), drop)?
+        }
+        None => resolve_manifest(config, matches.value_of_os("manifest"))?,
+    };
+
+    let invert = match matches.value_of("invert") {
+        Some(drop) => {
+            let query = Query::<&str>::parse_liberal(drop);
+            let name = ScopedName::<String>::new(
+                query.scope.unwrap_or("core"),
+                query.name,
+            );
+            match resolution.drops.get_key_value(&name) {
+                Some((name, _)) => Some(name),
+                None => failure::bail!("\"{}\" is not in the graph", name),
+            }
+        }
+        None => None,
+    };
+
+    let graph = Graph::new(&root, &resolution, invert, depth);
+    match matches.value_of("format") {
+        Some("dot") => print!("{}", graph.dot()),
+        Some("json") => println!("{:#}", graph.json()),
+        _ => print!("{}", graph.tree()),
+    }
+    Ok(())
+}
+
+/// The platform that dependencies are resolved for.
+fn host_platform() -> Platform {
+    Platform::new(env!("OCEAN_TARGET_OS"), env!("OCEAN_TARGET_ARCH"))
+}
+
+/// Resolves the dependencies of the manifest at `path`, preferring the
+/// versions in its lockfile.
+fn resolve_manifest(
+    config: &Config,
+    path: Option<&OsStr>,
+) -> crate::Result<(String, Resolution)> {
+    let manifest_path = match path {
+        Some(path) => PathBuf::from(path),
+        None => config.rt.current_dir().join(Manifest::FILE_NAME),
+    };
+    let manifest = Manifest::read_toml_file(&manifest_path)?;
+    let root = manifest.meta.name;
+    let deps = manifest.deps.unwrap_or_default();
+
+    let lock_path = manifest_path.with_file_name(Lockfile::FILE_NAME);
+    let lockfile = if lock_path.exists() {
+        Some(Lockfile::read_toml_file(&lock_path)?)
+    } else {
+        None
+    };
+
+    let mut options = match &lockfile {
+        Some(lockfile) => resolve::Options::locked(lockfile),
+        None => resolve::Options::default(),
+    };
+    options.platform = Some(host_platform());
+
+    // An up-to-date lockfile is enough to resolve without network access.
+    if let Some(lockfile) = &lockfile {
+        let mut lockfile = lockfile.clone();
+        let resolution =
+            resolve::resolve_deps_with(&root, &deps, &mut lockfile, &options);
+        if let Ok(resolution) = resolution {
+            return Ok((root, resolution));
+        }
+    }
+
+    let base_dir = match manifest_path.parent() {
+        Some(dir) => dir.to_path_buf(),
+        None => config.rt.current_dir().to_path_buf(),
+    };
+    let registry = api::v1::Registry::new()?;
+    let mut registry =
+        resolve::SourceRegistry::new(registry, config.rt.cache_dir());
+    if let Some(lockfile) = &lockfile {
+        registry.set_locked(lockfile);
+    }
+    registry.add_deps(&deps, &base_dir)?;
+    let resolution =
+        resolve::resolve_deps_with(&root, &deps, &mut registry, &options)?;
+    Ok((root, resolution))
+}
+
+/// Resolves the dependencies of the installed `drop` against the other drops
+/// installed alongside it.
+fn resolve_installed(
+    config: &Config,
+    install_target: &InstallTarget,
+    drop: &str,
+) -> crate::Result<(String, Resolution)> {
+    let query = Query::<&str>::parse_liberal(drop);
+    let name =
+        ScopedName::<String>::new(query.scope.unwrap_or("core"), query.name);
+
+    let drops_dir = config.rt.drops_dir(install_target)?;
+    let installed = install::installed_manifests(&drops_dir)?;
+    let mut manifests = installed.get(&name).into_iter().flatten();
+    let current = install::current_version(&drops_dir, name.to_ref())?;
+    let manifest = match (query.version, current) {
+        (Some(version), _) => {
+            manifests.find(|manifest| manifest.meta.version == *version)
+        }
+        (None, Some(current)) => {
+            manifests.find(|manifest| manifest.meta.version == current)
+        }
+        (None, None) => manifests
+            .max_by(|a, b| a.meta.version.cmp_precedence(&b.meta.version)),
+    };
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => failure::bail!("\"{}\" is not installed", drop),
+    };
+
+    let root = format!("{}@{}", name, manifest.meta.version);
+    let deps = manifest.deps.clone().unwrap_or_default();
+
+    let mut registry = MemoryRegistry::new();
+    for (name, manifests) in &installed {
+        for manifest in manifests {
+            registry.insert(&name.scope, manifest.clone());
+        }
+    }
+
+    let options = resolve::Options {
+        platform: Some(host_platform()),
+        ..resolve::Options::default()
+    };
+    let resolution =
+        resolve::resolve_deps_with(&root, &deps, &mut registry, &options)?;
+    Ok((root, resolution))
+}
+
+/// A node in the graph, where `None` is the root.
+type Node<'a> = Option<&'a ScopedName>;
+
+/// The part of a resolved dependency graph that is displayed.
+struct Graph<'a> {
+    root: &'a str,
+    resolution: &'a Resolution,
+    /// Where traversal starts.
+    start: Node<'a>,
+    /// Whether edges point from drops to their dependents.
+    inverted: bool,
+    /// The edges to follow from each node.
+    edges: BTreeMap<Node<'a>, Vec<Node<'a>>>,
+    depth: Option<usize>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(
+        root: &'a str,
+        resolution: &'a Resolution,
+        invert: Option<&'a ScopedName>,
+        depth: Option<usize>,
+    ) -> Self {
+        let mut edges = BTreeMap::<Node, Vec<Node>>::new();
+        let root_deps = resolution.root.iter().map(|dep| (None, dep));
+        let drop_deps = resolution.drops.iter().flat_map(|(name, drop)| {
+            drop.deps.iter().map(move |dep| (Some(name), dep))
+        });
+        for (dependent, dep) in root_deps.chain(drop_deps) {
+            let dep = match resolution.drops.get_key_value(dep) {
+                Some((dep, _)) => Some(dep),
+                None => continue,
+            };
+            if invert.is_some() {
+                edges.entry(dep).or_default().push(dependent);
+            } else {
+                edges.entry(dependent).or_default().push(dep);
+            }
+        }
+        Self {
+            root,
+            resolution,
+            start: invert,
+            inverted: invert.is_some(),
+            edges,
+            depth,
+        }
+    }
+
+    fn label(&self, node: Node) -> String {
+        match node {
+            None => self.root.to_owned(),
+            Some(name) => match self.resolution.drops.get(name) {
+                Some(drop) => format!("{}@{}", name, drop.version),
+                None => name.to_string(),
+            },
+        }
+    }
+
+    fn edges(&self, node: Node<'a>) -> &[Node<'a>] {
+        self.edges.get(&node).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn is_within_depth(&self, level: usize) -> bool {
+        self.depth.is_none_or(|depth| level < depth)
+    }
+
+    /// Returns the nodes reachable within the maximum depth, in breadth-first
+    /// order, along with whether their edges are followed.
+    fn reachable(&self) -> Vec<(Node<'a>, bool)> {
+        let mut seen = BTreeSet::new();
+        seen.insert(self.start);
+        let mut nodes = vec![(self.start, self.is_within_depth(0))];
+        let mut level = vec![self.start];
+        let mut depth = 0;
+        while !level.is_empty() && self.is_within_depth(depth) {
+            depth += 1;
+            let next: Vec<Node> = level
+                .iter()
+                .flat_map(|&node| self.edges(node))
+                .copied()
+                .filter(|&node| seen.insert(node))
+                .collect();
+            let followed = self.is_within_depth(depth);
+            nodes.extend(next.iter().map(|&node| (node, followed)));
+            level = next;
+        }
+        nodes
+    }
+
+    /// Renders the graph as an indented tree where drops that were already
+    /// displayed are marked with `(*)` and not expanded again.
+    fn tree(&self) -> String {
+        let mut out = formatln!("{}", self.label(self.start));
+        let mut seen = BTreeSet::new();
+        seen.insert(self.start);
+        self.subtree(self.start, "", 1, &mut seen, &mut out);
+        out
+    }
+
+    fn subtree(
+        &self,
+        node: Node<'a>,
+        prefix: &str,
+        level: usize,
+        seen: &mut BTreeSet<Node<'a>>,
+        out: &mut String,
+    ) {
+        if !self.is_within_depth(level - 1) {
+            return;
+        }
+        let edges = self.edges(node);
+        for (i, &child) in edges.iter().enumerate() {
+            let last = i + 1 == edges.len();
+            let branch = if last { "└── " } else { "├── " };
+            let duplicate = !seen.insert(child);
+            let mark = if duplicate { " (*)" } else { "" };
+            out.push_str(&formatln!(
+                "{}{}{}{}",
+                prefix,
+                branch,
+                self.label(child),
+                mark,
+            ));
+            if !duplicate {
+                let indent = if last { "    " } else { "│   " };
+                let prefix = format!("{}{}", prefix, indent);
+                self.subtree(child, &prefix, level + 1, seen, out);
+            }
+        }
+    }
+
+    /// Renders the graph in the Graphviz DOT language, with edges pointing
+    /// from drops to their dependencies.
+    fn dot(&self) -> String {
+        let mut out = formatln!("digraph dependencies {{");
+        for (node, followed) in self.reachable() {
+            out.push_str(&formatln!("    {:?};", self.label(node)));
+            if !followed {
+                continue;
+            }
+            for &other in self.edges(node) {
+                let (from, to) = if self.inverted {
+                    (other, node)
+                } else {
+                    (node, other)
+                };
+                out.push_str(&formatln!(
+                    "    {:?} -> {:?};",
+                    self.label(from),
+                    self.label(to),
+                ));
+            }
+        }
+        out.push_str(&formatln!("}}"));
+        out
+    }
+
+    /// Renders the graph as JSON, listing each drop with the drops that it
+    /// depends on, or that depend on it if inverted.
+    fn json(&self) -> serde_json::Value {
+        let edges_key = if self.inverted {
+            "dependents"
+        } else {
+            "dependencies"
+        };
+        let drops: Vec<serde_json::Value> = self
+            .reachable()
+            .into_iter()
+            .map(|(node, followed)| {
+                let edges: &[Node] =
+                    if followed { self.edges(node) } else { &[] };
+                let name = match node {
+                    Some(name) => name.to_string(),
+                    None => self.root.to_owned(),
+                };
+                let version = node
+                    .and_then(|name| self.resolution.drops.get(name))
+                    .map(|drop| drop.version.to_string());
+                let edges: Vec<String> = edges
+                    .iter()
+                    .map(|edge| match edge {
+                        Some(name) => name.to_string(),
+                        None => self.root.to_owned(),
+                    })
+                    .collect();
+                let mut drop = serde_json::json!({
+                    "name": name,
+                    "version": version,
+                });
+                drop[edges_key] = edges.into();
+                drop
+            })
+            .collect();
+        serde_json::json!({
+            "root": self.label(self.start),
+            "drops": drops,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oceanpkg::{drop::version::Version, resolve::ResolvedDrop};
+
+    fn name(name: &str) -> ScopedName {
+        ScopedName::new("core", name)
+    }
+
+    /// `root -> {a, b}`, `a -> c`, `b -> c`, and `c -> d`.
+    fn resolution() -> Resolution {
+        let drop = |deps: &[&str]| ResolvedDrop {
+            version: Version::parse("1.0.0"),
+            deps: deps.iter().map(|dep| name(dep)).collect(),
+        };
+        Resolution {
+            root: vec![name("a"), name("b")],
+            drops: vec![
+                (name("a"), drop(&["c"])),
+                (name("b"), drop(&["c"])),
+                (name("c"), drop(&["d"])),
+                (name("d"), drop(&[])),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn tree_marks_repeated_subtrees() {
+        let resolution = resolution();
+        let graph = Graph::new("root", &resolution, None, None);
+        let expected = concat!(
+            "root\n",
+            "├── core/a@1.0.0\n",
+            "│   └── core/c@1.0.0\n",
+            "│       └── core/d@1.0.0\n",
+            "└── core/b@1.0.0\n",
+            "    └── core/c@1.0.0 (*)\n",
+        );
+        assert_eq!(graph.tree(), expected);
+    }
+
+    #[test]
+    fn tree_depth() {
+        let resolution = resolution();
+        let graph = Graph::new("root", &resolution, None, Some(1));
+        let expected =
+            concat!("root\n", "├── core/a@1.0.0\n", "└── core/b@1.0.0\n",);
+        assert_eq!(graph.tree(), expected);
+
+        let graph = Graph::new("root", &resolution, None, Some(0));
+        assert_eq!(graph.tree(), "root\n");
+    }
+
+    #[test]
+    fn tree_inverted() {
+        let resolution = resolution();
+        let d = name("d");
+        let graph = Graph::new("root", &resolution, Some(&d), None);
+        let expected = concat!(
+            "core/d@1.0.0\n",
+            "└── core/c@1.0.0\n",
+            "    ├── core/a@1.0.0\n",
+            "    │   └── root\n",
+            "    └── core/b@1.0.0\n",
+            "        └── root (*)\n",
+        );
+        assert_eq!(graph.tree(), expected);
+    }
+
+    #[test]
+    fn dot_depth() {
+        let resolution = resolution();
+        let graph = Graph::new("root", &resolution, None, Some(1));
+        let expected = concat!(
+            "digraph dependencies {\n",
+            "    \"root\";\n",
+            "    \"root\" -> \"core/a@1.0.0\";\n",
+            "    \"root\" -> \"core/b@1.0.0\";\n",
+            "    \"core/a@1.0.0\";\n",
+            "    \"core/b@1.0.0\";\n",
+            "}\n",
+        );
+        assert_eq!(graph.dot(), expected);
+    }
+
+    #[test]
+    fn json_lists_each_drop_once() {
+        let resolution = resolution();
+        let graph = Graph::new("root", &resolution, None, None);
+        let json = graph.json();
+        assert_eq!(json["root"], "root");
+        let names: Vec<&str> = json["drops"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|drop| drop["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["root", "core/a", "core/b", "core/c", "core/d"]);
+        assert_eq!(json["drops"][3]["version"], "1.0.0");
+        assert_eq!(
+            json["drops"][3]["dependencies"],
+            serde_json::json!(["core/d"])
+        );
+    }
+}
@@ -1,10 +1,18 @@
-use super::prelude::*;
+use super::{
+    install::{check_access, recover},
+    prelude::*,
+};
+use oceanpkg::{
+    drop::name::Query,
+    install::{self, Receipts, UninstallPlan},
+};
 
 pub const NAME: &str = "uninstall";
 
 pub fn cmd() -> App {
     SubCommand::with_name(NAME)
         .about("Removes a drop")
+        .arg(Arg::user_flag().help("Remove the drop for a specific user"))
         .arg(Arg::global_flag().help("Remove the drop for all users"))
         .arg(
             Arg::with_name("drop")
@@ -12,17 +20,121 @@ pub fn cmd() -> App {
                 .multiple(true)
                 .required(true),
         )
+        .arg(
+            Arg::with_name("force")
+                .help("Remove drops even if other drops depend on them")
+                .long("force")
+                .short("f"),
+        )
+        .arg(
+            Arg::with_name("autoremove")
+                .help("Also remove dependencies that are no longer needed")
+                .long("autoremove"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .help("Show what would be removed without removing anything")
+                .long("dry-run")
+                .short("n"),
+        )
 }
 
-pub fn run(_state: &mut Config, matches: &ArgMatches) -> crate::Result {
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
     let install_target = matches.install_target();
-    println!("Uninstalling for {:?}", install_target);
+    let dry_run = matches.is_present("dry-run");
+    if !dry_run {
+        check_access(config, &install_target)?;
+        recover(config, &install_target)?;
+    }
+
+    let drops_dir = config.rt.drops_dir(&install_target)?;
+    let receipts = Receipts::new(config.rt.receipts_dir(&install_target)?);
+
+    let queries: Vec<Query<&str>> = matches
+        .values_of("drop")
+        .into_iter()
+        .flatten()
+        .map(Query::parse_liberal)
+        .collect();
+
+    let mut installed = Vec::new();
+    for drop in install::installed_drops(&drops_dir)? {
+        let version = &drop.manifest.meta.version;
+        let receipt = receipts.get(drop.name.to_ref(), version)?;
+        installed.push((drop, receipt));
+    }
+
+    for query in &queries {
+        let found = installed.iter().any(|(drop, _)| is_match(query, drop));
+        if !found {
+            failure::bail!("\"{}\" is not installed", query);
+        }
+    }
+
+    let plan = UninstallPlan::new(
+        installed,
+        |drop| queries.iter().any(|query| is_match(query, drop)),
+        matches.is_present("autoremove"),
+    );
+
+    let force = matches.is_present("force");
+    for needed in &plan.needed {
+        let dependents: Vec<String> = needed
+            .dependents
+            .iter()
+            .map(|(name, version)| format!("{}@{}", name, version))
+            .collect();
+        let message = format!(
+            "\"{}@{}\" is needed by {}",
+            needed.name,
+            needed.version,
+            dependents.join(", "),
+        );
+        if force {
+            eprintln!("warning: {}", message);
+        } else {
+            error!("{}", message);
+        }
+    }
+    if plan.is_blocked() && !force {
+        failure::bail!("refusing to remove needed drops without `--force`");
+    }
+
+    if dry_run {
+        println!("Would remove:");
+    } else {
+        println!("Uninstalling for {:?}", install_target);
+    }
 
-    if let Some(values) = matches.values_of_os("drop") {
-        for value in values {
-            println!("Uninstalling {:?}...", value);
+    let bin_dir = config.rt.target_bin_dir(&install_target)?;
+    for removal in &plan.remove {
+        let drop = &removal.drop;
+        let label = format!("{}@{}", drop.name, drop.manifest.meta.version);
+        let orphaned = if removal.orphaned {
+            " (no longer needed)"
+        } else {
+            ""
+        };
+        if dry_run {
+            println!("- {}{}", label, orphaned);
+            continue;
         }
+        println!("Uninstalling \"{}\"{}...", label, orphaned);
+        removal.remove(&drops_dir, &bin_dir, &receipts)?;
     }
 
-    unimplemented!()
+    if !dry_run {
+        println!();
+        println!("Finished in {:?}", config.rt.time_elapsed());
+    }
+    Ok(())
+}
+
+/// Returns whether `drop` is what `query` refers to, where a missing scope
+/// means "core" and a missing version means every version.
+fn is_match(query: &Query<&str>, drop: &install::InstalledDrop) -> bool {
+    let version = &drop.manifest.meta.version;
+    drop.name.scope == query.scope.unwrap_or("core")
+        && drop.name.name == query.name
+        && query.version.is_none_or(|query| *version == *query)
 }
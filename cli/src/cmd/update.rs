@@ -1,4 +1,28 @@
-use super::prelude::*;
+use super::{
+    install::{
+        add_published_checksums, cached_signatures, check_access, commit,
+        download_drops, evict_cache, published_checksums, published_signatures,
+        recover, resolve_offline, stage_drop, use_cached_tarballs,
+        verify_signatures, StageOptions,
+    },
+    prelude::*,
+};
+use oceanpkg::{
+    api,
+    cache::CacheIndex,
+    drop::{
+        lock::LockedDrop,
+        manifest::{DepInfo, Deps},
+        name::{Query, ScopedName},
+        source::{Git, Kind},
+        version::{Version, VersionReq},
+        Source,
+    },
+    install::{self, InstallReason, Receipt, Receipts, Transaction},
+    resolve,
+    system::Platform,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 pub const NAME: &str = "update";
 
@@ -18,19 +42,282 @@ pub fn cmd() -> App {
                 .multiple(true)
                 .required_unless("all"),
         )
+        .arg(
+            Arg::offline_flag()
+                .help("Update only to drops that were already downloaded"),
+        )
+        .arg(Arg::jobs_flag().help("The number of drops to download at once"))
+        .arg(
+            Arg::with_name("dry-run")
+                .help("Show what would be updated without updating anything")
+                .long("dry-run")
+                .short("n"),
+        )
 }
 
-pub fn run(_state: &mut Config, matches: &ArgMatches) -> crate::Result {
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
     let install_target = matches.install_target();
+    let jobs = matches.jobs()?;
+    let offline = matches.offline(config);
     println!("Updating for {:?}", install_target);
+    check_access(config, &install_target)?;
+    recover(config, &install_target)?;
+
+    let drops_dir = config.rt.drops_dir(&install_target)?;
+    let receipts = Receipts::new(config.rt.receipts_dir(&install_target)?);
 
-    if let Some(values) = matches.values_of_os("drop") {
-        for value in values {
-            println!("Updating {:?}...", value);
+    // Drops are updated from their active version, or from their newest one
+    // if none is active.
+    let mut active = BTreeMap::<ScopedName, Receipt>::new();
+    for receipt in receipts.all()? {
+        let current =
+            install::current_version(&drops_dir, receipt.name.to_ref())?;
+        if current.as_ref() == Some(&receipt.version) {
+            active.insert(receipt.name.clone(), receipt);
+        } else {
+            active.entry(receipt.name.clone()).or_insert(receipt);
         }
-    } else if matches.is_present("all") {
-        println!("Updating all packages...")
     }
 
-    unimplemented!()
+    let selected: BTreeSet<&ScopedName> = match matches.values_of("drop") {
+        Some(drops) => {
+            let mut selected = BTreeSet::new();
+            for drop in drops {
+                let query = Query::<&str>::parse_liberal(drop);
+                if query.version.is_some() {
+                    failure::bail!(
+                        "drops are updated within their requirements, so a \
+                         version can't be given: {}",
+                        drop,
+                    );
+                }
+                let name = ScopedName::<String>::new(
+                    query.scope.unwrap_or("core"),
+                    query.name,
+                );
+                match active.get_key_value(&name) {
+                    Some((name, _)) => selected.insert(name),
+                    None => failure::bail!("\"{}\" is not installed", name),
+                };
+            }
+            selected
+        }
+        None => active.keys().collect(),
+    };
+
+    // Explicitly installed drops are the roots that everything else is
+    // needed by.
+    let deps: Deps = active
+        .values()
+        .filter(|receipt| receipt.is_explicit())
+        .map(|receipt| {
+            let query = Query {
+                scope: Some(receipt.name.scope.clone()),
+                name: receipt.name.name.clone(),
+                version: None,
+            };
+            (query, dep_info(receipt))
+        })
+        .collect();
+
+    println!("Resolving dependencies...");
+    let options = resolve::Options {
+        // Drops that weren't selected stay where they are if possible.
+        preferred: active
+            .iter()
+            .filter(|(name, _)| !selected.contains(name))
+            .map(|(name, receipt)| (name.clone(), receipt.version.clone()))
+            .collect(),
+        platform: Some(Platform::new(
+            env!("OCEAN_TARGET_OS"),
+            env!("OCEAN_TARGET_ARCH"),
+        )),
+        ..resolve::Options::default()
+    };
+    let mut cache = CacheIndex::open(config.rt.cache_dir())?;
+    let (resolution, published, signatures, sourced) = if offline {
+        let (resolution, sourced) = resolve_offline(
+            config,
+            &cache,
+            "installed drops",
+            &deps,
+            config.rt.current_dir(),
+            &options,
+            None,
+        )?;
+        (resolution, BTreeMap::new(), None, sourced)
+    } else {
+        let registry = api::v1::Registry::new()?;
+        let mut registry =
+            resolve::SourceRegistry::new(registry, config.rt.cache_dir());
+        registry.add_deps(&deps, config.rt.current_dir())?;
+        let resolution = resolve::resolve_deps_with(
+            "installed drops",
+            &deps,
+            &mut registry,
+            &options,
+        )?;
+        let published = published_checksums(registry.registry(), &resolution);
+        let signatures = published_signatures(registry.registry(), &resolution);
+        (
+            resolution,
+            published,
+            Some(signatures),
+            registry.into_sourced(),
+        )
+    };
+
+    let updates: Vec<_> = resolution
+        .drops
+        .iter()
+        .filter_map(|(name, drop)| {
+            let old = active.get(name);
+            match old {
+                Some(old) if old.version == drop.version => None,
+                _ => Some((name, drop, old)),
+            }
+        })
+        .collect();
+
+    if updates.is_empty() {
+        println!("All drops are up to date");
+        return Ok(());
+    }
+
+    if matches.is_present("dry-run") {
+        println!("Would update:");
+        for (name, drop, old) in &updates {
+            match old {
+                Some(old) => {
+                    println!("- {} ({} → {})", name, old.version, drop.version)
+                }
+                None => println!("- {} (new: {})", name, drop.version),
+            }
+        }
+        return Ok(());
+    }
+
+    let registry_source = Source::from_registry(api::url()?);
+    let mut locked_drops: Vec<LockedDrop> = updates
+        .iter()
+        .map(|(name, drop, _)| LockedDrop {
+            name: (*name).clone(),
+            version: drop.version.clone(),
+            source: match sourced.get(name) {
+                Some(sourced) => sourced.source.clone(),
+                None => registry_source.clone(),
+            },
+            checksum: None,
+            dependencies: drop.deps.clone(),
+        })
+        .collect();
+
+    add_published_checksums(&mut locked_drops, &published)?;
+    if offline {
+        let drops = locked_drops
+            .iter_mut()
+            .filter(|drop| !sourced.contains_key(&drop.name));
+        use_cached_tarballs(&cache, drops)?;
+    }
+    let signatures =
+        signatures.unwrap_or_else(|| cached_signatures(&cache, &locked_drops));
+    verify_signatures(config, &signatures, &locked_drops)?;
+    let failed_downloads = if offline {
+        HashSet::new()
+    } else {
+        let downloads = locked_drops
+            .iter()
+            .filter(|drop| !sourced.contains_key(&drop.name));
+        download_drops(config, &cache, jobs, downloads)?
+    };
+    let mut failed = !failed_downloads.is_empty();
+
+    let bin_dir = config.rt.target_bin_dir(&install_target)?;
+    let mut transaction = Transaction::begin(&drops_dir, bin_dir, receipts)?;
+
+    for (locked_drop, (name, drop, old)) in
+        locked_drops.iter_mut().zip(&updates)
+    {
+        if failed_downloads.contains(*name) {
+            continue;
+        }
+        println!("Fetching \"{}\" ({})...", name, drop.version);
+
+        let staged = stage_drop(
+            config,
+            &mut cache,
+            &mut transaction,
+            locked_drop,
+            StageOptions {
+                sourced: sourced.get(name),
+                frozen: false,
+                requirement: old.and_then(|old| old.requirement.clone()),
+                reason: old.map_or(InstallReason::Dependency, |old| old.reason),
+                signature: signatures.get(name).copied(),
+            },
+        );
+        if let Err(error) = staged {
+            error!("failed to update \"{}\": {}", name, error);
+            failed = true;
+        }
+    }
+
+    cache.save()?;
+    if failed {
+        failure::bail!("nothing was updated because of the errors above");
+    }
+
+    println!("Installing...");
+    commit(transaction)?;
+    evict_cache(config, &mut cache);
+
+    // Get duration immediately after updating finishes.
+    let elapsed = config.rt.time_elapsed();
+
+    println!();
+    println!("Successfully updated:");
+    for (name, drop, old) in updates {
+        match old {
+            Some(old) => {
+                println!("- {} ({} → {})", name, old.version, drop.version)
+            }
+            None => println!("- {} (new: {})", name, drop.version),
+        }
+    }
+
+    println!();
+    println!("Finished in {:?}", elapsed);
+    Ok(())
+}
+
+/// Returns how to fetch newer versions of the drop that `receipt` records.
+///
+/// Drops from git repositories and local directories are fetched from the
+/// same place again.
+fn dep_info(receipt: &Receipt) -> DepInfo {
+    // Custom versions can't be ranked, so they stay pinned unless the
+    // receipt says otherwise.
+    let requirement =
+        receipt
+            .requirement
+            .clone()
+            .unwrap_or_else(|| match receipt.version {
+                Version::Custom(_) => VersionReq::exact(&receipt.version),
+                _ => VersionReq::any(),
+            });
+    let mut info = DepInfo::from(requirement);
+    let url = receipt.source.url();
+    match receipt.source.kind() {
+        Kind::Registry => {}
+        Kind::Path => {
+            info.path = url
+                .to_file_path()
+                .ok()
+                .map(|path| path.display().to_string());
+        }
+        Kind::Git(reference) => {
+            info.git = Some(Git::new(url.as_str(), reference.clone()));
+        }
+    }
+    info
 }
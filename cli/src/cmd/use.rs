@@ -0,0 +1,87 @@
+use super::{
+    install::{check_access, recover},
+    prelude::*,
+};
+use oceanpkg::{
+    drop::{
+        name::{Query, ScopedName},
+        Manifest,
+    },
+    install::{self, LinkError, Receipts},
+};
+
+pub const NAME: &str = "use";
+
+pub fn cmd() -> App {
+    SubCommand::with_name(NAME)
+        .about("Switches the active version of an installed drop")
+        .arg(
+            Arg::user_flag()
+                .help("Use a drop locally available to a specific user"),
+        )
+        .arg(
+            Arg::global_flag()
+                .help("Use a drop globally available to all users"),
+        )
+        .arg(
+            Arg::with_name("drop")
+                .help("The drop version to use, e.g. `wget@1.20.3`")
+                .required(true),
+        )
+}
+
+pub fn run(config: &mut Config, matches: &ArgMatches) -> crate::Result {
+    let install_target = matches.install_target();
+    let drops_dir = config.rt.drops_dir(&install_target)?;
+    let receipts = Receipts::new(config.rt.receipts_dir(&install_target)?);
+
+    let drop = matches.value_of("drop").unwrap_or_default();
+    let query = Query::<&str>::parse_liberal(drop);
+    let version = match query.version {
+        Some(version) => version,
+        None => {
+            failure::bail!("a version must be given, e.g. `{}@1.0.0`", drop)
+        }
+    };
+    let name = ScopedName::new(query.scope.unwrap_or("core"), query.name);
+
+    check_access(config, &install_target)?;
+    recover(config, &install_target)?;
+
+    let drop_path =
+        drops_dir.join(format!("{}/{}@{}", name.scope, name.name, version));
+    let manifest_path = drop_path.join(Manifest::FILE_NAME);
+    if !manifest_path.is_file() {
+        failure::bail!("\"{}@{}\" is not installed", name, version);
+    }
+    let manifest = Manifest::read_toml_file(&manifest_path)?;
+
+    let previous = install::current_version(&drops_dir, name)?;
+    let activated = install::activate(
+        &drops_dir,
+        &config.rt.target_bin_dir(&install_target)?,
+        &receipts,
+        name,
+        &manifest,
+    );
+    let binaries = match activated {
+        Ok(binaries) => binaries,
+        Err(LinkError::Io(error)) => return Err(error.into()),
+        Err(error) => {
+            eprintln!("warning: not adding \"{}\" to PATH: {}", name, error);
+            Vec::new()
+        }
+    };
+    receipts.update(name, &manifest.meta.version, |receipt| {
+        receipt.binaries = binaries;
+    })?;
+
+    match previous {
+        Some(previous) if previous != manifest.meta.version => println!(
+            "Switched \"{}\" from {} to {}",
+            name, previous, manifest.meta.version,
+        ),
+        _ => println!("Using \"{}\" {}", name, manifest.meta.version),
+    }
+    Ok(())
+}
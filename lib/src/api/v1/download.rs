@@ -1,6 +1,12 @@
 use crate::{api, drop::name::Query};
 use std::io;
 
+/// Returns where the archive for a drop that matches `query` is requested
+/// from by [`download_drop`](fn.download_drop.html).
+pub fn drop_url(query: Query<&str>) -> Result<url::Url, url::ParseError> {
+    query.join_to_url(&api::url()?.join("/v1/")?)
+}
+
 /// Requests the archive for a drop that matches `query` from [`url`].
 ///
 /// [`url`]: fn.url.html
@@ -9,9 +9,15 @@ mod download;
 #[cfg(feature = "reqwest")]
 mod login;
 
+#[cfg(feature = "reqwest")]
+mod registry;
+
+#[cfg(feature = "reqwest")]
+mod scheduler;
+
 #[cfg(feature = "reqwest")]
 mod ship;
 
 #[cfg(feature = "reqwest")]
 #[doc(inline)]
-pub use self::{download::*, login::*, ship::*};
+pub use self::{download::*, login::*, registry::*, scheduler::*, ship::*};
@@ -0,0 +1,232 @@
+use super::DownloadError;
+use crate::{
+    api,
+    auth::Signature,
+    drop::{name::ScopedName, version::Version, Manifest},
+    resolve::{self, MemoryRegistry},
+};
+use serde::{de, Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A published version of a drop.
+#[derive(Clone, Debug)]
+pub struct Release {
+    /// The manifest of the version.
+    pub manifest: Manifest,
+    /// The hex-encoded SHA-256 digest of the version's tarball, if the
+    /// registry knows it.
+    pub checksum: Option<String>,
+    /// The publisher's signature of `checksum`, if the version was signed.
+    pub signature: Option<Signature>,
+}
+
+impl<'de> Deserialize<'de> for Release {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The digest and signature are stored alongside the fields of the
+        // manifest.
+        let mut value =
+            json::Map::<String, json::Value>::deserialize(deserializer)?;
+        let checksum = match value.remove("checksum") {
+            Some(json::Value::String(checksum)) => Some(checksum),
+            Some(json::Value::Null) | None => None,
+            Some(other) => {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other(&other.to_string()),
+                    &"a hex-encoded SHA-256 digest",
+                ));
+            }
+        };
+        let signature = match value.remove("signature") {
+            Some(json::Value::String(signature)) => {
+                Some(signature.parse().map_err(de::Error::custom)?)
+            }
+            Some(json::Value::Null) | None => None,
+            Some(other) => {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other(&other.to_string()),
+                    &"a hex-encoded ed25519 signature",
+                ));
+            }
+        };
+        let manifest = Manifest::deserialize(json::Value::Object(value))
+            .map_err(de::Error::custom)?;
+        Ok(Self {
+            manifest,
+            checksum,
+            signature,
+        })
+    }
+}
+
+/// Requests every published version of `drop` from [`url`].
+///
+/// [`url`]: ../fn.url.html
+pub fn request_releases(
+    drop: ScopedName<&str>,
+) -> Result<Vec<Release>, DownloadError> {
+    let url = api::url()?;
+    request_releases_at(&url, drop)
+}
+
+/// Requests every published version of `drop` from a base API URL.
+///
+/// This mainly exists so that we can also issue requests to testing and staging
+/// environments.
+pub fn request_releases_at(
+    api_url: &url::Url,
+    drop: ScopedName<&str>,
+) -> Result<Vec<Release>, DownloadError> {
+    let path = format!("/v1/u/{}/p/{}/versions", drop.scope, drop.name);
+    let url = api_url.join(&path)?;
+
+    let mut response = reqwest::Client::new().get(url.as_str()).send()?;
+
+    let status = response.status();
+    if status == http::StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    } else if !status.is_success() {
+        return Err(DownloadError::from(status));
+    }
+
+    Ok(response.json()?)
+}
+
+/// A [`Registry`] backed by Ocean's web API.
+///
+/// Responses are cached so that each drop is only requested once.
+///
+/// [`Registry`]: ../../resolve/trait.Registry.html
+#[derive(Debug)]
+pub struct Registry {
+    api_url: url::Url,
+    fetched: BTreeSet<ScopedName>,
+    cache: MemoryRegistry,
+    checksums: BTreeMap<(ScopedName, String), String>,
+    signatures: BTreeMap<(ScopedName, String), Signature>,
+}
+
+impl Registry {
+    /// Creates a registry that requests drops from [`url`].
+    ///
+    /// [`url`]: ../fn.url.html
+    pub fn new() -> Result<Self, url::ParseError> {
+        Ok(Self::at(api::url()?))
+    }
+
+    /// Creates a registry that requests drops from a base API URL.
+    pub fn at(api_url: url::Url) -> Self {
+        Self {
+            api_url,
+            fetched: BTreeSet::new(),
+            cache: MemoryRegistry::new(),
+            checksums: BTreeMap::new(),
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the digest that the registry published for the tarball of
+    /// `drop` at `version`, if it was requested during resolution.
+    pub fn checksum(
+        &self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Option<&str> {
+        self.checksums
+            .get(&(drop.to_owned(), version.to_string()))
+            .map(String::as_str)
+    }
+
+    /// Returns the publisher's signature of the digest of `drop` at
+    /// `version`, if it was requested during resolution.
+    pub fn signature(
+        &self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Option<&Signature> {
+        self.signatures.get(&(drop.to_owned(), version.to_string()))
+    }
+
+    fn fetch(&mut self, drop: ScopedName<&str>) -> Result<(), DownloadError> {
+        let owned = drop.to_owned();
+        if self.fetched.contains(&owned) {
+            return Ok(());
+        }
+        for release in request_releases_at(&self.api_url, drop)? {
+            let version = release.manifest.meta.version.to_string();
+            if let Some(checksum) = release.checksum {
+                let key = (owned.clone(), version.clone());
+                self.checksums.insert(key, checksum);
+            }
+            if let Some(signature) = release.signature {
+                self.signatures.insert((owned.clone(), version), signature);
+            }
+            self.cache.insert(drop.scope, release.manifest);
+        }
+        self.fetched.insert(owned);
+        Ok(())
+    }
+}
+
+impl resolve::Registry for Registry {
+    type Error = DownloadError;
+
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error> {
+        self.fetch(drop)?;
+        let versions = resolve::Registry::versions(&mut self.cache, drop);
+        Ok(versions.unwrap_or_default())
+    }
+
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error> {
+        self.fetch(drop)?;
+        resolve::Registry::manifest(&mut self.cache, drop, version)
+            .map_err(|_| DownloadError::Status(http::StatusCode::NOT_FOUND))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_checksum() {
+        let json = r#"[
+            {
+                "meta": { "name": "wget", "description": "", "version": "1.0.0" },
+                "checksum": "ba7816bf"
+            },
+            { "meta": { "name": "wget", "description": "", "version": "2.0.0" } }
+        ]"#;
+        let releases: Vec<Release> = json::from_str(json).unwrap();
+        assert_eq!(releases[0].manifest.meta.version, *"1.0.0");
+        assert_eq!(releases[0].checksum.as_deref(), Some("ba7816bf"));
+        assert_eq!(releases[1].checksum, None);
+
+        let json = r#"{ "meta": {}, "checksum": 42 }"#;
+        assert!(json::from_str::<Release>(json).is_err());
+    }
+
+    #[test]
+    fn release_signature() {
+        let signature = "ab".repeat(64);
+        let json = format!(
+            r#"{{ "meta": {{ "name": "wget", "description": "", "version": "1.0.0" }},
+                "checksum": "ba7816bf", "signature": "{}" }}"#,
+            signature,
+        );
+        let release: Release = json::from_str(&json).unwrap();
+        assert_eq!(release.signature, Some(signature.parse().unwrap()));
+
+        let json = r#"{ "meta": {}, "signature": "ab" }"#;
+        assert!(json::from_str::<Release>(json).is_err());
+    }
+}
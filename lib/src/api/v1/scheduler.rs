@@ -0,0 +1,467 @@
+use super::{drop_url, DownloadError};
+use crate::drop::name::Query;
+use reqwest::{
+    header::{CONTENT_RANGE, ETAG, IF_NONE_MATCH, IF_RANGE, RANGE},
+    StatusCode,
+};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+};
+use url::Url;
+
+/// Downloads drop tarballs in parallel, with at most a fixed number in flight
+/// at once.
+///
+/// Each tarball is written to a `.partial` file next to its destination and
+/// renamed into place once complete, so that an interrupted download never
+/// leaves a truncated tarball behind. An interrupted download is resumed with
+/// a `Range` request the next time, and a tarball that is already downloaded
+/// is revalidated with its `ETag` instead of being fetched again.
+///
+/// A destination that is already scheduled is only downloaded once.
+#[derive(Debug)]
+pub struct DownloadScheduler {
+    jobs: usize,
+    queue: Vec<(Url, PathBuf)>,
+}
+
+/// How a [`DownloadScheduler`] obtained a tarball.
+///
+/// [`DownloadScheduler`]: struct.DownloadScheduler.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Downloaded {
+    /// The whole tarball was fetched.
+    Full,
+    /// The rest of a previously interrupted download was fetched.
+    Resumed,
+    /// The tarball was already downloaded and is still up-to-date.
+    NotModified,
+}
+
+impl DownloadScheduler {
+    /// The number of downloads in flight at once if not otherwise configured.
+    pub const DEFAULT_JOBS: usize = 4;
+
+    /// Creates a scheduler that runs at most `jobs` downloads at once.
+    ///
+    /// A limit of 0 is treated as 1.
+    #[inline]
+    pub fn new(jobs: usize) -> Self {
+        Self {
+            jobs: jobs.max(1),
+            queue: Vec::new(),
+        }
+    }
+
+    /// Returns the maximum number of downloads in flight at once.
+    #[inline]
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Returns the number of scheduled downloads.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether no downloads are scheduled.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Schedules downloading `url` to `path`, returning `false` if `path` is
+    /// already scheduled.
+    pub fn add<P: Into<PathBuf>>(&mut self, url: Url, path: P) -> bool {
+        let path = path.into();
+        if self.queue.iter().any(|(_, scheduled)| *scheduled == path) {
+            return false;
+        }
+        self.queue.push((url, path));
+        true
+    }
+
+    /// Schedules downloading the drop that matches `query` from [`url`] to
+    /// `path`, returning `false` if `path` is already scheduled.
+    ///
+    /// [`url`]: ../fn.url.html
+    pub fn add_drop<P: Into<PathBuf>>(
+        &mut self,
+        query: Query<&str>,
+        path: P,
+    ) -> Result<bool, url::ParseError> {
+        Ok(self.add(drop_url(query)?, path))
+    }
+
+    /// Runs all scheduled downloads, returning the result for each
+    /// destination in the order they were scheduled.
+    pub fn run(self) -> Vec<(PathBuf, Result<Downloaded, DownloadError>)> {
+        let len = self.queue.len();
+        let workers = self.jobs.min(len);
+        let queue: Mutex<VecDeque<_>> =
+            Mutex::new(self.queue.into_iter().enumerate().collect());
+        let results = Mutex::new(Vec::with_capacity(len));
+        let client = reqwest::Client::new();
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let next = queue.lock().unwrap().pop_front();
+                    let (index, (url, path)) = match next {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    let result = download_to(&client, &url, &path);
+                    results.lock().unwrap().push((index, path, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|&(index, ..)| index);
+        results
+            .into_iter()
+            .map(|(_, path, result)| (path, result))
+            .collect()
+    }
+}
+
+/// Downloads `url` to `path`, resuming a partial download or revalidating a
+/// complete one if possible.
+fn download_to(
+    client: &reqwest::Client,
+    url: &Url,
+    path: &Path,
+) -> Result<Downloaded, DownloadError> {
+    if let Some(parent) = path.parent() {
+        fs::DirBuilder::new().recursive(true).create(parent)?;
+    }
+    let partial_path = partial_path(path);
+
+    // A partial download can only be resumed if it's known to be of the same
+    // version of the resource.
+    let partial_len = fs::metadata(&partial_path).map_or(0, |m| m.len());
+    let partial_etag = read_etag(&partial_path)?;
+    let cached_etag = read_etag(path)?.filter(|_| path.is_file());
+
+    let mut request = client.get(url.as_str());
+    let resume = match &partial_etag {
+        Some(etag) if partial_len > 0 => {
+            request = request
+                .header(RANGE, format!("bytes={}-", partial_len))
+                .header(IF_RANGE, etag.as_str());
+            true
+        }
+        _ => {
+            if let Some(etag) = &cached_etag {
+                request = request.header(IF_NONE_MATCH, etag.as_str());
+            }
+            false
+        }
+    };
+    let mut response = request.send()?;
+
+    let status = response.status();
+    let (file, downloaded) = match status {
+        StatusCode::NOT_MODIFIED if cached_etag.is_some() => {
+            remove_partial(&partial_path)?;
+            return Ok(Downloaded::NotModified);
+        }
+        StatusCode::PARTIAL_CONTENT
+            if resume
+                && content_range_start(&response) == Some(partial_len) =>
+        {
+            let file =
+                fs::OpenOptions::new().append(true).open(&partial_path)?;
+            (file, Downloaded::Resumed)
+        }
+        StatusCode::OK => {
+            let file = fs::File::create(&partial_path)?;
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok());
+            write_etag(&partial_path, etag)?;
+            (file, Downloaded::Full)
+        }
+        _ => {
+            // Start over next time, since the partial download may be what
+            // the server is rejecting.
+            remove_partial(&partial_path)?;
+            return Err(DownloadError::from(status));
+        }
+    };
+
+    // On failure, whatever was written is kept to be resumed later.
+    {
+        let mut writer = BufWriter::new(&file);
+        response.copy_to(&mut writer)?;
+        writer.flush()?;
+    }
+    file.sync_all()?;
+
+    fs::rename(&partial_path, path)?;
+    match fs::rename(etag_path(&partial_path), etag_path(path)) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            write_etag(path, None)?;
+        }
+        result => result?,
+    }
+    Ok(downloaded)
+}
+
+/// Returns where the first byte of a `206 Partial Content` response goes.
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = range.strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
+
+/// Returns the path that the download for `path` is written to until it is
+/// complete.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+/// Returns the path where the `ETag` of the file at `path` is stored.
+fn etag_path(path: &Path) -> PathBuf {
+    let mut etag = path.as_os_str().to_owned();
+    etag.push(".etag");
+    PathBuf::from(etag)
+}
+
+fn read_etag(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(etag_path(path)) {
+        Ok(etag) => Ok(Some(etag)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Stores `etag` for the file at `path`, or removes it if `None`.
+fn write_etag(path: &Path, etag: Option<&str>) -> io::Result<()> {
+    let etag_path = etag_path(path);
+    match etag {
+        Some(etag) => fs::write(etag_path, etag),
+        None => match fs::remove_file(etag_path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn remove_partial(partial_path: &Path) -> io::Result<()> {
+    match fs::remove_file(partial_path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            return Err(error);
+        }
+        _ => {}
+    }
+    write_etag(partial_path, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    const BODY: &[u8] = b"pretend this is a tarball of some drop";
+    const ETAG: &str = "\"v1\"";
+
+    /// A stand-in for the registry that serves `BODY` at every path.
+    #[derive(Default)]
+    struct Server {
+        /// The headers of each request received, with lowercase names.
+        requests: Mutex<Vec<Vec<(String, String)>>>,
+        /// Whether to cut off the next response halfway through its body.
+        truncate: AtomicBool,
+        delay: Duration,
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    impl Server {
+        fn start(self) -> (Arc<Self>, Url) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let server = Arc::new(self);
+            let handle = server.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let server = handle.clone();
+                    thread::spawn(move || server.respond(stream.unwrap()));
+                }
+            });
+            (server, Url::parse(&url).unwrap())
+        }
+
+        fn respond(&self, mut stream: TcpStream) {
+            let mut head = Vec::new();
+            let mut byte = [0];
+            while !head.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                head.push(byte[0]);
+            }
+            let headers: Vec<(String, String)> = String::from_utf8(head)
+                .unwrap()
+                .lines()
+                .skip(1)
+                .filter_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    Some((name.to_lowercase(), value.trim().to_owned()))
+                })
+                .collect();
+            let header = |name: &str| {
+                headers.iter().find(|(n, _)| n == name).map(|(_, v)| &**v)
+            };
+
+            let now = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(self.delay);
+
+            let range_start = header("range")
+                .filter(|_| header("if-range").is_none_or(|tag| tag == ETAG))
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.trim_end_matches('-').parse().ok());
+            let (status, extra, body) = if header("if-none-match") == Some(ETAG)
+            {
+                ("304 Not Modified", String::new(), &BODY[..0])
+            } else if let Some(start) = range_start {
+                let range = format!(
+                    "Content-Range: bytes {}-{}/{}\r\n",
+                    start,
+                    BODY.len() - 1,
+                    BODY.len(),
+                );
+                ("206 Partial Content", range, &BODY[start..])
+            } else {
+                ("200 OK", String::new(), BODY)
+            };
+            self.requests.lock().unwrap().push(headers);
+
+            let head = format!(
+                "HTTP/1.1 {}\r\nETag: {}\r\nContent-Length: {}\r\n{}\
+                 Connection: close\r\n\r\n",
+                status,
+                ETAG,
+                body.len(),
+                extra,
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            if self.truncate.swap(false, Ordering::SeqCst) {
+                stream.write_all(&body[..body.len() / 2]).unwrap();
+            } else {
+                stream.write_all(body).unwrap();
+            }
+            self.active.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        fn header(&self, request: usize, name: &str) -> Option<String> {
+            let requests = self.requests.lock().unwrap();
+            let (_, value) =
+                requests[request].iter().find(|(n, _)| n == name)?;
+            Some(value.clone())
+        }
+    }
+
+    fn download(url: &Url, path: &Path) -> Result<Downloaded, DownloadError> {
+        let mut scheduler = DownloadScheduler::new(1);
+        scheduler.add(url.clone(), path);
+        scheduler.run().pop().unwrap().1
+    }
+
+    #[test]
+    fn downloads_in_parallel_up_to_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, url) = Server {
+            delay: Duration::from_millis(50),
+            ..Server::default()
+        }
+        .start();
+
+        let mut scheduler = DownloadScheduler::new(3);
+        for i in 0..10 {
+            let url = url.join(&i.to_string()).unwrap();
+            assert!(scheduler.add(url, dir.path().join(i.to_string())));
+        }
+        let results = scheduler.run();
+
+        assert_eq!(results.len(), 10);
+        for (i, (path, result)) in results.into_iter().enumerate() {
+            assert_eq!(result.unwrap(), Downloaded::Full);
+            assert_eq!(path, dir.path().join(i.to_string()));
+            assert_eq!(fs::read(path).unwrap(), BODY);
+        }
+        let max_active = server.max_active.load(Ordering::SeqCst);
+        assert!(max_active > 1 && max_active <= 3, "{}", max_active);
+    }
+
+    #[test]
+    fn downloads_each_path_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wumbo.tar.gz");
+        let (server, url) = Server::default().start();
+
+        let mut scheduler = DownloadScheduler::new(4);
+        assert!(scheduler.add(url.clone(), &path));
+        assert!(!scheduler.add(url, &path));
+        assert_eq!(scheduler.len(), 1);
+
+        assert_eq!(scheduler.run().len(), 1);
+        assert_eq!(server.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn resumes_interrupted_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wumbo.tar.gz");
+        let (server, url) = Server::default().start();
+
+        server.truncate.store(true, Ordering::SeqCst);
+        assert!(download(&url, &path).is_err());
+        assert!(!path.exists());
+        let partial = fs::read(partial_path(&path)).unwrap();
+        assert_eq!(partial, &BODY[..BODY.len() / 2]);
+
+        assert_eq!(download(&url, &path).unwrap(), Downloaded::Resumed);
+        assert_eq!(fs::read(&path).unwrap(), BODY);
+        assert!(!partial_path(&path).exists());
+        let range = format!("bytes={}-", partial.len());
+        assert_eq!(server.header(1, "range"), Some(range));
+        assert_eq!(server.header(1, "if-range").as_deref(), Some(ETAG));
+    }
+
+    #[test]
+    fn revalidates_cached_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wumbo.tar.gz");
+        let (server, url) = Server::default().start();
+
+        assert_eq!(download(&url, &path).unwrap(), Downloaded::Full);
+        assert_eq!(server.header(0, "if-none-match"), None);
+
+        assert_eq!(download(&url, &path).unwrap(), Downloaded::NotModified);
+        assert_eq!(server.header(1, "if-none-match").as_deref(), Some(ETAG));
+        assert_eq!(fs::read(&path).unwrap(), BODY);
+
+        // Without a known `ETag`, the tarball is fetched again.
+        fs::remove_file(etag_path(&path)).unwrap();
+        assert_eq!(download(&url, &path).unwrap(), Downloaded::Full);
+    }
+}
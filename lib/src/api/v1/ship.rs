@@ -55,7 +55,13 @@ pub fn ship_at_specific<U: reqwest::IntoUrl>(
             mem::transmute::<&str, &'static str>(name)
         };
 
-        let form = Form::new().text("name", name).text("version", version);
+        let mut form = Form::new()
+            .text("name", name)
+            .text("version", version)
+            .text("checksum", package.checksum.clone());
+        if let Some(signature) = &package.signature {
+            form = form.text("signature", signature.to_string());
+        }
 
         // TODO: Replace with `Part::reader` when we figure out how to make that
         // work correctly.
@@ -1,18 +1,317 @@
 //! Archiving utilities.
 
+use crate::drop::Manifest;
 use flate2::read::GzDecoder;
-use std::{io, path::Path};
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
 
-/// Reads `tarball` as a `.tar.gz` file and unpacks it to `path`.
+/// A drop unpacked by [`unpack_tarball`](fn.unpack_tarball.html).
+#[derive(Clone, Debug)]
+pub struct Unpacked {
+    /// Where the drop's manifest was unpacked to.
+    pub manifest_path: PathBuf,
+    /// The drop's parsed manifest.
+    #[cfg(feature = "toml")]
+    pub manifest: Manifest,
+}
+
+/// Reads `tarball` as a `.tar.gz` file of a drop and unpacks it to `path`.
+///
+/// The archive is expected to have its manifest at `<dir>/Ocean.toml`, as
+/// created by [`Package::create`](../drop/struct.Package.html#method.create).
+///
+/// Entries with absolute paths or `..` components are rejected, as are
+/// symbolic links that point outside of `path`. File permissions, including
+/// exec bits, are restored from the entry headers.
 ///
 /// Because `GzDecoder` uses a buffered reader internally, this is appropriate
 /// to call on `File`s.
-pub fn unpack_tarball<R, P>(tarball: R, path: P) -> io::Result<()>
+pub fn unpack_tarball<R, P>(tarball: R, path: P) -> io::Result<Unpacked>
 where
     R: io::Read,
     P: AsRef<Path>,
 {
-    let decoder = GzDecoder::new(tarball);
-    println!("{:#?}", decoder.header());
-    tar::Archive::new(decoder).unpack(path)
+    let dst = path.as_ref();
+    fs::DirBuilder::new().recursive(true).create(dst)?;
+    let dst = fs::canonicalize(dst)?;
+
+    let mut manifest_path = None;
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        check_relative(&entry_path)?;
+
+        let kind = entry.header().entry_type();
+        if let Some(target) = entry.link_name()? {
+            if kind.is_symlink() {
+                check_symlink(&dst, &entry_path, &target)?;
+            } else if kind.is_hard_link() {
+                check_relative(&target)?;
+            }
+        }
+
+        if !entry.unpack_in(&dst)? {
+            return Err(invalid_data(format!(
+                "refusing to unpack \"{}\"",
+                entry_path.display(),
+            )));
+        }
+
+        let is_manifest = kind.is_file()
+            && entry_path.file_name() == Some(Manifest::FILE_NAME.as_ref())
+            && entry_path.components().count() == 2;
+        if is_manifest && manifest_path.is_none() {
+            manifest_path = Some(dst.join(entry_path));
+        }
+    }
+
+    let manifest_path = manifest_path.ok_or_else(|| {
+        invalid_data(format!("no {} found in archive", Manifest::FILE_NAME))
+    })?;
+    Ok(Unpacked {
+        #[cfg(feature = "toml")]
+        manifest: Manifest::read_toml_file(&manifest_path)?,
+        manifest_path,
+    })
+}
+
+/// Reads the manifest of the drop in `tarball` without unpacking it.
+///
+/// The manifest is expected where [`unpack_tarball`](fn.unpack_tarball.html)
+/// looks for it.
+#[cfg(feature = "toml")]
+pub fn read_manifest<R: io::Read>(tarball: R) -> io::Result<Manifest> {
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?;
+        let is_manifest = entry.header().entry_type().is_file()
+            && entry_path.file_name() == Some(Manifest::FILE_NAME.as_ref())
+            && entry_path.components().count() == 2;
+        if is_manifest {
+            let mut toml = String::new();
+            io::Read::read_to_string(&mut entry, &mut toml)?;
+            return Manifest::parse_toml(&toml)
+                .map_err(|error| invalid_data(error.to_string()));
+        }
+    }
+    Err(invalid_data(format!(
+        "no {} found in archive",
+        Manifest::FILE_NAME
+    )))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Ensures that `path` can only refer to a location within the directory that
+/// it's relative to.
+fn check_relative(path: &Path) -> io::Result<()> {
+    let is_safe = path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if is_safe {
+        Ok(())
+    } else {
+        Err(invalid_data(format!(
+            "archive contains unsafe path \"{}\"",
+            path.display(),
+        )))
+    }
+}
+
+/// Ensures that the symbolic link at `link`, relative to `dst`, points to
+/// `target` within `dst`.
+///
+/// `..` is only allowed at the start of `target`, so that links unpacked
+/// later can't change where it ends up.
+fn check_symlink(dst: &Path, link: &Path, target: &Path) -> io::Result<()> {
+    let escapes = || {
+        invalid_data(format!(
+            "archive contains link \"{}\" to outside location \"{}\"",
+            link.display(),
+            target.display(),
+        ))
+    };
+
+    // Find the real directory of the link by following links before it.
+    let mut resolved = dst.to_path_buf();
+    for component in link.parent().into_iter().flat_map(Path::components) {
+        if let Component::Normal(component) = component {
+            resolved.push(component);
+            if resolved.exists() {
+                resolved = fs::canonicalize(&resolved)?;
+            }
+        }
+    }
+
+    let mut components = target.components().peekable();
+    while components.peek() == Some(&Component::ParentDir) {
+        components.next();
+        resolved.pop();
+    }
+    if !resolved.starts_with(dst) {
+        return Err(escapes());
+    }
+
+    for component in components {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            _ => return Err(escapes()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use tar::EntryType;
+
+    const MANIFEST: &str = r#"
+        [meta]
+        name = "wumbo"
+        description = "Something silly"
+        version = "0.1.0"
+    "#;
+
+    /// An entry's path, kind, mode, and either contents or link target.
+    type Entry<'a> = (&'a str, EntryType, u32, &'a [u8]);
+
+    /// Creates a `.tar.gz` without the path validation done by `tar`.
+    fn tarball(entries: &[Entry]) -> Vec<u8> {
+        let gz = GzEncoder::new(Vec::new(), Compression::fast());
+        let mut builder = tar::Builder::new(gz);
+        for &(path, kind, mode, data) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..path.len()]
+                .copy_from_slice(path.as_bytes());
+            header.set_entry_type(kind);
+            header.set_mode(mode);
+            let data = if kind.is_symlink() || kind.is_hard_link() {
+                header.as_old_mut().linkname[..data.len()]
+                    .copy_from_slice(data);
+                &[][..]
+            } else {
+                data
+            };
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn unpack(entries: &[Entry], dst: &Path) -> io::Result<Unpacked> {
+        unpack_tarball(tarball(entries).as_slice(), dst)
+    }
+
+    #[test]
+    fn unpacks_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let dst = dir.path().join("drops");
+        let manifest = MANIFEST.as_bytes();
+        let unpacked = unpack(
+            &[
+                ("wumbo@0.1.0/bin/wumbo", EntryType::Regular, 0o755, b"hi"),
+                ("wumbo@0.1.0/README", EntryType::Regular, 0o644, b"..."),
+                (
+                    "wumbo@0.1.0/bin/README",
+                    EntryType::Symlink,
+                    0,
+                    b"../README",
+                ),
+                (
+                    "wumbo@0.1.0/Ocean.toml",
+                    EntryType::Regular,
+                    0o644,
+                    manifest,
+                ),
+            ],
+            &dst,
+        )
+        .unwrap();
+
+        let drop_dir = fs::canonicalize(&dst).unwrap().join("wumbo@0.1.0");
+        assert_eq!(unpacked.manifest_path, drop_dir.join("Ocean.toml"));
+        #[cfg(feature = "toml")]
+        assert_eq!(unpacked.manifest.meta.name, "wumbo");
+        let readme = fs::read(drop_dir.join("bin/README")).unwrap();
+        assert_eq!(readme, b"...");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = |path: &str| {
+                let metadata = fs::metadata(drop_dir.join(path)).unwrap();
+                metadata.permissions().mode() & 0o777
+            };
+            assert_eq!(mode("bin/wumbo"), 0o755);
+            assert_eq!(mode("README"), 0o644);
+        }
+    }
+
+    #[test]
+    fn requires_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries =
+            [("wumbo@0.1.0/wumbo", EntryType::Regular, 0o755, &[][..])];
+        let error = unpack(&entries, dir.path()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        #[cfg(feature = "toml")]
+        {
+            let error =
+                read_manifest(tarball(&entries).as_slice()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn reads_manifest() {
+        let manifest = MANIFEST.as_bytes();
+        let tarball = tarball(&[
+            ("wumbo@0.1.0/bin/wumbo", EntryType::Regular, 0o755, b"hi"),
+            (
+                "wumbo@0.1.0/Ocean.toml",
+                EntryType::Regular,
+                0o644,
+                manifest,
+            ),
+        ]);
+        let manifest = read_manifest(tarball.as_slice()).unwrap();
+        assert_eq!(manifest.meta.name, "wumbo");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_unsafe_paths() {
+        let cases: &[&[Entry]] = &[
+            &[("../evil", EntryType::Regular, 0o644, b"")],
+            &[("a/../../evil", EntryType::Regular, 0o644, b"")],
+            &[("/tmp/evil", EntryType::Regular, 0o644, b"")],
+            &[("a/evil", EntryType::Symlink, 0, b"../../evil")],
+            &[("a/evil", EntryType::Symlink, 0, b"/tmp/evil")],
+            &[("evil", EntryType::Symlink, 0, b"a/../../evil")],
+            &[("evil", EntryType::Link, 0, b"../evil")],
+            &[
+                ("a/b", EntryType::Symlink, 0, b"."),
+                ("a/b/evil", EntryType::Symlink, 0, b"../../evil"),
+            ],
+        ];
+        for entries in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let dst = dir.path().join("drops");
+            let error = unpack(entries, &dst).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", error);
+            assert!(!dir.path().join("evil").exists());
+        }
+    }
 }
@@ -0,0 +1,175 @@
+//! Storing secret keys and trusted public keys on disk.
+
+use super::signing::{PublicKey, SecretKey, TrustStore};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// A directory of secret keys and the [`TrustStore`](../signing/struct.TrustStore.html).
+///
+/// Each secret key is stored at `<dir>/<name>.key` and the trust store at
+/// `<dir>/trust.toml`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keyring {
+    dir: PathBuf,
+}
+
+impl Keyring {
+    const TRUST_FILE: &'static str = "trust.toml";
+
+    /// Uses the keys within `dir`, which need not exist yet.
+    #[inline]
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the directory containing the keys.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn key_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.key", name))
+    }
+
+    /// Generates and stores a secret key called `name`, returning its public
+    /// key.
+    ///
+    /// This fails if a key called `name` already exists.
+    pub fn generate(&self, name: &str) -> io::Result<PublicKey> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid key name {:?}", name),
+            ));
+        }
+        fs::DirBuilder::new().recursive(true).create(&self.dir)?;
+
+        let key = SecretKey::generate();
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(self.key_path(name))?;
+        let seed: String = key
+            .seed()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        writeln!(file, "{}", seed)?;
+        file.sync_all()?;
+        Ok(key.public_key())
+    }
+
+    /// Reads the secret key called `name`.
+    pub fn secret_key(&self, name: &str) -> io::Result<SecretKey> {
+        let path = self.key_path(name);
+        fs::read_to_string(&path)?.parse().map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid key \"{}\": {}", path.display(), error),
+            )
+        })
+    }
+
+    /// Returns the names and public keys of all secret keys, sorted by name.
+    pub fn keys(&self) -> io::Result<Vec<(String, PublicKey)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Vec::new());
+            }
+            Err(error) => return Err(error),
+        };
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "key") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|name| name.to_str()) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            let key = self.secret_key(&name)?.public_key();
+            keys.push((name, key));
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Reads the trust store, which is empty if it doesn't exist yet.
+    pub fn trust_store(&self) -> io::Result<TrustStore> {
+        let path = self.dir.join(Self::TRUST_FILE);
+        match fs::read_to_string(&path) {
+            Ok(toml) => toml::from_str(&toml).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid \"{}\": {}", path.display(), error),
+                )
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(TrustStore::default())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Writes `trust`, replacing the trust store atomically.
+    pub fn save_trust_store(&self, trust: &TrustStore) -> io::Result<()> {
+        let toml = toml::to_string(trust).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, error)
+        })?;
+        fs::DirBuilder::new().recursive(true).create(&self.dir)?;
+
+        let path = self.dir.join(Self::TRUST_FILE);
+        let tmp_path = self.dir.join(format!("{}.tmp", Self::TRUST_FILE));
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(toml.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(tmp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_and_trust() {
+        let dir = tempfile::tempdir().unwrap();
+        let keyring = Keyring::new(dir.path());
+        assert!(keyring.keys().unwrap().is_empty());
+
+        let public_key = keyring.generate("publisher").unwrap();
+        assert!(keyring.generate("publisher").is_err());
+        assert!(keyring.generate("../escape").is_err());
+        assert_eq!(keyring.keys().unwrap(), [("publisher".into(), public_key)]);
+
+        let key = keyring.secret_key("publisher").unwrap();
+        assert_eq!(key.public_key(), public_key);
+
+        let mut trust = keyring.trust_store().unwrap();
+        assert!(trust.trust("core", public_key));
+        keyring.save_trust_store(&trust).unwrap();
+
+        let trust = keyring.trust_store().unwrap();
+        let signature = key.sign(b"checksum");
+        assert_eq!(
+            trust.verify("core", b"checksum", Some(&signature)),
+            Ok(public_key),
+        );
+    }
+}
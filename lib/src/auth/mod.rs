@@ -1,6 +1,16 @@
 //! Authentication utilities.
 
 pub mod credentials;
+#[cfg(feature = "toml")]
+pub mod keyring;
+pub mod signing;
 
 #[doc(inline)]
-pub use self::credentials::Credentials;
+pub use self::{
+    credentials::Credentials,
+    signing::{PublicKey, SecretKey, Signature},
+};
+
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::keyring::Keyring;
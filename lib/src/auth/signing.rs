@@ -0,0 +1,355 @@
+//! Signing drops and verifying their signatures.
+//!
+//! Publishers sign the hex-encoded SHA-256 checksum of a drop's tarball with
+//! an [ed25519] key. Installers accept a drop if its signature was made by a
+//! key trusted for the drop's scope, so a registry mirror can't serve altered
+//! tarballs without being noticed.
+//!
+//! [ed25519]: https://ed25519.cr.yp.to
+
+use ed25519_compact as ed25519;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt,
+    str::FromStr,
+};
+
+/// A key that verifies signatures, written as 64 hexadecimal digits.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PublicKey([u8; 32]);
+
+/// A detached signature, written as 128 hexadecimal digits.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature([u8; 64]);
+
+/// A key that makes signatures.
+///
+/// Only the 32-byte seed that the key is derived from is stored.
+#[derive(Clone)]
+pub struct SecretKey(ed25519::KeyPair);
+
+/// A key that could not be parsed from hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseKeyError(());
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a key or signature in hexadecimal")
+    }
+}
+
+impl Error for ParseKeyError {}
+
+impl PublicKey {
+    /// Returns whether `signature` of `message` was made by the secret key
+    /// for `self`.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        let key = ed25519::PublicKey::new(self.0);
+        key.verify(message, &ed25519::Signature::new(signature.0))
+            .is_ok()
+    }
+}
+
+impl SecretKey {
+    /// Generates a new random key.
+    pub fn generate() -> Self {
+        Self(ed25519::KeyPair::from_seed(ed25519::Seed::generate()))
+    }
+
+    /// Returns the key that verifies signatures made by `self`.
+    #[inline]
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(*self.0.pk)
+    }
+
+    /// Returns the seed that `self` is derived from, which is what
+    /// [`from_str`](#method.from_str) parses in hexadecimal.
+    pub fn seed(&self) -> [u8; 32] {
+        *self.0.sk.seed()
+    }
+
+    /// Signs `message`.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(*self.0.sk.sign(message, None))
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SecretKey")
+            .field(&self.public_key())
+            .finish()
+    }
+}
+
+impl FromStr for SecretKey {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seed = ed25519::Seed::new(parse_hex(s)?);
+        Ok(Self(ed25519::KeyPair::from_seed(seed)))
+    }
+}
+
+macro_rules! impl_hex {
+    ($($t:ty),+) => { $(
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write_hex(f, &self.0)
+            }
+        }
+
+        impl fmt::Debug for $t {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}(\"{}\")", stringify!($t), self)
+            }
+        }
+
+        impl FromStr for $t {
+            type Err = ParseKeyError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_hex(s).map(Self)
+            }
+        }
+
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let hex = <&str>::deserialize(deserializer)?;
+                hex.parse().map_err(de::Error::custom)
+            }
+        }
+    )+ };
+}
+
+impl_hex!(PublicKey, Signature);
+
+fn write_hex(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+}
+
+fn parse_hex<const N: usize>(s: &str) -> Result<[u8; N], ParseKeyError> {
+    let s = s.trim();
+    if s.len() != N * 2 || !s.is_ascii() {
+        return Err(ParseKeyError(()));
+    }
+    let mut bytes = [0; N];
+    for (byte, digits) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+        let digits =
+            std::str::from_utf8(digits).map_err(|_| ParseKeyError(()))?;
+        *byte =
+            u8::from_str_radix(digits, 16).map_err(|_| ParseKeyError(()))?;
+    }
+    Ok(bytes)
+}
+
+/// What to do when a drop lacks a trusted signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyMode {
+    /// Warn and install the drop anyway.
+    Warn,
+    /// Refuse to install the drop.
+    Require,
+}
+
+impl Default for VerifyMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Warn
+    }
+}
+
+impl FromStr for VerifyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "require" => Ok(Self::Require),
+            _ => Err(format!("expected \"warn\" or \"require\", got {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for VerifyMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Warn => f.write_str("warn"),
+            Self::Require => f.write_str("require"),
+        }
+    }
+}
+
+/// A drop lacks a trusted signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The drop was published without a signature.
+    Unsigned,
+    /// No keys are trusted for the drop's scope.
+    NoTrustedKeys(String),
+    /// The signature wasn't made by any key trusted for the drop's scope.
+    Untrusted(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unsigned => write!(f, "the drop is not signed"),
+            Self::NoTrustedKeys(scope) => {
+                write!(f, "no keys are trusted for scope \"{}\"", scope)
+            }
+            Self::Untrusted(scope) => write!(
+                f,
+                "the signature was not made by a key trusted for scope \"{}\"",
+                scope,
+            ),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Which keys are trusted to sign drops of each scope.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustStore {
+    /// What to do when a drop lacks a trusted signature.
+    #[serde(default)]
+    pub mode: VerifyMode,
+    /// Keys that are never trusted again.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    revoked: BTreeSet<PublicKey>,
+    /// The keys trusted for each scope.
+    #[serde(default)]
+    scopes: BTreeMap<String, BTreeSet<PublicKey>>,
+}
+
+impl TrustStore {
+    /// Returns the keys trusted for `scope`.
+    pub fn keys(&self, scope: &str) -> impl Iterator<Item = &PublicKey> {
+        self.scopes.get(scope).into_iter().flatten()
+    }
+
+    /// Returns each scope with the keys trusted for it.
+    pub fn scopes(&self) -> impl Iterator<Item = (&str, &BTreeSet<PublicKey>)> {
+        self.scopes
+            .iter()
+            .map(|(scope, keys)| (scope.as_str(), keys))
+    }
+
+    /// Returns the revoked keys.
+    pub fn revoked(&self) -> impl Iterator<Item = &PublicKey> {
+        self.revoked.iter()
+    }
+
+    /// Returns whether `key` was revoked.
+    #[inline]
+    pub fn is_revoked(&self, key: &PublicKey) -> bool {
+        self.revoked.contains(key)
+    }
+
+    /// Trusts `key` to sign drops of `scope`, returning whether it wasn't
+    /// already.
+    ///
+    /// Revoked keys can't be trusted again.
+    pub fn trust(&mut self, scope: &str, key: PublicKey) -> bool {
+        if self.is_revoked(&key) {
+            return false;
+        }
+        self.scopes.entry(scope.to_owned()).or_default().insert(key)
+    }
+
+    /// Stops trusting `key` for any scope and never trusts it again,
+    /// returning whether it wasn't already revoked.
+    pub fn revoke(&mut self, key: PublicKey) -> bool {
+        for keys in self.scopes.values_mut() {
+            keys.remove(&key);
+        }
+        self.scopes.retain(|_, keys| !keys.is_empty());
+        self.revoked.insert(key)
+    }
+
+    /// Checks that `signature` of `message` was made by a key trusted for
+    /// `scope`, returning the key.
+    pub fn verify(
+        &self,
+        scope: &str,
+        message: &[u8],
+        signature: Option<&Signature>,
+    ) -> Result<PublicKey, VerifyError> {
+        let signature = signature.ok_or(VerifyError::Unsigned)?;
+        let mut keys = self.keys(scope).peekable();
+        if keys.peek().is_none() {
+            return Err(VerifyError::NoTrustedKeys(scope.to_owned()));
+        }
+        keys.find(|key| key.verify(message, signature))
+            .copied()
+            .ok_or_else(|| VerifyError::Untrusted(scope.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let key = SecretKey::generate();
+        let other = SecretKey::generate();
+        let signature = key.sign(b"checksum");
+
+        let public_key = key.public_key();
+        assert!(public_key.verify(b"checksum", &signature));
+        assert!(!public_key.verify(b"altered", &signature));
+        assert!(!other.public_key().verify(b"checksum", &signature));
+
+        let parsed: Signature = signature.to_string().parse().unwrap();
+        assert_eq!(parsed, signature);
+        let parsed: PublicKey = public_key.to_string().parse().unwrap();
+        assert_eq!(parsed, public_key);
+        assert!("abc".parse::<PublicKey>().is_err());
+    }
+
+    #[test]
+    fn trust_and_revoke() {
+        let key = SecretKey::generate();
+        let public_key = key.public_key();
+        let signature = key.sign(b"checksum");
+
+        let mut trust = TrustStore::default();
+        assert_eq!(
+            trust.verify("core", b"checksum", Some(&signature)),
+            Err(VerifyError::NoTrustedKeys("core".into())),
+        );
+        assert!(trust.trust("core", public_key));
+        assert!(!trust.trust("core", public_key));
+        assert_eq!(
+            trust.verify("core", b"checksum", Some(&signature)),
+            Ok(public_key),
+        );
+        assert_eq!(
+            trust.verify("core", b"altered", Some(&signature)),
+            Err(VerifyError::Untrusted("core".into())),
+        );
+        assert_eq!(
+            trust.verify("core", b"checksum", None),
+            Err(VerifyError::Unsigned),
+        );
+        assert!(trust.keys("other").next().is_none());
+
+        assert!(trust.revoke(public_key));
+        assert!(!trust.trust("core", public_key));
+        assert!(trust.verify("core", b"checksum", Some(&signature)).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+//! Tarballs downloaded for installing drops.
+
+use crate::{
+    archive,
+    auth::Signature,
+    drop::{name::ScopedName, version::Version, Manifest},
+    resolve::{MemoryRegistry, MissingManifest, Registry},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+/// An index of the tarballs within a cache directory, keyed by the
+/// hex-encoded SHA-256 digest of each tarball.
+///
+/// The index is stored at `<dir>/index.toml`. Lookups by digest find a
+/// tarball that is known to match a lockfile or the registry without
+/// downloading it again.
+/// Tarballs packaged from local sources aren't indexed, since they can differ
+/// from the registry's tarball of the same drop and version.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheIndex {
+    #[serde(skip)]
+    dir: PathBuf,
+    #[serde(default, rename = "tarballs")]
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/// A tarball recorded in a [`CacheIndex`](struct.CacheIndex.html).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheEntry {
+    /// The drop's name.
+    pub name: ScopedName,
+    /// The drop's version.
+    pub version: Version,
+    /// The name of the tarball within the cache directory.
+    pub file: String,
+    /// The size of the tarball in bytes.
+    pub size: u64,
+    /// When the tarball was last used, in seconds since the Unix epoch.
+    pub last_used: u64,
+    /// Where the tarball was downloaded from, so that it can be revalidated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    /// The registry's signature of the tarball's digest, so that it can be
+    /// verified again without contacting the registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl CacheIndex {
+    /// The name of the index file within a cache directory.
+    pub const FILE_NAME: &'static str = "index.toml";
+
+    /// Reads the index of `dir`, which is empty if it doesn't exist yet.
+    pub fn open<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+        let dir = dir.into();
+        let path = dir.join(Self::FILE_NAME);
+        let mut index = match fs::read_to_string(&path) {
+            Ok(toml) => toml::from_str(&toml).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid index \"{}\": {}", path.display(), error),
+                )
+            })?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Self::default()
+            }
+            Err(error) => return Err(error),
+        };
+        index.dir = dir;
+        Ok(index)
+    }
+
+    /// Returns the cache directory.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns where the tarball of `entry` is stored.
+    #[inline]
+    pub fn path(&self, entry: &CacheEntry) -> PathBuf {
+        self.dir.join(&entry.file)
+    }
+
+    /// Returns the tarball whose digest is `checksum`, if any.
+    #[inline]
+    pub fn get(&self, checksum: &str) -> Option<&CacheEntry> {
+        self.entries.get(checksum)
+    }
+
+    /// Returns the digest and entry of a tarball for `drop` at `version`, if
+    /// any.
+    pub fn find(
+        &self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Option<(&str, &CacheEntry)> {
+        self.entries().find(|(_, entry)| {
+            entry.name.scope == drop.scope
+                && entry.name.name == drop.name
+                && entry.version == *version
+        })
+    }
+
+    /// Returns the total size of all tarballs in bytes.
+    pub fn size(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+
+    /// Returns all tarballs with their digests.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &CacheEntry)> {
+        self.entries
+            .iter()
+            .map(|(checksum, entry)| (checksum.as_str(), entry))
+    }
+
+    /// Records `entry` under `checksum`.
+    ///
+    /// Since a file can only have one digest, entries for the same file under
+    /// other digests are removed.
+    pub fn insert(&mut self, checksum: String, entry: CacheEntry) {
+        self.entries.retain(|_, other| other.file != entry.file);
+        self.entries.insert(checksum, entry);
+    }
+
+    /// Removes the entry for `checksum`, returning it if it existed.
+    ///
+    /// The tarball itself is left in place.
+    #[inline]
+    pub fn remove(&mut self, checksum: &str) -> Option<CacheEntry> {
+        self.entries.remove(checksum)
+    }
+
+    /// Removes the entry for `checksum` along with its tarball, returning the
+    /// entry if it existed.
+    ///
+    /// Validators stored next to the tarball for revalidating it, such as
+    /// `<file>.etag`, are removed too.
+    pub fn delete(&mut self, checksum: &str) -> io::Result<Option<CacheEntry>> {
+        let entry = match self.entries.remove(checksum) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let path = self.path(&entry);
+        let etag = self.dir.join(format!("{}.etag", entry.file));
+        for path in [path, etag] {
+            match fs::remove_file(path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                    return Err(error);
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(entry))
+    }
+
+    /// Deletes the least recently used tarballs until the rest take up at
+    /// most `max_size` bytes, returning the deleted entries.
+    pub fn evict(&mut self, max_size: u64) -> io::Result<Vec<CacheEntry>> {
+        let mut lru: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(checksum, entry)| (entry.last_used, checksum.clone()))
+            .collect();
+        lru.sort();
+
+        let mut size = self.size();
+        let mut evicted = Vec::new();
+        for (_, checksum) in lru {
+            if size <= max_size {
+                break;
+            }
+            if let Some(entry) = self.delete(&checksum)? {
+                size -= entry.size;
+                evicted.push(entry);
+            }
+        }
+        Ok(evicted)
+    }
+
+    /// Marks the tarball for `checksum` as used now.
+    pub fn touch(&mut self, checksum: &str) {
+        if let Some(entry) = self.entries.get_mut(checksum) {
+            entry.last_used = now();
+        }
+    }
+
+    /// Writes the index, replacing the previous one atomically.
+    pub fn save(&self) -> io::Result<()> {
+        let toml = toml::to_string(self).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, error)
+        })?;
+        fs::DirBuilder::new().recursive(true).create(&self.dir)?;
+
+        let path = self.dir.join(Self::FILE_NAME);
+        let tmp_path = self.dir.join(format!("{}.tmp", Self::FILE_NAME));
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(toml.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(tmp_path, path)
+    }
+}
+
+impl CacheEntry {
+    /// Creates an entry for the tarball of `name` at `version` stored as
+    /// `file`, used now.
+    pub fn new(
+        name: ScopedName,
+        version: Version,
+        file: String,
+        size: u64,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            file,
+            size,
+            last_used: now(),
+            url: None,
+            signature: None,
+        }
+    }
+}
+
+/// A [`Registry`] of the drops whose tarballs are recorded in a
+/// [`CacheIndex`](struct.CacheIndex.html), for resolving without the network.
+///
+/// Drops that are requested without having any cached versions are
+/// remembered, so that a failed resolution can say what is missing.
+///
+/// [`Registry`]: ../resolve/trait.Registry.html
+#[derive(Clone, Debug, Default)]
+pub struct CacheRegistry {
+    drops: MemoryRegistry,
+    missing: BTreeSet<ScopedName>,
+}
+
+impl CacheRegistry {
+    /// Reads the manifest within each tarball of `index`.
+    ///
+    /// Entries whose tarball was deleted or doesn't contain the drop it was
+    /// recorded as are skipped.
+    pub fn new(index: &CacheIndex) -> io::Result<Self> {
+        let mut drops = MemoryRegistry::new();
+        for (_, entry) in index.entries() {
+            let path = index.path(entry);
+            let file = match fs::File::open(&path) {
+                Ok(file) => file,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            let manifest: Manifest =
+                archive::read_manifest(file).map_err(|error| {
+                    io::Error::new(
+                        error.kind(),
+                        format!("\"{}\": {}", path.display(), error),
+                    )
+                })?;
+            if manifest.meta.name == entry.name.name
+                && manifest.meta.version == entry.version
+            {
+                drops.insert(&entry.name.scope, manifest);
+            }
+        }
+        Ok(Self {
+            drops,
+            missing: BTreeSet::new(),
+        })
+    }
+
+    /// Returns the requested drops that have no cached versions.
+    pub fn missing(&self) -> impl Iterator<Item = &ScopedName> {
+        self.missing.iter()
+    }
+}
+
+impl Registry for CacheRegistry {
+    type Error = MissingManifest;
+
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error> {
+        let versions = self.drops.versions(drop)?;
+        if versions.is_empty() {
+            self.missing.insert(drop.to_owned());
+        }
+        Ok(versions)
+    }
+
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error> {
+        self.drops.manifest(drop, version)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::SecretKey;
+
+    fn entry(name: &str, version: &str) -> CacheEntry {
+        let name = ScopedName::parse(name).unwrap();
+        let file = format!("{}@{}.tar.gz", name.name, version);
+        CacheEntry::new(name, Version::parse(version), file, 42)
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = CacheIndex::open(dir.path()).unwrap();
+        assert_eq!(index.entries().count(), 0);
+
+        let signature = SecretKey::generate().sign(b"aaaa");
+        let mut signed = entry("core/wget", "1.20.3");
+        signed.signature = Some(signature);
+        index.insert("aaaa".to_owned(), signed);
+        index.insert("bbbb".to_owned(), entry("core/wget", "1.21.0"));
+        index.save().unwrap();
+
+        let mut index = CacheIndex::open(dir.path()).unwrap();
+        let wget = index.get("aaaa").unwrap();
+        assert_eq!(wget.version, *"1.20.3");
+        assert_eq!(wget.signature, Some(signature));
+        assert_eq!(index.get("bbbb").unwrap().signature, None);
+        assert_eq!(index.path(wget), dir.path().join("wget@1.20.3.tar.gz"));
+
+        let version = Version::parse("1.21.0");
+        let wget = ScopedName::parse("core/wget").unwrap();
+        let (checksum, _) = index.find(wget, &version).unwrap();
+        assert_eq!(checksum, "bbbb");
+
+        // Replacing the file replaces its digest.
+        index.insert("cccc".to_owned(), entry("core/wget", "1.21.0"));
+        assert!(index.get("bbbb").is_none());
+        assert!(index.remove("cccc").is_some());
+        assert_eq!(index.entries().count(), 1);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = CacheIndex::open(dir.path()).unwrap();
+        for (checksum, version, last_used) in [
+            ("aaaa", "1.0.0", 30),
+            ("bbbb", "2.0.0", 10),
+            ("cccc", "3.0.0", 20),
+        ] {
+            let mut entry = entry("core/wget", version);
+            entry.last_used = last_used;
+            fs::write(index.path(&entry), [0; 42]).unwrap();
+            index.insert(checksum.to_owned(), entry);
+        }
+        assert_eq!(index.size(), 126);
+
+        let evicted = index.evict(100).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].version, *"2.0.0");
+        assert!(!dir.path().join("wget@2.0.0.tar.gz").exists());
+        assert_eq!(index.size(), 84);
+
+        assert!(index.evict(84).unwrap().is_empty());
+        let evicted = index.evict(0).unwrap();
+        assert_eq!(evicted[0].version, *"3.0.0");
+        assert_eq!(index.entries().count(), 0);
+    }
+
+    #[test]
+    fn registry() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = CacheIndex::open(dir.path()).unwrap();
+
+        let entry = entry("core/wget", "1.20.3");
+        let manifest = br#"
+            [meta]
+            name = "wget"
+            description = "Retrieves files"
+            version = "1.20.3"
+        "#;
+        let file = fs::File::create(index.path(&entry)).unwrap();
+        let mut tar =
+            tar::Builder::new(GzEncoder::new(file, Compression::fast()));
+        let mut header = tar::Header::new_gnu();
+        header.set_path("wget@1.20.3/Ocean.toml").unwrap();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append(&header, &manifest[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+        index.insert("aaaa".to_owned(), entry);
+
+        // Entries whose tarball is gone are skipped.
+        index.insert("bbbb".to_owned(), self::entry("core/curl", "7.0.0"));
+
+        let mut registry = CacheRegistry::new(&index).unwrap();
+        let wget = ScopedName::parse("core/wget").unwrap();
+        let curl = ScopedName::parse("core/curl").unwrap();
+        let versions = registry.versions(wget).unwrap();
+        assert_eq!(versions, [Version::parse("1.20.3")]);
+        let manifest = registry.manifest(wget, &versions[0]).unwrap();
+        assert_eq!(manifest.meta.name, "wget");
+
+        assert!(registry.versions(curl).unwrap().is_empty());
+        let missing: Vec<_> = registry.missing().collect();
+        assert_eq!(missing, [&curl.to_owned()]);
+    }
+}
@@ -245,7 +245,7 @@ mod tests {
                 let lower = cfg_name.to_lowercase();
 
                 for cfg_name in &[lower, upper] {
-                    let cfg_path = dir.path().join(&cfg_name);
+                    let cfg_path = dir.path().join(cfg_name);
                     std::fs::File::create(&cfg_path).unwrap();
 
                     let cfg_file = ConfigFile::find(dir.path()).unwrap();
@@ -263,7 +263,7 @@ mod tests {
 
             let no_read = dir.path().join("no_read");
             std::fs::DirBuilder::new()
-                .mode(0) // no permissions
+                .mode(0o000) // no permissions
                 .create(&no_read)
                 .unwrap();
 
@@ -10,7 +10,7 @@ pub mod user;
 pub use self::{
     file::{ConfigFile, ConfigFileFmt},
     rt::RtConfig,
-    user::UserConfig,
+    user::{ByteSize, UserConfig},
 };
 
 /// Configuration values that are examined throughout the lifetime of a client
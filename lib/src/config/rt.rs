@@ -1,6 +1,9 @@
 //! Runtime configuration data.
 
-use crate::{drop::name::Query, install::InstallTarget};
+use crate::{
+    drop::name::Query,
+    install::{DirError, InstallTarget},
+};
 use lazycell::LazyCell;
 use std::{
     borrow::Cow,
@@ -52,8 +55,7 @@ impl RtConfig {
             current_dir: env::current_dir()
                 .map_err(CreateError::MissingCurrentDir)?,
             current_exe: LazyCell::new(),
-            user_home: dirs::home_dir()
-                .ok_or_else(|| CreateError::MissingUserHome)?,
+            user_home: dirs::home_dir().ok_or(CreateError::MissingUserHome)?,
             ocean_home: LazyCell::new(),
             path_var: LazyCell::new(),
         })
@@ -124,6 +126,11 @@ impl RtConfig {
         self.ocean_home().join("credentials.toml")
     }
 
+    /// Returns the directory where signing keys and trusted keys are stored.
+    pub fn keys_dir(&self) -> PathBuf {
+        self.ocean_home().join("keys")
+    }
+
     /// Returns the directory where binaries exposed via `$PATH` are stored.
     pub fn bin_dir(&self) -> PathBuf {
         #[cfg(unix)]
@@ -147,31 +154,73 @@ impl RtConfig {
         path
     }
 
-    /// Returns the directory where drops are installed.
-    pub fn drops_dir(&self, target: &InstallTarget) -> Cow<'static, Path> {
+    /// Returns the directory of tarballs packaged from local sources.
+    ///
+    /// These are kept apart from registry tarballs, which may have the same
+    /// name and version but different contents.
+    pub fn local_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("local")
+    }
+
+    /// Returns the path where a tarball for `query` that was packaged from a
+    /// local source should be cached.
+    pub fn local_tarball_cache_path(&self, query: Query<&str>) -> PathBuf {
+        let mut path = self.local_cache_dir();
+        path.push(query.tarball_name());
+        path
+    }
+
+    /// Returns the directory where drops, receipts, and binaries for `target`
+    /// are stored.
+    ///
+    /// This is [`ocean_home`](#method.ocean_home) for the current user.
+    pub fn target_home(
+        &self,
+        target: &InstallTarget,
+    ) -> Result<Cow<'_, Path>, DirError> {
         #[cfg(unix)]
         match target {
-            InstallTarget::CurrentUser => {
-                Cow::Owned(self.ocean_home().join("drops"))
-            }
-            InstallTarget::SpecificUser(username) => {
-                unimplemented!("TODO: Get base directory for {:?}", username);
-            }
-            InstallTarget::Global => {
-                // TODO+SUDO: Needs admin access to write to either. Should be
-                // in a separate process that runs based on user password input.
-                // Essentially the same UX as when shells try to run something
-                // prefixed with `sudo`
-                if cfg!(target_os = "macos") {
-                    Cow::Borrowed("/Library/Ocean/drops".as_ref())
-                } else {
-                    Cow::Borrowed("/usr/local/Ocean/drops".as_ref())
-                }
-            }
+            InstallTarget::CurrentUser => Ok(Cow::Borrowed(self.ocean_home())),
+            // TODO+SUDO: Needs admin access to write to global or other users'
+            // drops. Should be in a separate process that runs based on user
+            // password input. Essentially the same UX as when shells try to
+            // run something prefixed with `sudo`
+            _ => target.home_dir(),
         }
 
         #[cfg(windows)]
-        unimplemented!("TODO: Write & test on Windows :)");
+        match target {
+            InstallTarget::CurrentUser => Ok(Cow::Borrowed(self.ocean_home())),
+            _ => Err(DirError::Unsupported(target.clone())),
+        }
+    }
+
+    /// Returns the directory where drops are installed.
+    pub fn drops_dir(
+        &self,
+        target: &InstallTarget,
+    ) -> Result<PathBuf, DirError> {
+        self.target_home(target).map(|home| home.join("drops"))
+    }
+
+    /// Returns the directory where receipts for installed drops are stored.
+    ///
+    /// This is next to [`drops_dir`](#method.drops_dir).
+    pub fn receipts_dir(
+        &self,
+        target: &InstallTarget,
+    ) -> Result<PathBuf, DirError> {
+        self.target_home(target).map(|home| home.join("receipts"))
+    }
+
+    /// Returns the directory where binaries installed for `target` are linked.
+    ///
+    /// This is [`bin_dir`](#method.bin_dir) for the current user.
+    pub fn target_bin_dir(
+        &self,
+        target: &InstallTarget,
+    ) -> Result<PathBuf, DirError> {
+        self.target_home(target).map(|home| home.join("bin"))
     }
 }
 
@@ -1,9 +1,21 @@
 //! User configuration data.
 
-use std::{collections::HashMap, ffi::OsStr, time::Duration};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap, convert::TryFrom, ffi::OsStr, fmt, str::FromStr,
+    time::Duration,
+};
+
+#[cfg(feature = "toml")]
+use super::{ConfigFile, ConfigFileFmt};
+#[cfg(feature = "toml")]
+use std::{fs, io};
 
 /// Represents the configuration specific to the user.
+///
+/// Values missing from a configuration file take their defaults.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UserConfig {
     /// Whether to send logs to ensure correct behavior.
     pub send_logs: bool,
@@ -12,6 +24,13 @@ pub struct UserConfig {
     /// Aliases for CLI commands.
     #[serde(rename = "alias")]
     pub aliases: HashMap<String, Command>,
+    /// Whether to install drops only from the tarball cache instead of
+    /// downloading them.
+    pub offline: bool,
+    /// The size that the tarball cache is trimmed to after each install, by
+    /// evicting the least recently used tarballs. Zero disables eviction. The
+    /// default is 1 GiB.
+    pub cache_max_size: ByteSize,
 }
 
 impl Default for UserConfig {
@@ -31,6 +50,35 @@ impl UserConfig {
             send_logs: false,
             send_logs_rate: Duration::from_secs(WEEK_SECS),
             aliases: HashMap::new(),
+            offline: false,
+            cache_max_size: ByteSize(1 << 30),
+        }
+    }
+
+    /// Reads the configuration from `file`.
+    #[cfg(feature = "toml")]
+    pub fn read(file: &ConfigFile) -> io::Result<Self> {
+        let contents = fs::read_to_string(&file.path)?;
+        let invalid = |error: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid \"{}\": {}", file.path.display(), error),
+            )
+        };
+        match file.fmt {
+            ConfigFileFmt::Toml => {
+                toml::from_str(&contents).map_err(|e| invalid(e.to_string()))
+            }
+            ConfigFileFmt::Json => {
+                json::from_str(&contents).map_err(|e| invalid(e.to_string()))
+            }
+            ConfigFileFmt::Yaml => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "\"{}\": YAML configuration is not supported yet",
+                    file.path.display(),
+                ),
+            )),
         }
     }
 
@@ -61,6 +109,92 @@ impl UserConfig {
     }
 }
 
+/// A number of bytes, written in configuration files as either an integer or
+/// a string with a binary unit, such as `"500MiB"` or `"2G"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let invalid = || format!("invalid size {:?}", s);
+
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let shift = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 0,
+            "k" | "kb" | "kib" => 10,
+            "m" | "mb" | "mib" => 20,
+            "g" | "gb" | "gib" => 30,
+            "t" | "tb" | "tib" => 40,
+            _ => return Err(invalid()),
+        };
+        number
+            .checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit + 1 < UNITS.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{:.1} {}", size, UNITS[unit])
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number of bytes or a size such as \"500MiB\"")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<ByteSize, E> {
+                Ok(ByteSize(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<ByteSize, E> {
+                u64::try_from(v).map(ByteSize).map_err(|_| {
+                    E::invalid_value(de::Unexpected::Signed(v), &self)
+                })
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<ByteSize, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
 /// A CLI command.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -70,3 +204,45 @@ pub enum Command {
     /// The command and its arguments as a list.
     Parsed(Vec<String>),
 }
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocean.toml");
+        fs::write(&path, "offline = true\n\n[alias]\nup = \"update\"\n")
+            .unwrap();
+
+        let file = ConfigFile::find(dir.path()).unwrap();
+        let config = UserConfig::read(&file).unwrap();
+        assert!(config.offline);
+        assert_eq!(config.parse_alias("up"), Some(vec!["update"]));
+        assert_eq!(config.send_logs_rate, UserConfig::new().send_logs_rate);
+        assert_eq!(config.cache_max_size, ByteSize(1 << 30));
+
+        fs::write(&path, "cache_max_size = \"500 MiB\"\n").unwrap();
+        let config = UserConfig::read(&file).unwrap();
+        assert_eq!(config.cache_max_size, ByteSize(500 << 20));
+
+        fs::write(&path, "cache_max_size = 4096\n").unwrap();
+        let config = UserConfig::read(&file).unwrap();
+        assert_eq!(config.cache_max_size, ByteSize(4096));
+    }
+
+    #[test]
+    fn byte_size() {
+        assert_eq!("2G".parse(), Ok(ByteSize(2 << 30)));
+        assert_eq!("10kb".parse(), Ok(ByteSize(10 << 10)));
+        assert_eq!("7".parse(), Ok(ByteSize(7)));
+        assert!("1.5G".parse::<ByteSize>().is_err());
+        assert!("G".parse::<ByteSize>().is_err());
+
+        assert_eq!(ByteSize(512).to_string(), "512 B");
+        assert_eq!(ByteSize(1536).to_string(), "1.5 KiB");
+        assert_eq!(ByteSize(3 << 30).to_string(), "3.0 GiB");
+        assert_eq!(ByteSize(1 << 50).to_string(), "1024.0 TiB");
+    }
+}
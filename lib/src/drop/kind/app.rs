@@ -4,6 +4,7 @@ use crate::drop::Metadata;
 #[derive(Clone, Debug)]
 pub struct App {
     metadata: Metadata,
+    #[allow(unused)]
     file_name: String,
 }
 
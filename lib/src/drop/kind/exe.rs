@@ -13,6 +13,7 @@ pub struct Exe {
 
 impl Exe {
     /// Returns an executable matching `query`, installed for `target`.
+    #[allow(clippy::result_unit_err)] // TODO: Add an error type
     pub fn installed<S: AsRef<str>>(
         query: &Query<S>,
         target: &InstallTarget,
@@ -62,5 +63,6 @@ impl Exe {
 /// An error returned when the binary for an executable drop cannot be found.
 #[derive(Debug)]
 pub struct FindError<'a> {
+    #[allow(unused)]
     target: &'a InstallTarget,
 }
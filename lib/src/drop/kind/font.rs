@@ -4,6 +4,7 @@ use crate::drop::Metadata;
 #[derive(Clone, Debug)]
 pub struct Font {
     metadata: Metadata,
+    #[allow(unused)]
     file_name: String,
 }
 
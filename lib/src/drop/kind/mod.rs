@@ -1,5 +1,7 @@
 //! Drop kinds.
 
+use std::fmt;
+
 mod app;
 mod exe;
 mod font;
@@ -9,7 +11,10 @@ mod lib;
 pub use self::{app::App, exe::Exe, font::Font, lib::Lib};
 
 /// The type of package a drop can be.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+///
+/// This is written in lowercase, such as `kind = "exe"` in a manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Kind {
     /// Has a graphical interface.
     App,
@@ -20,3 +25,23 @@ pub enum Kind {
     /// A library of a given language.
     Lib,
 }
+
+impl fmt::Display for Kind {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl Kind {
+    /// Returns the lowercase name of the kind, e.g. `exe`.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::Exe => "exe",
+            Self::Font => "font",
+            Self::Lib => "lib",
+        }
+    }
+}
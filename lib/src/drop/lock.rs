@@ -0,0 +1,283 @@
+//! Exact versions of a resolved dependency graph.
+
+use crate::{
+    drop::{
+        manifest::{DepInfo, Manifest, Meta},
+        name::{Query, ScopedName},
+        version::{Version, VersionReq},
+        Source,
+    },
+    resolve::Registry,
+};
+use sha2::{Digest, Sha256};
+use std::{convert::Infallible, io};
+
+/// The contents of an `Ocean.lock` file.
+///
+/// This records every drop that was selected when resolving the dependencies
+/// of a manifest, so that later installs can reproduce the same selection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// The format version of the file.
+    pub version: u32,
+
+    /// The selected drops, sorted by name.
+    #[serde(rename = "drop")]
+    #[serde(default)]
+    pub drops: Vec<LockedDrop>,
+}
+
+/// A drop within a [`Lockfile`](struct.Lockfile.html).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedDrop {
+    /// The drop's name.
+    pub name: ScopedName,
+
+    /// The selected version.
+    pub version: Version,
+
+    /// Where the drop was fetched from.
+    pub source: Source,
+
+    /// The hex-encoded SHA-256 digest of the drop's tarball.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+
+    /// The drops that this drop directly depends on.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<ScopedName>,
+}
+
+impl Default for Lockfile {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Lockfile {
+    /// The name used for lockfiles.
+    pub const FILE_NAME: &'static str = "Ocean.lock";
+
+    /// The format version written by this library.
+    pub const VERSION: u32 = 1;
+
+    /// Creates a lockfile for `drops`, sorting them by name.
+    pub fn new(mut drops: Vec<LockedDrop>) -> Self {
+        drops.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            version: Self::VERSION,
+            drops,
+        }
+    }
+
+    /// Returns the entry for the drop named `name`.
+    pub fn get(&self, name: ScopedName<&str>) -> Option<&LockedDrop> {
+        self.drops.iter().find(|drop| drop.name == name)
+    }
+
+    /// Parses a lockfile from [TOML](https://en.wikipedia.org/wiki/TOML).
+    #[cfg(feature = "toml")]
+    pub fn parse_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::de::from_str(toml).map(|lock: Self| Self::new(lock.drops))
+    }
+
+    /// Parses a lockfile from a [TOML](https://en.wikipedia.org/wiki/TOML)
+    /// file at the given path.
+    #[cfg(feature = "toml")]
+    pub fn read_toml_file<T>(toml: T) -> Result<Self, std::io::Error>
+    where
+        T: AsRef<std::path::Path>,
+    {
+        use std::io::{Error, ErrorKind};
+
+        let toml = std::fs::read_to_string(toml)?;
+        Self::parse_toml(&toml)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))
+    }
+
+    /// Returns `self` as a TOML string.
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Writes `self` as TOML to the file at the given path.
+    #[cfg(feature = "toml")]
+    pub fn write_toml_file<T>(&self, path: T) -> Result<(), std::io::Error>
+    where
+        T: AsRef<std::path::Path>,
+    {
+        use std::io::{Error, ErrorKind};
+
+        let toml = self
+            .to_toml()
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+        std::fs::write(path, toml)
+    }
+}
+
+/// Returns the hex-encoded SHA-256 digest of the bytes in `reader`, as used
+/// by [`LockedDrop::checksum`].
+///
+/// [`LockedDrop::checksum`]: struct.LockedDrop.html#structfield.checksum
+pub fn checksum<R: io::Read>(mut reader: R) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A lockfile can be used as the only source of drops during resolution, which
+/// allows for checking that it satisfies a manifest without network access.
+///
+/// Each locked drop provides only its locked version, which depends on the
+/// exact locked versions of its dependencies.
+impl Registry for Lockfile {
+    type Error = Infallible;
+
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error> {
+        Ok(self
+            .get(drop)
+            .map(|d| d.version.clone())
+            .into_iter()
+            .collect())
+    }
+
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error> {
+        let deps = self
+            .get(drop)
+            .map(|locked| {
+                locked
+                    .dependencies
+                    .iter()
+                    .filter_map(|dep| self.get(dep.to_ref()))
+                    .map(|dep| {
+                        let query = Query {
+                            scope: Some(dep.name.scope.clone()),
+                            name: dep.name.name.clone(),
+                            version: None,
+                        };
+                        let req = VersionReq::exact(&dep.version);
+                        (query, DepInfo::from(req))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Manifest {
+            meta: Meta {
+                name: drop.name.to_owned(),
+                display_name: None,
+                description: String::new(),
+                exe_path: None,
+                kind: None,
+                version: version.clone(),
+                conflicts: None,
+                license: None,
+                authors: None,
+                readme: None,
+                changelog: None,
+                git: None,
+                homepage: None,
+                documentation: None,
+            },
+            deps: Some(deps),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        drop::source::git::Ref,
+        resolve::{self, ResolveError},
+    };
+    use url::Url;
+
+    fn locked(name: &str, version: &str, deps: &[&str]) -> LockedDrop {
+        LockedDrop {
+            name: ScopedName::parse(name).unwrap(),
+            version: Version::parse(version),
+            source: Source::main_registry().clone(),
+            checksum: None,
+            dependencies: deps
+                .iter()
+                .map(|dep| ScopedName::parse(*dep).unwrap())
+                .collect(),
+        }
+    }
+
+    fn deps(deps: &[(&str, &str)]) -> crate::drop::manifest::Deps {
+        deps.iter()
+            .map(|&(name, version)| {
+                let query = Query::<&str>::parse_liberal(name).to_owned();
+                (query, DepInfo::from(VersionReq::parse(version).unwrap()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksum_sha256() {
+        assert_eq!(
+            checksum(&b"abc"[..]).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        );
+    }
+
+    #[test]
+    fn source_round_trip() {
+        let url = Url::parse("https://github.com/oceanpkg/ocean").unwrap();
+        let sources = [
+            Source::main_registry().clone(),
+            Source::from_path(Url::parse("file:///src/tool/").unwrap()),
+            Source::from_git_at(url.clone(), Ref::rev("1a2b3c")),
+            Source::from_git_at(url.clone(), Ref::branch("main")),
+            Source::from_git_at(url, Ref::tag("v1.0.0")),
+        ];
+        for source in &sources {
+            let parsed: Source = source.to_string().parse().unwrap();
+            assert_eq!(&parsed, source);
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip() {
+        let mut wget = locked("core/wget", "1.20.3", &["core/openssl"]);
+        wget.checksum = Some("ba7816bf".to_owned());
+        let lockfile =
+            Lockfile::new(vec![wget, locked("core/openssl", "1.1.1", &[])]);
+
+        let toml = lockfile.to_toml().unwrap();
+        assert_eq!(Lockfile::parse_toml(&toml).unwrap(), lockfile);
+        assert_eq!(lockfile.drops[0].name, "core/openssl");
+    }
+
+    #[test]
+    fn resolves_from_lockfile() {
+        let mut lockfile = Lockfile::new(vec![
+            locked("core/wget", "1.20.3", &["core/openssl"]),
+            locked("core/openssl", "1.1.1", &[]),
+        ]);
+
+        let root = deps(&[("wget", "^1.20")]);
+        let resolution =
+            resolve::resolve_deps("root", &root, &mut lockfile).unwrap();
+        assert_eq!(resolution.drops.len(), 2);
+
+        let root = deps(&[("wget", "^2")]);
+        match resolve::resolve_deps("root", &root, &mut lockfile) {
+            Err(ResolveError::NoSolution(_)) => {}
+            result => panic!("expected no solution, got {:?}", result),
+        }
+    }
+}
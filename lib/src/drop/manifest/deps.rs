@@ -1,4 +1,8 @@
-use crate::drop::{name::Query, source::Git};
+use crate::{
+    drop::{name::Query, source::Git, version::VersionReq},
+    system::Platform,
+};
+use serde::{de, Deserialize, Deserializer};
 use std::collections::BTreeMap;
 
 /// A mapping from drop names to dependency specification information.
@@ -16,13 +20,36 @@ flexible! {
     /// while retaining flexibility in parsing.
     #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
     pub struct DepInfo {
-        /// The version requirement string, e.g. `^1.0.0`.
-        pub version: String,
+        /// The version requirement, e.g. `^1.0.0`.
+        pub version: VersionReq,
 
         /// Whether the dependency is optional. The default is `false`.
         #[serde(default)]
         pub optional: bool,
 
+        /// Whether the dependency is installed unless it is explicitly
+        /// excluded. The default is `false`.
+        #[serde(default)]
+        pub recommended: bool,
+
+        /// The directory containing the dependency's manifest, relative to
+        /// the directory of the manifest that declares it. When provided,
+        /// this is used instead of a registry or `git`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub path: Option<String>,
+
+        /// The operating systems on which the dependency is needed, e.g.
+        /// `["linux", "macos"]`. If empty, it is needed on all of them.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub os: Vec<String>,
+
+        /// The CPU architectures on which the dependency is needed, e.g.
+        /// `["x86_64"]`. If empty, it is needed on all of them.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub arch: Vec<String>,
+
         // Tables: all types that serialize into maps (or "tables" in TOML)
         // them must be placed last to succeed.
 
@@ -34,12 +61,62 @@ flexible! {
     }
 }
 
-impl From<String> for DepInfo {
-    fn from(version: String) -> Self {
+impl From<VersionReq> for DepInfo {
+    fn from(version: VersionReq) -> Self {
         Self {
             version,
             git: None,
             optional: false,
+            recommended: false,
+            path: None,
+            os: Vec::new(),
+            arch: Vec::new(),
         }
     }
 }
+
+impl DepInfo {
+    /// Returns whether the dependency is needed on `platform`.
+    #[inline]
+    pub fn is_needed_on(&self, platform: &Platform) -> bool {
+        platform.matches_os(&self.os) && platform.matches_arch(&self.arch)
+    }
+}
+
+/// Deserializes the `dependencies` of a manifest.
+///
+/// Unlike deserializing `Deps` directly, errors name the offending dependency
+/// and invalid version requirements are reported as such.
+pub(super) fn deserialize<'de, D>(de: D) -> Result<Option<Deps>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use json::Value;
+
+    let raw = BTreeMap::<Query, Value>::deserialize(de)?;
+    let mut deps = Deps::new();
+
+    for (query, value) in raw {
+        let version = match &value {
+            Value::String(version) => Some(version.as_str()),
+            Value::Object(info) => info.get("version").and_then(Value::as_str),
+            _ => None,
+        };
+        if let Some(Err(error)) = version.map(VersionReq::parse) {
+            return Err(de::Error::custom(format_args!(
+                "invalid version requirement for dependency \"{}\": {}",
+                query, error,
+            )));
+        }
+
+        let info = json::from_value(value).map_err(|error| {
+            de::Error::custom(format_args!(
+                "invalid dependency \"{}\": {}",
+                query, error,
+            ))
+        })?;
+        deps.insert(query, info);
+    }
+
+    Ok(Some(deps))
+}
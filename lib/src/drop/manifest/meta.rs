@@ -1,4 +1,4 @@
-use crate::drop::{source::Git, version::SemVer};
+use crate::drop::{source::Git, version::Version, Kind};
 use std::collections::BTreeMap;
 
 /// The value for the `meta` key in the drop manifest.
@@ -19,6 +19,10 @@ pub struct Meta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exe_path: Option<String>,
 
+    /// The kind of drop, such as `exe` or `font`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+
     /// The licenses used.
     ///
     /// This can be a single license or multiple delimited by "AND" or "OR".
@@ -46,11 +50,7 @@ pub struct Meta {
     pub documentation: Option<String>,
 
     /// The drop version.
-    // TODO: Switch to a flexible versioning scheme that can parse `SemVer` with
-    // any number of dots. If not `SemVer`, call it `Custom` and look into other
-    // versioning schemes.
-    // TODO: Consider accepting dates?
-    pub version: SemVer,
+    pub version: Version,
 
     // Tables: all types that serialize into maps (or "tables" in TOML)
     // them must be placed last to succeed.
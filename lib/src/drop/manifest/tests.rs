@@ -2,16 +2,17 @@
 
 use super::*;
 use crate::drop::{
+    kind::Kind,
     license::{self, SpdxLicense},
     name::{Name, Query},
     source::git::{self, Git, OCEAN_REPO},
-    version::SemVer,
+    version::{Version, VersionReq},
 };
 
 #[cfg(feature = "toml")]
 fn manifests() -> Vec<(String, Manifest)> {
     let version = "0.1.0";
-    let semver = SemVer::parse(version).unwrap();
+    let semver = Version::parse(version);
     let repo = OCEAN_REPO;
     let home = "https://www.oceanpkg.org";
     let docs = "https://docs.oceanpkg.org";
@@ -21,7 +22,8 @@ fn manifests() -> Vec<(String, Manifest)> {
         display_name: Some("Ocean".to_owned()),
         description: "Cross-platform package manager".to_owned(),
         exe_path: None,
-        version: semver.into(),
+        kind: Some(Kind::Exe),
+        version: semver,
         conflicts: None,
         license: Some(SpdxLicense::Agpl3Only.id().to_owned()),
         authors: Some(vec![
@@ -45,6 +47,7 @@ fn manifests() -> Vec<(String, Manifest)> {
             display-name = "Ocean"
             description = "Cross-platform package manager"
             version = "{version}"
+            kind = "exe"
             license = "AGPL-3.0-only"
             authors = ["Nikolai Vazquez", "Alex Farra", "Nicole Zhao"]
             readme = "README.md"
@@ -61,8 +64,12 @@ fn manifests() -> Vec<(String, Manifest)> {
     let detailed_deps: Deps = vec![(
         wget.to_owned(),
         DepInfo {
-            version: "*".to_owned(),
+            version: VersionReq::any(),
             optional: false,
+            recommended: false,
+            path: None,
+            os: vec!["linux".to_owned(), "macOS".to_owned()],
+            arch: Vec::new(),
             git: Some(Git::new(
                 "https://git.savannah.gnu.org/git/wget.git",
                 git::Ref::branch("1.0"),
@@ -84,7 +91,7 @@ fn manifests() -> Vec<(String, Manifest)> {
             Manifest {
                 meta: meta.clone(),
                 deps: Some(
-                    vec![(wget.to_owned(), "*".to_owned().into())]
+                    vec![(wget.to_owned(), VersionReq::any().into())]
                         .into_iter()
                         .collect(),
                 ),
@@ -95,7 +102,7 @@ fn manifests() -> Vec<(String, Manifest)> {
                 r#"
                     {}
                     [dependencies]
-                    wget = {{ version = "*", git = {{ repo = "https://git.savannah.gnu.org/git/wget.git", branch = "1.0" }} }}
+                    wget = {{ version = "*", os = ["linux", "macOS"], git = {{ repo = "https://git.savannah.gnu.org/git/wget.git", branch = "1.0" }} }}
                 "#,
                 header,
             ),
@@ -110,6 +117,7 @@ fn manifests() -> Vec<(String, Manifest)> {
                     {}
                     [dependencies.wget]
                     version = "*"
+                    os = ["linux", "macOS"]
                     git = {{ repo = "https://git.savannah.gnu.org/git/wget.git", branch = "1.0" }}
                 "#,
                 header,
@@ -129,7 +137,8 @@ fn example_manifest() -> Manifest {
             display_name: Some("Wumbo".to_owned()),
             description: "Something silly".to_owned(),
             exe_path: Some("wumbo".to_owned()),
-            version: SemVer::new(0, 1, 0).into(),
+            kind: None,
+            version: Version::parse("0.1.0"),
             conflicts: None,
             license: Some("MIT OR AGPL-3.0-only".to_owned()),
             authors: Some(vec![
@@ -164,6 +173,29 @@ mod toml {
         manifest.to_toml(false).unwrap();
         manifest.to_toml(true).unwrap();
     }
+
+    #[test]
+    fn invalid_dependency_version() {
+        let header = r#"
+            [meta]
+            name = "ocean"
+            description = "Cross-platform package manager"
+            version = "0.1.0"
+        "#;
+        let deps = [
+            r#"wget = "^^1""#,
+            r#"wget = { version = "^^1", optional = true }"#,
+        ];
+        for deps in &deps {
+            let toml = format!("{}\n[dependencies]\n{}", header, deps);
+            let error = Manifest::parse_toml(&toml).unwrap_err().to_string();
+            assert!(
+                error.contains("requirement for dependency \"wget\""),
+                "{}",
+                error,
+            );
+        }
+    }
 }
 
 mod json {
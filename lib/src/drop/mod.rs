@@ -5,6 +5,7 @@ mod package;
 
 pub mod kind;
 pub mod license;
+pub mod lock;
 pub mod manifest;
 pub mod name;
 pub mod source;
@@ -14,8 +15,8 @@ use self::kind::{App, Exe, Font, Lib};
 
 #[doc(inline)]
 pub use self::{
-    kind::Kind, license::License, manifest::Manifest, name::Name,
-    source::Source, version::Version,
+    kind::Kind, license::License, lock::Lockfile, manifest::Manifest,
+    name::Name, source::Source, version::Version,
 };
 
 #[cfg(feature = "toml")]
@@ -64,7 +65,8 @@ impl From<Lib> for Drop {
 }
 
 impl Drop {
-    ///
+    /// Returns the drop matching `query`.
+    #[allow(clippy::result_unit_err)] // TODO: Add an error type
     pub fn query<S>(query: &name::Query<S>) -> Result<Self, ()>
     where
         S: AsRef<str>,
@@ -153,10 +153,7 @@ impl Name {
     /// start and end of a full name.
     #[inline]
     pub fn is_valid_ascii(byte: u8) -> bool {
-        match byte {
-            b'0'..=b'9' | b'a'..=b'z' | b'-' => true,
-            _ => false,
-        }
+        matches!(byte, b'0'..=b'9' | b'a'..=b'z' | b'-')
     }
 
     /// Returns whether the unicode scalar is valid within a name.
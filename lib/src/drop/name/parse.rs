@@ -3,6 +3,7 @@ use super::{
     scoped::{self, ScopedName},
     Name, ValidateError,
 };
+use crate::drop::version::VersionReq;
 use serde::{
     de::{self, Deserialize, Deserializer, Visitor},
     ser::{Serialize, Serializer},
@@ -230,6 +231,7 @@ where
 }
 
 assert_impl_all!(Query<Box<Name>, String>: Deserialize<'static>);
+assert_impl_all!(Query<Box<Name>, VersionReq>: Deserialize<'static>);
 
 impl<'de, N, V, NE, VE> Deserialize<'de> for Query<N, V>
 where
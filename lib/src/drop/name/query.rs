@@ -370,7 +370,7 @@ impl<N, V> Query<N, V> {
     }
 }
 
-impl<'n, 'v, N: ?Sized, V: ?Sized> Query<&'n N, &'v V> {
+impl<N: ?Sized, V: ?Sized> Query<&N, &V> {
     /// Returns the result of calling [`ToOwned::to_owned`] on the fields of
     /// `self`.
     ///
@@ -426,7 +426,9 @@ mod tests {
 
     #[test]
     fn parse_liberal() {
-        let cases: &[(&str, (Option<&str>, &str, Option<&str>))] = &[
+        type Parts<'a> = (Option<&'a str>, &'a str, Option<&'a str>);
+
+        let cases: &[(&str, Parts)] = &[
             ("ocean", (None, "ocean", None)),
             ("ocean@1", (None, "ocean", Some("1"))),
             ("ocean/ocean@1", (Some("ocean"), "ocean", Some("1"))),
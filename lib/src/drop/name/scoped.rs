@@ -221,7 +221,7 @@ impl<N> ScopedName<N> {
     }
 }
 
-impl<'n, N: ?Sized> ScopedName<&'n N> {
+impl<N: ?Sized> ScopedName<&N> {
     /// Returns the result of calling [`Clone::clone`] on the fields of `self`.
     ///
     /// [`Clone::clone`]: https://doc.rust-lang.org/std/clone/trait.Clone.html#tymethod.clone
@@ -1,6 +1,9 @@
 //! Packaging and unpackaging drops.
 
-use crate::drop::Manifest;
+use crate::{
+    auth::{SecretKey, Signature},
+    drop::{lock, Manifest},
+};
 use flate2::{Compression, GzBuilder};
 use std::{
     ffi::OsString,
@@ -18,6 +21,12 @@ pub struct Package<Path = PathBuf> {
     pub manifest: Manifest,
     /// An open handle to the file for reading/writing.
     pub file: File,
+    /// The hex-encoded SHA-256 digest of the package, which the registry
+    /// stores to check downloads against.
+    pub checksum: String,
+    /// A signature of `checksum`, which installers check against the keys
+    /// they trust for the drop's scope.
+    pub signature: Option<Signature>,
 }
 
 impl Package {
@@ -42,10 +51,18 @@ impl Package {
             output_dir.as_ref().map(|p| p.as_ref()),
         )
     }
+
+    /// Signs the package's checksum with `key`.
+    pub fn sign(&mut self, key: &SecretKey) {
+        self.signature = Some(key.sign(self.checksum.as_bytes()));
+    }
 }
 
 type TarBuilder<'a> = tar::Builder<flate2::write::GzEncoder<&'a mut File>>;
 
+/// Appends `file` with only its size and whether it's executable, so that
+/// packaging the same files on another machine or checkout produces the same
+/// checksum.
 fn append_header(
     tar: &mut TarBuilder,
     tar_path: &Path, // The relative path within the tar file
@@ -53,7 +70,10 @@ fn append_header(
 ) -> io::Result<()> {
     let mut header = tar::Header::new_gnu();
     header.set_path(tar_path)?;
-    header.set_metadata(&file.metadata()?);
+    header.set_metadata_in_mode(
+        &file.metadata()?,
+        tar::HeaderMode::Deterministic,
+    );
     header.set_cksum();
     tar.append(&header, file)
 }
@@ -96,12 +116,13 @@ fn package_impl(
     // TODO: Change to `trace!`
     println!("Packaging \"{}\"", tar_path.display());
 
-    fs::DirBuilder::new().recursive(true).create(&output_dir)?;
+    fs::DirBuilder::new().recursive(true).create(output_dir)?;
 
     let mut tmp_archive = fs::OpenOptions::new()
         .read(true)
         .write(true)
         .create(true)
+        .truncate(true)
         .open(&tmp_path)?;
 
     let gz = GzBuilder::new()
@@ -116,11 +137,15 @@ fn package_impl(
         path::MAIN_SEPARATOR
     ));
 
-    for relative_path in manifest.files() {
-        let full_path = current_dir.join(&relative_path);
+    let mut files = manifest.files();
+    files.sort_unstable();
+    files.dedup();
+
+    for relative_path in files {
+        let full_path = current_dir.join(relative_path);
 
         let mut tar_path = tar_dir.clone();
-        tar_path.push(&relative_path);
+        tar_path.push(relative_path);
 
         append_header(
             &mut tar,
@@ -147,10 +172,58 @@ fn package_impl(
 
     // Set the internal cursor to 0 to allow for subsequent reading.
     tmp_archive.seek(SeekFrom::Start(0))?;
+    let checksum = lock::checksum(&mut tmp_archive)?;
+    tmp_archive.seek(SeekFrom::Start(0))?;
 
     Ok(Package {
         path: tar_path,
         manifest,
         file: tmp_archive,
+        checksum,
+        signature: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn deterministic_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join(Manifest::FILE_NAME);
+        let exe_path = dir.path().join("wumbo");
+        fs::write(
+            &manifest_path,
+            r#"
+                [meta]
+                name = "wumbo"
+                description = "Something silly"
+                version = "0.1.0"
+            "#,
+        )
+        .unwrap();
+        fs::write(&exe_path, "#!/bin/sh\n").unwrap();
+
+        let package = |output: &str, mtime: SystemTime| {
+            for path in &[&manifest_path, &exe_path] {
+                File::options()
+                    .write(true)
+                    .open(path)
+                    .unwrap()
+                    .set_modified(mtime)
+                    .unwrap();
+            }
+            let output = dir.path().join(output);
+            Package::create(dir.path(), None::<&Path>, Some(output)).unwrap()
+        };
+
+        let first = package("first", SystemTime::UNIX_EPOCH);
+        let second = package(
+            "second",
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+        );
+        assert_eq!(first.checksum, second.checksum);
+    }
+}
@@ -3,6 +3,15 @@
 use serde::{Serialize, Serializer};
 use std::fmt;
 
+#[cfg(feature = "toml")]
+use crate::drop::{Manifest, Source};
+#[cfg(feature = "toml")]
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
 /// Ocean's git repository.
 pub const OCEAN_REPO: &str = env!("CARGO_PKG_REPOSITORY");
 
@@ -68,6 +77,202 @@ impl Git {
 
         Displayer(self)
     }
+
+    /// Fetches the repository into a cache within `cache_dir`, checks out
+    /// `reference` (or `master` if unspecified), and reads its manifest.
+    ///
+    /// The repository is kept as a bare clone under `cache_dir/git/db`, from
+    /// which each checked out revision gets its own directory under
+    /// `cache_dir/git/checkouts`.
+    #[cfg(feature = "toml")]
+    pub fn checkout<P: AsRef<Path>>(
+        &self,
+        cache_dir: P,
+    ) -> io::Result<Checkout> {
+        self.checkout_impl(cache_dir.as_ref(), true, None)
+    }
+
+    /// Like [`checkout`](#method.checkout), but only uses the clone already
+    /// within `cache_dir` instead of fetching the repository.
+    #[cfg(feature = "toml")]
+    pub fn checkout_cached<P: AsRef<Path>>(
+        &self,
+        cache_dir: P,
+    ) -> io::Result<Checkout> {
+        self.checkout_impl(cache_dir.as_ref(), false, None)
+    }
+
+    /// Checks out `self` like [`checkout`](#method.checkout), fetching only
+    /// if `fetch` is `true`.
+    ///
+    /// If `locked` is a revision that's still reachable from the reference,
+    /// it's checked out instead of the reference's latest commit. This keeps
+    /// a revision recorded in Ocean.lock until the drop is updated, while a
+    /// reference that changed to point elsewhere is followed.
+    #[cfg(feature = "toml")]
+    pub(crate) fn checkout_impl(
+        &self,
+        cache_dir: &Path,
+        fetch: bool,
+        locked: Option<&str>,
+    ) -> io::Result<Checkout> {
+        let url = repo_url(&self.repo)?;
+        let dir_name = cache_name(&self.repo);
+
+        let git_dir = cache_dir.join("git");
+        let db = git_dir.join("db").join(&dir_name);
+        if !fetch {
+            if !db.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not in the cache", self.repo),
+                ));
+            }
+        } else {
+            if !db.is_dir() {
+                fs::DirBuilder::new().recursive(true).create(&db)?;
+                git(&db, &["init", "--quiet", "--bare"])?;
+            }
+            git(
+                &db,
+                &[
+                    "fetch",
+                    "--quiet",
+                    "--force",
+                    url.as_str(),
+                    "+refs/heads/*:refs/heads/*",
+                    "+refs/tags/*:refs/tags/*",
+                ],
+            )?;
+        }
+
+        let spec = match self.reference.clone().unwrap_or_default() {
+            Ref::Branch(branch) => format!("refs/heads/{}^{{commit}}", branch),
+            Ref::Tag(tag) => format!("refs/tags/{}^{{commit}}", tag),
+            Ref::Rev(rev) => format!("{}^{{commit}}", rev),
+        };
+        let head = git(&db, &["rev-parse", "--verify", "--quiet", &spec])
+            .map_err(|_| {
+                let reference = self.reference.clone().unwrap_or_default();
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "{} \"{}\" not found in {}",
+                        reference.kind(),
+                        reference,
+                        self.repo,
+                    ),
+                )
+            })?;
+        let rev = match locked {
+            Some(locked)
+                if locked != head
+                    && locked.bytes().all(|b| b.is_ascii_hexdigit())
+                    && git(
+                        &db,
+                        &["merge-base", "--is-ancestor", locked, &head],
+                    )
+                    .is_ok() =>
+            {
+                locked.to_owned()
+            }
+            _ => head,
+        };
+
+        let path = git_dir.join("checkouts").join(&dir_name).join(&rev);
+        if !path.join(".git").is_dir() {
+            if path.exists() {
+                fs::remove_dir_all(&path)?;
+            }
+            fs::DirBuilder::new().recursive(true).create(&path)?;
+            let db = db.to_string_lossy();
+            git(&path, &["clone", "--quiet", "--no-checkout", &db, "."])?;
+            git(&path, &["checkout", "--quiet", "--detach", &rev])?;
+        }
+
+        let manifest =
+            Manifest::read_toml_file(path.join(Manifest::FILE_NAME))?;
+        Ok(Checkout {
+            path,
+            url,
+            rev,
+            manifest,
+        })
+    }
+}
+
+/// A revision of a [`Git`](struct.Git.html) repository that has been checked
+/// out locally.
+#[cfg(feature = "toml")]
+#[derive(Clone, Debug)]
+pub struct Checkout {
+    /// The directory containing the checked out files.
+    pub path: PathBuf,
+    /// Where the repository was fetched from.
+    pub url: url::Url,
+    /// The full hash of the checked out commit.
+    pub rev: String,
+    /// The manifest at the root of the repository.
+    pub manifest: Manifest,
+}
+
+#[cfg(feature = "toml")]
+impl Checkout {
+    /// Returns the source of `self`, which points to its exact revision.
+    pub fn source(&self) -> Source {
+        Source::from_git_at(self.url.clone(), Ref::rev(self.rev.as_str()))
+    }
+}
+
+/// Parses `repo` as a URL, falling back to treating it as a local path.
+#[cfg(feature = "toml")]
+pub(crate) fn repo_url(repo: &str) -> io::Result<url::Url> {
+    if let Ok(url) = url::Url::parse(repo) {
+        return Ok(url);
+    }
+    let path = fs::canonicalize(repo)?;
+    url::Url::from_directory_path(&path).map_err(|()| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("\"{}\" is not a valid git repository", repo),
+        )
+    })
+}
+
+/// Returns a directory name that is unique to `repo` while still being
+/// recognizable.
+#[cfg(feature = "toml")]
+fn cache_name(repo: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let trimmed = repo.trim_end_matches('/').trim_end_matches(".git");
+    let base = trimmed
+        .rsplit(['/', '\\', ':'])
+        .next()
+        .filter(|base| !base.is_empty())
+        .unwrap_or("repo");
+    let hash = Sha256::digest(repo.as_bytes());
+    format!("{}-{:x}", base, hash)[..base.len() + 17].to_owned()
+}
+
+/// Runs `git` with `args` in `dir`, returning its trimmed output.
+#[cfg(feature = "toml")]
+fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    } else {
+        Err(io::Error::other(format!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )))
+    }
 }
 
 /// A reference to a git branch/tag/revision.
@@ -166,3 +371,107 @@ impl Ref {
         }
     }
 }
+
+#[cfg(all(test, feature = "toml"))]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Creates a bare repository in `dir` where each version of `name` is
+    /// committed and tagged as `v<version>`, in order, on `master`.
+    pub(crate) fn bare_repo(
+        dir: &Path,
+        name: &str,
+        versions: &[&str],
+    ) -> PathBuf {
+        let work = dir.join(format!("{}-work", name));
+        fs::create_dir_all(&work).unwrap();
+        git(&work, &["init", "--quiet", "--initial-branch=master"]).unwrap();
+
+        for version in versions {
+            commit_version(&work, name, version);
+        }
+
+        let bare = dir.join(format!("{}.git", name));
+        let work = work.to_string_lossy();
+        let bare_str = bare.to_string_lossy();
+        git(dir, &["clone", "--quiet", "--bare", &work, &bare_str]).unwrap();
+        bare
+    }
+
+    /// Commits `version` of `name` to `master` of the bare repository made
+    /// by `bare_repo` in `dir`.
+    pub(crate) fn advance_repo(dir: &Path, name: &str, version: &str) {
+        let work = dir.join(format!("{}-work", name));
+        commit_version(&work, name, version);
+        let bare = dir.join(format!("{}.git", name));
+        let bare = bare.to_string_lossy();
+        git(&work, &["push", "--quiet", "--tags", &bare, "master"]).unwrap();
+    }
+
+    fn commit_version(work: &Path, name: &str, version: &str) {
+        let manifest = format!(
+            "[meta]\nname = \"{}\"\ndescription = \"\"\nversion = \"{}\"\n",
+            name, version,
+        );
+        fs::write(work.join(Manifest::FILE_NAME), manifest).unwrap();
+        let tag = format!("v{}", version);
+        git(work, &["add", "."]).unwrap();
+        git(
+            work,
+            &[
+                "-c",
+                "user.name=Ocean",
+                "-c",
+                "user.email=ocean@localhost",
+                "commit",
+                "--quiet",
+                "-m",
+                &tag,
+            ],
+        )
+        .unwrap();
+        git(work, &["tag", &tag]).unwrap();
+    }
+
+    #[test]
+    fn checkout_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = bare_repo(dir.path(), "tool", &["1.0.0", "1.1.0"]);
+        let repo = repo.to_string_lossy();
+        let cache = dir.path().join("cache");
+
+        let head = Git::new(repo.as_ref(), None).checkout(&cache).unwrap();
+        assert_eq!(head.manifest.meta.version.to_string(), "1.1.0");
+
+        let tag = Git::new(repo.as_ref(), Ref::tag("v1.0.0"));
+        let tag = tag.checkout(&cache).unwrap();
+        assert_eq!(tag.manifest.meta.version.to_string(), "1.0.0");
+        assert!(tag.path.join(Manifest::FILE_NAME).is_file());
+        assert_ne!(tag.rev, head.rev);
+
+        let rev = Git::new(repo.as_ref(), Ref::rev(tag.rev.as_str()));
+        assert_eq!(rev.checkout(&cache).unwrap().path, tag.path);
+        assert_eq!(
+            tag.source().to_string(),
+            format!("git+{}#{}", tag.url, tag.rev)
+        );
+
+        let missing = Git::new(repo.as_ref(), Ref::branch("missing"));
+        assert!(missing.checkout(&cache).is_err());
+    }
+
+    #[test]
+    fn checkout_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = bare_repo(dir.path(), "tool", &["1.0.0"]);
+        let repo = repo.to_string_lossy();
+        let cache = dir.path().join("cache");
+
+        let git = Git::new(repo.as_ref(), None);
+        let error = git.checkout_cached(&cache).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        let fetched = git.checkout(&cache).unwrap();
+        assert_eq!(git.checkout_cached(&cache).unwrap().rev, fetched.rev);
+    }
+}
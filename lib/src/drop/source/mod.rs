@@ -1,5 +1,7 @@
 //! The source of a package.
 
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{error::Error, fmt, str::FromStr};
 use url::Url;
 
 pub mod git;
@@ -16,7 +18,11 @@ lazy_static! {
 }
 
 /// The source of a drop.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// This is written as a string in the form of `registry+<url>`, `path+<url>`,
+/// or `git+<url>`, where a git URL is suffixed with `?branch=<name>`,
+/// `?tag=<name>`, or `#<rev>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Source {
     url: Url,
     kind: Kind,
@@ -53,6 +59,15 @@ impl Source {
         }
     }
 
+    /// A drop source at a `file:` `Url` for a local directory.
+    #[inline]
+    pub const fn from_path(url: Url) -> Self {
+        Source {
+            url,
+            kind: Kind::Path,
+        }
+    }
+
     /// Where this source is located.
     #[inline]
     pub const fn url(&self) -> &Url {
@@ -66,12 +81,119 @@ impl Source {
     }
 }
 
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            Kind::Registry => write!(f, "registry+{}", self.url),
+            Kind::Path => write!(f, "path+{}", self.url),
+            Kind::Git(Ref::Rev(rev)) => write!(f, "git+{}#{}", self.url, rev),
+            Kind::Git(reference) => {
+                write!(f, "git+{}?{}={}", self.url, reference.kind(), reference)
+            }
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '+');
+        let (kind, url) = match (parts.next(), parts.next()) {
+            (Some(kind), Some(url)) => (kind, url),
+            _ => return Err(ParseError::MissingKind),
+        };
+
+        match kind {
+            "registry" => Ok(Self::from_registry(Url::parse(url)?)),
+            "path" => Ok(Self::from_path(Url::parse(url)?)),
+            "git" => {
+                let mut url = Url::parse(url)?;
+                let reference = if let Some(rev) = url.fragment() {
+                    Ref::rev(rev)
+                } else if let Some((kind, name)) = url.query_pairs().next() {
+                    Ref::all(name.into_owned())
+                        .iter()
+                        .find(|reference| reference.kind() == kind)
+                        .cloned()
+                        .ok_or(ParseError::UnknownRef)?
+                } else {
+                    Ref::master()
+                };
+                url.set_fragment(None);
+                url.set_query(None);
+                Ok(Self::from_git_at(url, reference))
+            }
+            _ => Err(ParseError::UnknownKind),
+        }
+    }
+}
+
+impl Serialize for Source {
+    #[inline]
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Source {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let source = String::deserialize(deserializer)?;
+        source.parse().map_err(de::Error::custom)
+    }
+}
+
+/// An error returned when parsing a [`Source`](struct.Source.html) from a
+/// string.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The `<kind>+` prefix is missing.
+    MissingKind,
+    /// The prefix is not `registry`, `path`, or `git`.
+    UnknownKind,
+    /// The git URL query is not a `branch` or `tag`.
+    UnknownRef,
+    /// The URL is malformed.
+    Url(url::ParseError),
+}
+
+impl From<url::ParseError> for ParseError {
+    #[inline]
+    fn from(error: url::ParseError) -> Self {
+        Self::Url(error)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingKind => {
+                write!(f, "missing `registry+`, `path+`, or `git+` prefix")
+            }
+            Self::UnknownKind => {
+                write!(f, "expected `registry`, `path`, or `git` source")
+            }
+            Self::UnknownRef => {
+                write!(f, "expected `branch` or `tag` in git URL")
+            }
+            Self::Url(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
 /// Determines how to treat a [`Source`](struct.Source.html).
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Kind {
     /// The drop is located in a git repository and the given reference should
     /// be used.
     Git(Ref),
     /// The drop is located in a registry.
     Registry,
+    /// The drop is located in a local directory.
+    Path,
 }
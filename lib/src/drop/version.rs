@@ -1,22 +1,32 @@
 //! Versioning schemes.
 
-use serde::{Serialize, Serializer};
-use std::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering, convert::TryFrom, error::Error, fmt, num::ParseIntError,
+    str::FromStr,
+};
 
 #[doc(inline)]
 pub use semver::Version as SemVer;
 
-flexible! {
-    /// A drop version.
-    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-    pub enum Version {
-        /// [Semantic versioning](http://semver.org). This is the default.
-        #[serde(rename = "semver")]
-        SemVer(SemVer),
-        /// A custom versioning scheme.
-        #[serde(rename = "custom")]
-        Custom(String),
-    }
+/// A drop version.
+///
+/// Versions are only ordered relative to versions of the same scheme, and
+/// `Custom` versions are not ordered at all.
+///
+/// When written as a plain string, a version is parsed via
+/// [`Version::parse`](#method.parse). A specific scheme can be chosen by
+/// writing it as a table, such as `{ calendar = "2023.10.1" }`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Version {
+    /// [Semantic versioning](http://semver.org). This is the default.
+    SemVer(SemVer),
+    /// Any number of numeric components, e.g. `1.2.3.4`.
+    Dotted(Dotted),
+    /// A date-based version, e.g. `2023.10.1`.
+    Calendar(Calendar),
+    /// A custom versioning scheme, e.g. `r1234`.
+    Custom(String),
 }
 
 impl From<SemVer> for Version {
@@ -26,22 +36,56 @@ impl From<SemVer> for Version {
     }
 }
 
+impl From<Dotted> for Version {
+    #[inline]
+    fn from(v: Dotted) -> Self {
+        Version::Dotted(v)
+    }
+}
+
+impl From<Calendar> for Version {
+    #[inline]
+    fn from(v: Calendar) -> Self {
+        Version::Calendar(v)
+    }
+}
+
 impl PartialEq<str> for Version {
     fn eq(&self, s: &str) -> bool {
         match self {
             // TODO: Switch to a `SemVer` type that supports string equality
             // without doing full parsing
             Self::SemVer(v) => Ok(v) == SemVer::parse(s).as_ref(),
+            Self::Dotted(v) => Ok(v) == Dotted::parse(s).as_ref(),
+            Self::Calendar(v) => Ok(v) == Calendar::parse(s).as_ref(),
             Self::Custom(v) => v == s,
         }
     }
 }
 
+/// Versions of the same scheme compare by their ordering. Versions of
+/// different schemes and unequal `Custom` versions are incomparable.
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::SemVer(a), Self::SemVer(b)) => Some(a.cmp(b)),
+            (Self::Dotted(a), Self::Dotted(b)) => Some(a.cmp(b)),
+            (Self::Calendar(a), Self::Calendar(b)) => Some(a.cmp(b)),
+            (Self::Custom(a), Self::Custom(b)) if a == b => {
+                Some(Ordering::Equal)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for Version {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::SemVer(v) => v.fmt(f),
+            Self::Dotted(v) => v.fmt(f),
+            Self::Calendar(v) => v.fmt(f),
             Self::Custom(v) => v.fmt(f),
         }
     }
@@ -54,18 +98,87 @@ impl Serialize for Version {
     {
         use serde::ser::SerializeMap;
 
-        match self {
-            Version::SemVer(semver) => ser.collect_str(semver),
-            Version::Custom(custom) => {
-                let mut map = ser.serialize_map(Some(1))?;
-                map.serialize_entry("custom", custom)?;
-                map.end()
-            }
+        // Only use the simple form if it parses back into `self`.
+        let version = self.to_string();
+        if Self::parse(&version) == *self {
+            ser.serialize_str(&version)
+        } else {
+            let mut map = ser.serialize_map(Some(1))?;
+            map.serialize_entry(self.kind(), &version)?;
+            map.end()
         }
     }
 }
 
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Explicit {
+            SemVer(String),
+            Dotted(String),
+            Calendar(String),
+            Custom(String),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Simple(String),
+            Explicit(Explicit),
+        }
+
+        let version = match Repr::deserialize(deserializer)? {
+            Repr::Simple(v) => Self::parse(&v),
+            Repr::Explicit(Explicit::SemVer(v)) => {
+                Self::parse_semver(&v).map_err(de::Error::custom)?
+            }
+            Repr::Explicit(Explicit::Dotted(v)) => {
+                Dotted::parse(&v).map_err(de::Error::custom)?.into()
+            }
+            Repr::Explicit(Explicit::Calendar(v)) => {
+                Calendar::parse(&v).map_err(de::Error::custom)?.into()
+            }
+            Repr::Explicit(Explicit::Custom(v)) => Self::Custom(v),
+        };
+        Ok(version)
+    }
+}
+
 impl Version {
+    /// Parses `version` as a calendar version if it looks like a date, then
+    /// as SemVer if possible, then as a dotted version, and otherwise as a
+    /// custom version.
+    ///
+    /// A date is `<year>.<month>` or `<year>.<month>.<day>`, with a four-digit
+    /// year, a month from 1 to 12, and a day from 1 to 31. Upstream versions
+    /// of this shape are almost always dates, whereas SemVer major versions
+    /// rarely have four digits. Other calendar versions, such as with a
+    /// release number after the day, must be written as a table.
+    ///
+    /// ```
+    /// use oceanpkg::drop::version::Version;
+    ///
+    /// assert_eq!(Version::parse("1.2.3").kind(), "semver");
+    /// assert_eq!(Version::parse("2023.10.1").kind(), "calendar");
+    /// assert_eq!(Version::parse("1.2.3.4").kind(), "dotted");
+    /// assert_eq!(Version::parse("r1234").kind(), "custom");
+    /// ```
+    pub fn parse(version: &str) -> Self {
+        if let Some(v) = Calendar::parse_date(version) {
+            Self::Calendar(v)
+        } else if let Ok(v) = SemVer::parse(version) {
+            Self::SemVer(v)
+        } else if let Ok(v) = Dotted::parse(version) {
+            Self::Dotted(v)
+        } else {
+            Self::Custom(version.to_owned())
+        }
+    }
+
     /// Creates a new instance from a custom `version`.
     #[inline]
     pub fn custom<V>(version: V) -> Self
@@ -81,12 +194,678 @@ impl Version {
         SemVer::parse(version).map(Self::SemVer)
     }
 
-    /// Returns the name of the version kind: `semver` or `custom`.
+    /// Returns the name of the version kind: `semver`, `dotted`, `calendar`,
+    /// or `custom`.
     #[inline]
     pub fn kind(&self) -> &'static str {
         match self {
             Version::SemVer(_) => "semver",
+            Version::Dotted(_) => "dotted",
+            Version::Calendar(_) => "calendar",
             Version::Custom(_) => "custom",
         }
     }
+
+    /// Parses `version` using the same scheme as `self`.
+    fn parse_like(&self, version: &str) -> Option<Self> {
+        match self {
+            Self::SemVer(_) => SemVer::parse(version).ok().map(Self::SemVer),
+            Self::Dotted(_) => Dotted::parse(version).ok().map(Self::Dotted),
+            Self::Calendar(_) => {
+                Calendar::parse(version).ok().map(Self::Calendar)
+            }
+            Self::Custom(_) => Some(Self::custom(version)),
+        }
+    }
+
+    /// Returns a total ordering where greater means more preferable.
+    ///
+    /// This agrees with `partial_cmp` where it is defined. Otherwise, SemVer
+    /// is preferred over dotted, then calendar, then custom versions.
+    ///
+    /// Custom versions are equal to each other, since their text says nothing
+    /// about which is newer. A stable sort therefore keeps them in the order
+    /// that they were listed in, such as by the registry.
+    pub fn cmp_precedence(&self, other: &Self) -> Ordering {
+        fn rank(version: &Version) -> u8 {
+            match version {
+                Version::Custom(_) => 0,
+                Version::Calendar(_) => 1,
+                Version::Dotted(_) => 2,
+                Version::SemVer(_) => 3,
+            }
+        }
+        match (self, other) {
+            (Self::Custom(_), Self::Custom(_)) => Ordering::Equal,
+            _ => self
+                .partial_cmp(other)
+                .unwrap_or_else(|| rank(self).cmp(&rank(other))),
+        }
+    }
+}
+
+/// A version with any number of numeric components, e.g. `1.2.3.4`.
+///
+/// Components are compared from left to right. A version that is a prefix of
+/// another is older, so `1.2 < 1.2.0 < 1.2.1`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dotted(Vec<u64>);
+
+impl fmt::Display for Dotted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut components = self.0.iter();
+        if let Some(first) = components.next() {
+            write!(f, "{}", first)?;
+        }
+        for component in components {
+            write!(f, ".{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Dotted {
+    type Err = ParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Dotted {
+    /// Creates a new instance from at least one component.
+    pub fn new<C: Into<Vec<u64>>>(components: C) -> Option<Self> {
+        let components = components.into();
+        if components.is_empty() {
+            None
+        } else {
+            Some(Self(components))
+        }
+    }
+
+    /// Parses `version` as `.`-separated numbers.
+    pub fn parse(version: &str) -> Result<Self, ParseError> {
+        numbers(version)
+            .map(Self)
+            .map_err(|_| ParseError::new("dotted", version))
+    }
+
+    /// Returns the numeric components.
+    #[inline]
+    pub fn components(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+/// A date-based version in the form `<year>.<month>`, optionally followed by
+/// more numeric components such as a day or a release number, e.g.
+/// `2023.10.1`.
+///
+/// Versions are compared by year, then month, then the remaining components
+/// in the same way as [`Dotted`](struct.Dotted.html). Zero-padding is not
+/// retained, so `2023.01` is displayed as `2023.1`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Calendar {
+    /// The four-digit year.
+    pub year: u16,
+    /// The month, from 1 to 12.
+    pub month: u8,
+    /// Any components after the month.
+    pub rest: Vec<u64>,
+}
+
+impl fmt::Display for Calendar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.year, self.month)?;
+        for component in &self.rest {
+            write!(f, ".{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Calendar {
+    type Err = ParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Calendar {
+    /// Parses `version` as `<year>.<month>(.<number>)*`.
+    pub fn parse(version: &str) -> Result<Self, ParseError> {
+        let error = || ParseError::new("calendar", version);
+        if version.split('.').next().map(str::len) != Some(4) {
+            return Err(error());
+        }
+        let numbers = numbers(version).map_err(|_| error())?;
+        match numbers.as_slice() {
+            &[year, month, ref rest @ ..] if (1..=12).contains(&month) => {
+                Ok(Self {
+                    year: year as u16,
+                    month: month as u8,
+                    rest: rest.to_vec(),
+                })
+            }
+            _ => Err(error()),
+        }
+    }
+
+    /// Parses `version` if it's shaped like a date, as described in
+    /// [`Version::parse`](enum.Version.html#method.parse).
+    fn parse_date(version: &str) -> Option<Self> {
+        let calendar = Self::parse(version).ok()?;
+        match calendar.rest.as_slice() {
+            [] => Some(calendar),
+            [day] if (1..=31).contains(day) => Some(calendar),
+            _ => None,
+        }
+    }
+}
+
+/// Parses `.`-separated numbers, of which there must be at least one.
+fn numbers(version: &str) -> Result<Vec<u64>, ParseIntError> {
+    version
+        .split('.')
+        .map(|component| {
+            // `u64::from_str` accepts a leading `+`.
+            if component.starts_with('+') {
+                "".parse()
+            } else {
+                component.parse()
+            }
+        })
+        .collect()
+}
+
+/// The error returned when parsing a version of a specific scheme fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    scheme: &'static str,
+    version: String,
+}
+
+impl ParseError {
+    fn new(scheme: &'static str, version: &str) -> Self {
+        Self {
+            scheme,
+            version: version.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" is not a {} version", self.version, self.scheme)
+    }
+}
+
+impl Error for ParseError {}
+
+/// The error returned when parsing a [`VersionReq`](struct.VersionReq.html)
+/// fails.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReqParseError {
+    req: String,
+    semver: Option<semver::ReqParseError>,
+}
+
+impl fmt::Display for ReqParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" is not a valid version requirement", self.req)?;
+        match &self.semver {
+            Some(error) => write!(f, ": {}", error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for ReqParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.semver.as_ref().map(|error| error as _)
+    }
+}
+
+/// A requirement that drop versions can be matched against.
+///
+/// SemVer versions are matched as in [Cargo], e.g. `^1.2` or `>=1, <3`.
+///
+/// Other schemes only support comma-separated comparisons via `=`, `>`, `>=`,
+/// `<`, and `<=`. Each bound is parsed with the scheme of the version being
+/// matched, and incomparable versions never match. Because custom versions
+/// have no ordering, they are only matched by an exact version such as
+/// `=r1234`, and never by `*`, so that one is never picked as the newest.
+///
+/// What a bare version such as `1.2.3` means depends on the scheme of the
+/// version being matched:
+///
+/// - `SemVer`: `^1.2.3`, so any compatible version from `1.2.3` up to but
+///   excluding `2.0.0` matches, as in Cargo.
+/// - [`Dotted`](struct.Dotted.html) and [`Calendar`](struct.Calendar.html):
+///   `=1.2.3`, since these schemes don't define
+///   which versions are compatible.
+/// - `Custom`: `=1.2.3`.
+///
+/// The original text is retained for display.
+///
+/// [Cargo]: https://doc.rust-lang.org/cargo/reference/specifying-dependencies.html
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionReq {
+    text: String,
+    kind: ReqKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ReqKind {
+    Any,
+    Compare {
+        semver: Option<semver::VersionReq>,
+        comparators: Vec<Comparator>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Comparator {
+    op: Op,
+    version: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Comparator {
+    /// Parses `req` as comma-separated comparators.
+    fn parse_all(req: &str) -> Option<Vec<Self>> {
+        req.split(',').map(Self::parse).collect()
+    }
+
+    fn parse(comparator: &str) -> Option<Self> {
+        let comparator = comparator.trim();
+        let ops = [
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+            ("=", Op::Eq),
+        ];
+        let (op, version) = ops
+            .iter()
+            .find_map(|&(prefix, op)| {
+                comparator.strip_prefix(prefix).map(|rest| (op, rest))
+            })
+            .unwrap_or((Op::Eq, comparator));
+
+        let version = version.trim_start();
+        let is_valid = |c: char| !c.is_whitespace() && !"=<>^~*,".contains(c);
+        if !version.is_empty() && version.chars().all(is_valid) {
+            Some(Self {
+                op,
+                version: version.to_owned(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        // Custom versions can't be ranked, so they must be pinned exactly.
+        if matches!(version, Version::Custom(_)) && self.op != Op::Eq {
+            return false;
+        }
+        let bound = match version.parse_like(&self.version) {
+            Some(bound) => bound,
+            None => return false,
+        };
+        match (self.op, version.partial_cmp(&bound)) {
+            (_, None) => false,
+            (Op::Eq, Some(ord)) => ord == Ordering::Equal,
+            (Op::Gt, Some(ord)) => ord == Ordering::Greater,
+            (Op::Ge, Some(ord)) => ord != Ordering::Less,
+            (Op::Lt, Some(ord)) => ord == Ordering::Less,
+            (Op::Le, Some(ord)) => ord != Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for VersionReq {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+impl AsRef<str> for VersionReq {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = ReqParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<&str> for VersionReq {
+    type Error = ReqParseError;
+
+    #[inline]
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for VersionReq {
+    #[inline]
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(&self.text)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionReq {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let req = String::deserialize(deserializer)?;
+        Self::parse(&req).map_err(de::Error::custom)
+    }
+}
+
+impl VersionReq {
+    /// Parses a requirement, such as `^1.2`, `*`, `>=2023.10`, or `=r1234`.
+    ///
+    /// ```
+    /// use oceanpkg::drop::version::{Version, VersionReq};
+    ///
+    /// let req = VersionReq::parse("^1.2").unwrap();
+    /// assert!(req.matches(&Version::parse("1.4.0")));
+    ///
+    /// let req = VersionReq::parse(">=1.2.3.4").unwrap();
+    /// assert!(req.matches(&Version::parse("1.2.10.0")));
+    ///
+    /// let req = VersionReq::parse("=r1234").unwrap();
+    /// assert!(req.matches(&Version::custom("r1234")));
+    ///
+    /// assert!(VersionReq::parse("^^1").is_err());
+    /// ```
+    pub fn parse(req: &str) -> Result<Self, ReqParseError> {
+        match req.trim() {
+            "" => {
+                return Err(ReqParseError {
+                    req: req.to_owned(),
+                    semver: None,
+                })
+            }
+            "*" => return Ok(Self::any()),
+            _ => {}
+        }
+        let (semver, comparators) = match semver::VersionReq::parse(req) {
+            Ok(semver) => {
+                let comparators = Comparator::parse_all(req);
+                (Some(semver), comparators.unwrap_or_default())
+            }
+            Err(error) => match Comparator::parse_all(req) {
+                Some(comparators) => (None, comparators),
+                None => {
+                    return Err(ReqParseError {
+                        req: req.to_owned(),
+                        semver: Some(error),
+                    })
+                }
+            },
+        };
+        Ok(Self {
+            text: req.to_owned(),
+            kind: ReqKind::Compare {
+                semver,
+                comparators,
+            },
+        })
+    }
+
+    /// Returns a requirement that matches any version.
+    #[inline]
+    pub fn any() -> Self {
+        Self {
+            text: "*".to_owned(),
+            kind: ReqKind::Any,
+        }
+    }
+
+    /// Returns a requirement that only matches `version`.
+    pub fn exact(version: &Version) -> Self {
+        let semver = match version {
+            Version::SemVer(v) => Some(semver::VersionReq::exact(v)),
+            _ => None,
+        };
+        let comparator = Comparator {
+            op: Op::Eq,
+            version: version.to_string(),
+        };
+        Self {
+            text: format!("={}", version),
+            kind: ReqKind::Compare {
+                semver,
+                comparators: vec![comparator],
+            },
+        }
+    }
+
+    /// Returns the requirement as it was written.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns whether `version` satisfies `self`.
+    pub fn matches(&self, version: &Version) -> bool {
+        let (semver, comparators) = match &self.kind {
+            ReqKind::Any => return !matches!(version, Version::Custom(_)),
+            ReqKind::Compare {
+                semver,
+                comparators,
+            } => (semver, comparators),
+        };
+        match (semver, version) {
+            (Some(req), Version::SemVer(v)) => req.matches(v),
+            _ => {
+                !comparators.is_empty()
+                    && comparators.iter().all(|c| c.matches(version))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_schemes() {
+        assert_eq!(
+            Version::parse("1.2.3"),
+            Version::from(SemVer::new(1, 2, 3))
+        );
+        assert_eq!(
+            Version::parse("1.2.3.4"),
+            Version::from(Dotted::new(vec![1, 2, 3, 4]).unwrap()),
+        );
+        assert_eq!(Version::parse("r1234"), Version::custom("r1234"));
+        assert_eq!(Version::parse("1..2"), Version::custom("1..2"));
+        assert_eq!(Version::parse("+1.2"), Version::custom("+1.2"));
+
+        // Only date-shaped versions are inferred as calendar versions.
+        for (version, kind) in &[
+            ("2023.10.1", "calendar"),
+            ("2023.10", "calendar"),
+            ("2023.10.45", "semver"),
+            ("2023.13.1", "semver"),
+            ("2023.10.1.5", "dotted"),
+            ("23.10.1", "semver"),
+        ] {
+            assert_eq!(Version::parse(version).kind(), *kind, "{}", version);
+        }
+
+        let calendar = Calendar::parse("2023.10.1").unwrap();
+        assert_eq!(
+            Version::parse("2023.10.1"),
+            Version::from(calendar.clone())
+        );
+        assert_eq!((calendar.year, calendar.month), (2023, 10));
+        assert_eq!(calendar.rest, [1]);
+        assert_eq!(calendar.to_string(), "2023.10.1");
+        for invalid in &["23.10", "2023.13", "2023", "2023.1.x"] {
+            assert!(Calendar::parse(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let dotted = |v: &str| Version::Dotted(Dotted::parse(v).unwrap());
+        assert!(dotted("1.2.3.4") < dotted("1.2.3.10"));
+        assert!(dotted("1.2") < dotted("1.2.0"));
+        assert!(dotted("1.10") > dotted("1.9.9"));
+
+        let calendar = |v: &str| Version::Calendar(Calendar::parse(v).unwrap());
+        assert!(calendar("2023.10.1") < calendar("2023.11"));
+        assert!(calendar("2024.1") > calendar("2023.12.31"));
+
+        let custom = Version::custom("r1234");
+        assert_eq!(custom.partial_cmp(&custom), Some(Ordering::Equal));
+        assert_eq!(custom.partial_cmp(&Version::custom("r999")), None);
+        assert_eq!(custom.partial_cmp(&Version::custom("r1235")), None);
+        assert_eq!(dotted("1.2").partial_cmp(&calendar("2023.10")), None);
+        assert_eq!(Version::parse("1.2.3").partial_cmp(&dotted("1.2")), None);
+    }
+
+    #[test]
+    fn custom_precedence() {
+        // Ranking by text would make `r999` newer than `r1234`, so custom
+        // versions keep the order they were listed in.
+        let mut versions = vec![
+            Version::custom("r1234"),
+            Version::parse("1.0.0"),
+            Version::custom("r999"),
+        ];
+        versions.sort_by(|a, b| b.cmp_precedence(a));
+        assert_eq!(
+            versions,
+            [
+                Version::parse("1.0.0"),
+                Version::custom("r1234"),
+                Version::custom("r999"),
+            ],
+        );
+        let (r999, r1234) = (Version::custom("r999"), Version::custom("r1234"));
+        assert_eq!(r999.cmp_precedence(&r1234), Ordering::Equal);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let versions = [
+            Version::parse("1.2.3"),
+            Version::parse("1.2.3.4"),
+            Version::parse("r1234"),
+            Version::Dotted(Dotted::parse("1.2.3").unwrap()),
+            Version::Calendar(Calendar::parse("2023.10.1").unwrap()),
+        ];
+        for version in &versions {
+            let json = json::to_string(version).unwrap();
+            let parsed: Version = json::from_str(&json).unwrap();
+            assert_eq!(&parsed, version, "{}", json);
+        }
+
+        let json = r#"{ "calendar": "2023.13" }"#;
+        assert!(json::from_str::<Version>(json).is_err());
+    }
+
+    #[test]
+    fn req_matches() {
+        let semver = Version::SemVer(SemVer::new(1, 2, 3));
+        let custom = Version::custom("r1234");
+
+        let any = VersionReq::parse("*").unwrap();
+        assert!(any.matches(&semver));
+        assert!(!any.matches(&custom));
+
+        let caret = VersionReq::parse("^1.2").unwrap();
+        assert!(caret.matches(&semver));
+        assert!(!caret.matches(&custom));
+        assert_eq!(caret.to_string(), "^1.2");
+
+        let exact = VersionReq::parse("=r1234").unwrap();
+        assert!(exact.matches(&custom));
+        assert!(!exact.matches(&Version::custom("r1235")));
+        assert!(!exact.matches(&semver));
+        assert!(!VersionReq::parse(">r1234").unwrap().matches(&custom));
+        assert!(!VersionReq::parse(">=r1234").unwrap().matches(&custom));
+
+        assert_eq!(VersionReq::exact(&custom), exact);
+        assert!(VersionReq::exact(&semver).matches(&semver));
+    }
+
+    #[test]
+    fn req_matches_other_schemes() {
+        let dotted = Version::parse("1.2.3.4");
+        let range = VersionReq::parse(">=1.2.3, <1.3").unwrap();
+        assert!(range.matches(&dotted));
+        assert!(range.matches(&Version::parse("1.2.3")));
+        assert!(!range.matches(&Version::parse("1.3.0.0")));
+        assert!(VersionReq::parse("1.2.3.4").unwrap().matches(&dotted));
+
+        let calendar = Calendar::parse("2023.10.1").unwrap().into();
+        assert!(VersionReq::parse(">=2023.10").unwrap().matches(&calendar));
+        assert!(!VersionReq::parse("<2023.10").unwrap().matches(&calendar));
+        assert!(VersionReq::exact(&calendar).matches(&calendar));
+
+        // SemVer-only operators don't apply to other schemes.
+        assert!(!VersionReq::parse("^1.2").unwrap().matches(&dotted));
+    }
+
+    #[test]
+    fn req_bare_versions() {
+        let dotted = |v: &str| Version::Dotted(Dotted::parse(v).unwrap());
+        let calendar = |v: &str| Version::Calendar(Calendar::parse(v).unwrap());
+
+        let bare = VersionReq::parse("1.2.3").unwrap();
+        assert!(bare.matches(&Version::parse("1.4.0")));
+        assert!(!bare.matches(&Version::parse("2.0.0")));
+        assert!(bare.matches(&dotted("1.2.3")));
+        assert!(!bare.matches(&dotted("1.2.4")));
+
+        let bare = VersionReq::parse("2023.10").unwrap();
+        assert!(bare.matches(&calendar("2023.10")));
+        assert!(!bare.matches(&calendar("2023.11")));
+
+        let bare = VersionReq::parse("r1234").unwrap();
+        assert!(bare.matches(&Version::custom("r1234")));
+        assert!(!bare.matches(&Version::custom("r1235")));
+    }
+
+    #[test]
+    fn req_parse_errors() {
+        for req in &["^^1", "", "=", "=r1 r2", ">=", "1.2, ^"] {
+            assert!(VersionReq::parse(req).is_err(), "{:?}", req);
+        }
+        let error = VersionReq::parse("").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "\"\" is not a valid version requirement"
+        );
+        assert!(VersionReq::parse("^^1").unwrap_err().source().is_some());
+    }
 }
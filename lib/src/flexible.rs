@@ -32,6 +32,10 @@ macro_rules! flexible {
             )*
         }
 
+        impl $(<$l>)? crate::flexible::Detailed for $t $(<$l>)? {
+            type Simple = $s_ty;
+        }
+
         impl<'de $(: $l)? $(, $l)?> serde::Deserialize<'de> for $t $(<$l>)? {
             #[inline]
             fn deserialize<D>(de: D) -> Result<Self, D::Error>
@@ -65,10 +69,6 @@ macro_rules! flexible {
                     type Simple = $s_ty;
                 }
 
-                impl $(<$l>)? Detailed for $t $(<$l>)? {
-                    type Simple = $s_ty;
-                }
-
                 Flexible::<wrapper::$t>::deserialize(de)
                     .map(|wrapper| unsafe {
                         std::mem::transmute::<_, Flexible<$t>>(wrapper)
@@ -106,6 +106,10 @@ macro_rules! flexible {
             )*
         }
 
+        impl $(<$l>)? crate::flexible::Detailed for $t $(<$l>)? {
+            type Simple = $s_ty;
+        }
+
         impl<'de $(: $l)? $(, $l)?> serde::Deserialize<'de> for $t $(<$l>)? {
             #[inline]
             fn deserialize<D>(de: D) -> Result<Self, D::Error>
@@ -139,10 +143,6 @@ macro_rules! flexible {
                     type Simple = $s_ty;
                 }
 
-                impl $(<$l>)? Detailed for $t $(<$l>)? {
-                    type Simple = $s_ty;
-                }
-
                 Flexible::<wrapper::$t>::deserialize(de)
                     .map(|wrapper| unsafe {
                         std::mem::transmute::<_, Flexible<$t>>(wrapper)
@@ -0,0 +1,199 @@
+//! Exposing the executables of installed drops via `$PATH`.
+
+use super::Receipts;
+use crate::drop::name::ScopedName;
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// An executable could not be linked into a bin directory.
+#[derive(Debug)]
+pub enum LinkError {
+    /// Another drop already provides a binary with the same name.
+    Collision {
+        /// The name of the binary.
+        binary: String,
+        /// The drop that provides the binary.
+        owner: ScopedName,
+    },
+    /// A file that wasn't installed by Ocean is in the way.
+    Unmanaged(PathBuf),
+    /// Failed to read receipts or create the link.
+    Io(io::Error),
+}
+
+impl From<io::Error> for LinkError {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Collision { binary, owner } => write!(
+                f,
+                "binary \"{}\" is already provided by \"{}\"",
+                binary, owner,
+            ),
+            Self::Unmanaged(path) => write!(
+                f,
+                "\"{}\" already exists and was not installed by Ocean",
+                path.display(),
+            ),
+            Self::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for LinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the name that the executable at `exe_path` is exposed as, which is
+/// its file name.
+pub fn binary_name(exe_path: &str) -> Option<&str> {
+    Path::new(exe_path).file_name()?.to_str()
+}
+
+/// Links `binary` within `bin_dir` to `exe` on behalf of `drop`, returning the
+/// path of the link.
+///
+/// The binary can only replace one that was provided by another version of
+/// `drop`, according to `receipts`. The link is replaced atomically.
+pub fn link_binary(
+    bin_dir: &Path,
+    binary: &str,
+    exe: &Path,
+    drop: ScopedName<&str>,
+    receipts: &Receipts,
+) -> Result<PathBuf, LinkError> {
+    let mut is_owned = false;
+    for receipt in receipts.all()? {
+        if !receipt.binaries.iter().any(|b| b == binary) {
+            continue;
+        }
+        if receipt.name.scope == drop.scope && receipt.name.name == drop.name {
+            is_owned = true;
+        } else {
+            return Err(LinkError::Collision {
+                binary: binary.to_owned(),
+                owner: receipt.name,
+            });
+        }
+    }
+
+    let path = bin_dir.join(binary);
+    if !is_owned && fs::symlink_metadata(&path).is_ok() {
+        return Err(LinkError::Unmanaged(path));
+    }
+
+    fs::DirBuilder::new().recursive(true).create(bin_dir)?;
+    let tmp_path = bin_dir.join(format!(".{}.tmp", binary));
+    match fs::remove_file(&tmp_path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            return Err(error.into());
+        }
+        _ => {}
+    }
+    symlink(exe, &tmp_path)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(path)
+}
+
+/// Removes `binary` from `bin_dir` if it links to a file within `drop_dir`,
+/// returning whether it did.
+pub fn unlink_binary(
+    bin_dir: &Path,
+    binary: &str,
+    drop_dir: &Path,
+) -> io::Result<bool> {
+    let path = bin_dir.join(binary);
+    let target = match fs::read_link(&path) {
+        Ok(target) => bin_dir.join(target),
+        Err(_) => return Ok(false),
+    };
+    if target.starts_with(drop_dir) {
+        fs::remove_file(&path)?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        drop::{version::Version, Source},
+        install::{InstallReason, Receipt},
+    };
+
+    fn receipt(name: &str, binaries: &[&str]) -> Receipt {
+        Receipt {
+            name: ScopedName::parse(name).unwrap(),
+            version: Version::parse("1.0.0"),
+            requirement: None,
+            source: Source::main_registry().clone(),
+            installed_at: 0,
+            reason: InstallReason::Explicit,
+            dependencies: Vec::new(),
+            binaries: binaries.iter().map(|b| b.to_string()).collect(),
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn links_and_detects_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin_dir = dir.path().join("bin");
+        let drop_dir = dir.path().join("drops/core/wumbo@1.0.0");
+        let exe = drop_dir.join("wumbo");
+        let receipts = Receipts::new(dir.path().join("receipts"));
+        let wumbo = ScopedName::new("core", "wumbo");
+
+        let link = link_binary(&bin_dir, "wumbo", &exe, wumbo, &receipts);
+        assert_eq!(fs::read_link(link.unwrap()).unwrap(), exe);
+
+        // Not yet recorded in a receipt, so it's not known to be ours.
+        let error = link_binary(&bin_dir, "wumbo", &exe, wumbo, &receipts);
+        assert!(matches!(error, Err(LinkError::Unmanaged(_))));
+
+        receipts.insert(&receipt("core/wumbo", &["wumbo"])).unwrap();
+        let new_exe = dir.path().join("drops/core/wumbo@2.0.0/wumbo");
+        link_binary(&bin_dir, "wumbo", &new_exe, wumbo, &receipts).unwrap();
+        assert_eq!(fs::read_link(bin_dir.join("wumbo")).unwrap(), new_exe);
+
+        let other = ScopedName::new("other", "wumbo");
+        let error = link_binary(&bin_dir, "wumbo", &exe, other, &receipts);
+        match error {
+            Err(LinkError::Collision { owner, .. }) => {
+                assert_eq!(owner.to_string(), "core/wumbo");
+            }
+            error => panic!("expected a collision, got {:?}", error),
+        }
+
+        assert!(!unlink_binary(&bin_dir, "wumbo", &drop_dir).unwrap());
+        let new_dir = new_exe.parent().unwrap();
+        assert!(unlink_binary(&bin_dir, "wumbo", new_dir).unwrap());
+        assert!(fs::symlink_metadata(bin_dir.join("wumbo")).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Tracking which installed version of a drop is active.
+
+use super::{bin, LinkError, Receipts};
+use crate::drop::{name::ScopedName, version::Version, Manifest};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the path of the pointer to the active version of `drop`, which is
+/// a symbolic link at `<drops_dir>/<scope>/<name>`.
+///
+/// Paths through this link always refer to the active version, so links in
+/// the bin directory follow it.
+pub fn current_path(drops_dir: &Path, drop: ScopedName<&str>) -> PathBuf {
+    let mut path = drops_dir.join(drop.scope);
+    path.push(drop.name);
+    path
+}
+
+/// Returns the active version of `drop`, if any.
+pub fn current_version(
+    drops_dir: &Path,
+    drop: ScopedName<&str>,
+) -> io::Result<Option<Version>> {
+    let target = match fs::read_link(current_path(drops_dir, drop)) {
+        Ok(target) => target,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(error) => return Err(error),
+    };
+    let prefix = format!("{}@", drop.name);
+    let version = target
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(&prefix));
+    match version {
+        Some(version) => Ok(Some(Version::parse(version))),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "\"{}\" points to \"{}\" instead of a version of \"{}\"",
+                current_path(drops_dir, drop).display(),
+                target.display(),
+                drop,
+            ),
+        )),
+    }
+}
+
+/// Points the active version of `drop` at `version`, atomically replacing the
+/// previous one.
+pub fn set_current(
+    drops_dir: &Path,
+    drop: ScopedName<&str>,
+    version: &Version,
+) -> io::Result<()> {
+    let scope_dir = drops_dir.join(drop.scope);
+    let target = format!("{}@{}", drop.name, version);
+    if !scope_dir.join(&target).is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("\"{}@{}\" is not installed", drop, version),
+        ));
+    }
+
+    let tmp_path = scope_dir.join(format!(".{}.current", drop.name));
+    match fs::remove_file(&tmp_path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            return Err(error);
+        }
+        _ => {}
+    }
+    symlink_dir(Path::new(&target), &tmp_path)?;
+    fs::rename(&tmp_path, current_path(drops_dir, drop))
+}
+
+/// Removes the pointer to the active version of `drop`, if any.
+pub fn clear_current(
+    drops_dir: &Path,
+    drop: ScopedName<&str>,
+) -> io::Result<()> {
+    let path = current_path(drops_dir, drop);
+    match fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(path)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Makes the version of `drop` described by `manifest` the active one and
+/// links its executable into `bin_dir` through the pointer, returning the
+/// names of the linked binaries.
+pub fn activate(
+    drops_dir: &Path,
+    bin_dir: &Path,
+    receipts: &Receipts,
+    drop: ScopedName<&str>,
+    manifest: &Manifest,
+) -> Result<Vec<String>, LinkError> {
+    set_current(drops_dir, drop, &manifest.meta.version)?;
+
+    let exe_path = manifest.meta.exe_path();
+    let binary = match bin::binary_name(exe_path) {
+        Some(binary) => binary,
+        None => return Ok(Vec::new()),
+    };
+    let exe = current_path(drops_dir, drop).join(exe_path);
+    bin::link_binary(bin_dir, binary, &exe, drop, receipts)?;
+    Ok(vec![binary.to_owned()])
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switch_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let drops_dir = dir.path().join("drops");
+        let bin_dir = dir.path().join("bin");
+        let receipts = Receipts::new(dir.path().join("receipts"));
+        let wumbo = ScopedName::new("core", "wumbo");
+
+        let manifest = |version: &str| {
+            let toml = format!(
+                "[meta]\nname = \"wumbo\"\ndescription = \"\"\nversion = {:?}",
+                version,
+            );
+            let manifest = Manifest::parse_toml(&toml).unwrap();
+            let drop_dir = drops_dir.join(format!("core/wumbo@{}", version));
+            fs::create_dir_all(&drop_dir).unwrap();
+            fs::write(drop_dir.join("wumbo"), version).unwrap();
+            manifest
+        };
+        let (old, new) = (manifest("1.0.0"), manifest("2.0.0"));
+
+        assert_eq!(current_version(&drops_dir, wumbo).unwrap(), None);
+        let binaries =
+            activate(&drops_dir, &bin_dir, &receipts, wumbo, &old).unwrap();
+        assert_eq!(binaries, ["wumbo"]);
+        assert_eq!(fs::read(bin_dir.join("wumbo")).unwrap(), b"1.0.0");
+
+        set_current(&drops_dir, wumbo, &new.meta.version).unwrap();
+        assert_eq!(
+            current_version(&drops_dir, wumbo).unwrap(),
+            Some(new.meta.version.clone()),
+        );
+        assert_eq!(fs::read(bin_dir.join("wumbo")).unwrap(), b"2.0.0");
+
+        let missing = Version::parse("3.0.0");
+        let error = set_current(&drops_dir, wumbo, &missing).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        clear_current(&drops_dir, wumbo).unwrap();
+        assert_eq!(current_version(&drops_dir, wumbo).unwrap(), None);
+    }
+}
@@ -3,20 +3,65 @@
 //! Drops can either be installed specifically for the current user executing
 //! Ocean or globally, making them available to _all_ users.
 
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, io, path::PathBuf};
 
+#[cfg(feature = "toml")]
+use crate::drop::{name::ScopedName, Manifest};
+#[cfg(feature = "toml")]
+use std::{collections::BTreeMap, fs, path::Path};
+
+#[cfg(feature = "toml")]
+mod bin;
+#[cfg(feature = "toml")]
+mod current;
+#[cfg(feature = "toml")]
+mod receipt;
 mod target;
+#[cfg(feature = "toml")]
+mod transaction;
+#[cfg(feature = "toml")]
+mod uninstall;
 
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::bin::{binary_name, link_binary, unlink_binary, LinkError};
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::current::{
+    activate, clear_current, current_path, current_version, set_current,
+};
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::receipt::{InstallReason, InstalledFile, Receipt, Receipts};
 #[doc(inline)]
 pub use self::target::InstallTarget;
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::transaction::{Staged, Transaction};
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::uninstall::{Needed, Removal, UninstallPlan};
 
 /// A directory for an `InstallTarget` could not be retrieved.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum DirError {
     /// Could not get the current user's home directory.
     CurrentUserHome,
     /// Could not get the current user's configuration directory.
     CurrentUserConfigDir,
+    /// The user is not in the passwd database.
+    UnknownUser(String),
+    /// The user has no home directory in the passwd database.
+    UserHome(String),
+    /// The passwd database could not be read.
+    UserLookup {
+        /// The user being looked up.
+        username: String,
+        /// The OS error code.
+        errno: i32,
+    },
+    /// Installing for the target is not supported on this platform yet.
+    Unsupported(InstallTarget),
 }
 
 impl Error for DirError {}
@@ -30,6 +75,164 @@ impl fmt::Display for DirError {
             Self::CurrentUserConfigDir => {
                 write!(f, "Could not get current user's config directory")
             }
+            Self::UnknownUser(username) => {
+                write!(f, "No user named \"{}\" exists", username)
+            }
+            Self::UserHome(username) => {
+                write!(f, "User \"{}\" has no home directory", username)
+            }
+            Self::UserLookup { username, errno } => write!(
+                f,
+                "Could not look up user \"{}\": {}",
+                username,
+                io::Error::from_raw_os_error(*errno),
+            ),
+            Self::Unsupported(target) => match target {
+                InstallTarget::CurrentUser => write!(
+                    f,
+                    "Installing drops is not supported on this platform",
+                ),
+                InstallTarget::SpecificUser(username) => write!(
+                    f,
+                    "Installing drops for user \"{}\" is not supported on \
+                     this platform",
+                    username,
+                ),
+                InstallTarget::Global => write!(
+                    f,
+                    "Installing drops globally is not supported on this \
+                     platform",
+                ),
+            },
         }
     }
 }
+
+/// The current process may not modify the drops of an `InstallTarget`.
+#[derive(Debug)]
+pub struct AccessError {
+    /// Whose drops were going to be modified.
+    pub target: InstallTarget,
+    /// The directory that could not be written to.
+    pub path: PathBuf,
+    /// Why the directory could not be written to.
+    pub error: io::Error,
+}
+
+impl Error for AccessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.error.kind() != io::ErrorKind::PermissionDenied {
+            return write!(
+                f,
+                "Could not write to {:?}: {}",
+                self.path, self.error,
+            );
+        }
+        match &self.target {
+            InstallTarget::CurrentUser => {
+                write!(f, "Permission to write to {:?} denied", self.path)
+            }
+            InstallTarget::SpecificUser(username) => write!(
+                f,
+                "Permission denied: {:?} belongs to user \"{}\"; run as {1} \
+                 or with `sudo`",
+                self.path, username,
+            ),
+            InstallTarget::Global => write!(
+                f,
+                "Permission denied: {:?} is shared by all users; run with \
+                 `sudo`",
+                self.path,
+            ),
+        }
+    }
+}
+
+/// A drop found within a drops directory.
+#[cfg(feature = "toml")]
+#[derive(Clone, Debug)]
+pub struct InstalledDrop {
+    /// The drop's name.
+    pub name: ScopedName,
+    /// The directory that the drop was unpacked to.
+    pub path: PathBuf,
+    /// The drop's manifest.
+    pub manifest: Manifest,
+}
+
+/// Reads the drops installed in `drops_dir`, sorted by name and then from
+/// newest to oldest version.
+///
+/// Drops are expected to be at `<drops_dir>/<scope>/<name>@<version>`. A
+/// missing `drops_dir` means that nothing is installed.
+#[cfg(feature = "toml")]
+pub fn installed_drops<P: AsRef<Path>>(
+    drops_dir: P,
+) -> io::Result<Vec<InstalledDrop>> {
+    let mut installed = Vec::new();
+
+    let scopes = match fs::read_dir(drops_dir) {
+        Ok(scopes) => scopes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(installed);
+        }
+        Err(error) => return Err(error),
+    };
+
+    for scope in scopes {
+        let scope = scope?;
+        if !scope.file_type()?.is_dir() {
+            continue;
+        }
+        let scope_name = scope.file_name().to_string_lossy().into_owned();
+
+        for drop in fs::read_dir(scope.path())? {
+            let drop = drop?;
+            let manifest_path = drop.path().join(Manifest::FILE_NAME);
+            if !manifest_path.is_file() {
+                continue;
+            }
+            let file_name = drop.file_name();
+            let file_name = file_name.to_string_lossy();
+            let name = match file_name.rfind('@') {
+                Some(index) => &file_name[..index],
+                None => continue,
+            };
+
+            installed.push(InstalledDrop {
+                name: ScopedName::new(scope_name.as_str(), name),
+                path: drop.path(),
+                manifest: Manifest::read_toml_file(&manifest_path)?,
+            });
+        }
+    }
+
+    installed.sort_by(|a, b| {
+        let (a_version, b_version) =
+            (&a.manifest.meta.version, &b.manifest.meta.version);
+        a.name
+            .cmp(&b.name)
+            .then_with(|| b_version.cmp_precedence(a_version))
+    });
+    Ok(installed)
+}
+
+/// Reads the manifests of the drops installed in `drops_dir`, grouped by name.
+///
+/// See [`installed_drops`](fn.installed_drops.html) for details.
+#[cfg(feature = "toml")]
+pub fn installed_manifests<P: AsRef<Path>>(
+    drops_dir: P,
+) -> io::Result<BTreeMap<ScopedName, Vec<Manifest>>> {
+    let mut installed = BTreeMap::<ScopedName, Vec<Manifest>>::new();
+    for drop in installed_drops(drops_dir)? {
+        installed.entry(drop.name).or_default().push(drop.manifest);
+    }
+    Ok(installed)
+}
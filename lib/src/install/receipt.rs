@@ -0,0 +1,422 @@
+//! Records of installed drops.
+
+use crate::drop::{
+    lock,
+    name::ScopedName,
+    version::{Version, VersionReq},
+    Source,
+};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A record of how a drop version was installed.
+///
+/// Receipts are written when a drop is installed and are stored within a
+/// [`Receipts`](struct.Receipts.html) directory.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Receipt {
+    /// The drop's name.
+    pub name: ScopedName,
+
+    /// The installed version.
+    pub version: Version,
+
+    /// The version requirement that the drop was explicitly requested with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<VersionReq>,
+
+    /// Where the drop was fetched from.
+    pub source: Source,
+
+    /// When the drop was installed, in seconds since the Unix epoch.
+    pub installed_at: u64,
+
+    /// Why the drop was installed.
+    pub reason: InstallReason,
+
+    /// The drops that this drop directly depends on.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<ScopedName>,
+
+    /// The names of the binaries exposed via `$PATH`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<String>,
+
+    // Tables: all types that serialize into maps (or "tables" in TOML)
+    // them must be placed last to succeed.
+    /// The installed files, relative to the drop's directory.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<InstalledFile>,
+}
+
+/// Why a drop was installed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallReason {
+    /// The drop was requested by the user or by a manifest being installed.
+    Explicit,
+    /// The drop was pulled in as a dependency of another drop.
+    Dependency,
+}
+
+/// A file within an installed drop.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledFile {
+    /// The path relative to the drop's directory, using `/` as a separator.
+    pub path: String,
+
+    /// The hex-encoded SHA-256 digest of the file's contents, or `None` if
+    /// the file is a symbolic link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// Whether the file is executable.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub executable: bool,
+}
+
+impl InstalledFile {
+    /// Returns the files within `dir`, sorted by path.
+    pub fn scan<P: AsRef<Path>>(dir: P) -> io::Result<Vec<Self>> {
+        let mut files = Vec::new();
+        scan_dir(dir.as_ref(), "", &mut files)?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// Returns whether the file within `dir` still has its recorded
+    /// contents.
+    pub fn is_intact<P: AsRef<Path>>(&self, dir: P) -> io::Result<bool> {
+        let path = dir.as_ref().join(&self.path);
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(false);
+            }
+            Err(error) => return Err(error),
+        };
+        match &self.sha256 {
+            None => Ok(metadata.file_type().is_symlink()),
+            Some(_) if !metadata.is_file() => Ok(false),
+            Some(sha256) => {
+                Ok(lock::checksum(fs::File::open(path)?)? == *sha256)
+            }
+        }
+    }
+}
+
+fn scan_dir(
+    dir: &Path,
+    prefix: &str,
+    files: &mut Vec<InstalledFile>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let path = format!("{}{}", prefix, name.to_string_lossy());
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            scan_dir(&entry.path(), &format!("{}/", path), files)?;
+        } else if file_type.is_symlink() {
+            files.push(InstalledFile {
+                path,
+                sha256: None,
+                executable: false,
+            });
+        } else {
+            let sha256 = lock::checksum(fs::File::open(entry.path())?)?;
+            files.push(InstalledFile {
+                path,
+                sha256: Some(sha256),
+                executable: is_executable(&entry.metadata()?),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+impl Receipt {
+    /// Returns the current time in seconds since the Unix epoch, suitable for
+    /// `installed_at`.
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the drop was requested explicitly.
+    #[inline]
+    pub fn is_explicit(&self) -> bool {
+        self.reason == InstallReason::Explicit
+    }
+
+    /// Parses a receipt from [TOML](https://en.wikipedia.org/wiki/TOML).
+    pub fn parse_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::de::from_str(toml)
+    }
+
+    /// Returns `self` as a TOML string.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+}
+
+/// A directory of [`Receipt`](struct.Receipt.html)s for the drops installed
+/// for an [`InstallTarget`](enum.InstallTarget.html).
+///
+/// Each receipt is stored at `<dir>/<scope>/<name>@<version>.toml`. Writes
+/// replace receipts atomically, so readers never observe partial receipts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Receipts {
+    dir: PathBuf,
+}
+
+impl Receipts {
+    /// Uses the receipts within `dir`, which need not exist yet.
+    #[inline]
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the directory containing the receipts.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns where the receipt for `drop` at `version` is stored.
+    pub fn path(&self, drop: ScopedName<&str>, version: &Version) -> PathBuf {
+        let mut path = self.dir.join(drop.scope);
+        path.push(format!("{}@{}.toml", drop.name, version));
+        path
+    }
+
+    /// Reads the receipt for `drop` at `version`, if it exists.
+    pub fn get(
+        &self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> io::Result<Option<Receipt>> {
+        read(&self.path(drop, version))
+    }
+
+    /// Reads all receipts, sorted by name and then from newest to oldest
+    /// version.
+    pub fn all(&self) -> io::Result<Vec<Receipt>> {
+        let mut receipts = Vec::new();
+        let scopes = match fs::read_dir(&self.dir) {
+            Ok(scopes) => scopes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(receipts);
+            }
+            Err(error) => return Err(error),
+        };
+        for scope in scopes {
+            let scope = scope?;
+            if !scope.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(scope.path())? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    receipts.extend(read(&path)?);
+                }
+            }
+        }
+        receipts.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| b.version.cmp_precedence(&a.version))
+        });
+        Ok(receipts)
+    }
+
+    /// Reads the receipts of all installed versions of `drop`, from newest to
+    /// oldest.
+    pub fn versions(&self, drop: ScopedName<&str>) -> io::Result<Vec<Receipt>> {
+        let mut receipts = self.all()?;
+        receipts.retain(|receipt| {
+            receipt.name.scope == drop.scope && receipt.name.name == drop.name
+        });
+        Ok(receipts)
+    }
+
+    /// Writes `receipt`, replacing any existing receipt for the same drop
+    /// version.
+    pub fn insert(&self, receipt: &Receipt) -> io::Result<()> {
+        let path = self.path(receipt.name.to_ref(), &receipt.version);
+        let toml = receipt.to_toml().map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, error)
+        })?;
+
+        let dir = path.parent().unwrap_or(&self.dir);
+        fs::DirBuilder::new().recursive(true).create(dir)?;
+
+        // Write to a temporary file first so that the receipt is replaced
+        // atomically.
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(toml.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &path)
+    }
+
+    /// Applies `f` to the receipt for `drop` at `version` and writes the
+    /// result, returning it if the receipt exists.
+    pub fn update<F>(
+        &self,
+        drop: ScopedName<&str>,
+        version: &Version,
+        f: F,
+    ) -> io::Result<Option<Receipt>>
+    where
+        F: FnOnce(&mut Receipt),
+    {
+        match self.get(drop, version)? {
+            Some(mut receipt) => {
+                f(&mut receipt);
+                self.insert(&receipt)?;
+                Ok(Some(receipt))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the receipt for `drop` at `version`, returning whether it
+    /// existed.
+    pub fn remove(
+        &self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> io::Result<bool> {
+        match fs::remove_file(self.path(drop, version)) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+fn read(path: &Path) -> io::Result<Option<Receipt>> {
+    let toml = match fs::read_to_string(path) {
+        Ok(toml) => toml,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(error) => return Err(error),
+    };
+    Receipt::parse_toml(&toml).map(Some).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid receipt \"{}\": {}", path.display(), error),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(name: &str, version: &str) -> Receipt {
+        Receipt {
+            name: ScopedName::parse(name).unwrap(),
+            version: Version::parse(version),
+            requirement: Some(VersionReq::any()),
+            source: Source::main_registry().clone(),
+            installed_at: 1_600_000_000,
+            reason: InstallReason::Explicit,
+            dependencies: vec![ScopedName::parse("core/dep").unwrap()],
+            binaries: vec!["wumbo".to_owned()],
+            files: vec![InstalledFile {
+                path: "bin/wumbo".to_owned(),
+                sha256: Some("00".repeat(32)),
+                executable: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let receipt = receipt("core/wumbo", "1.2.3.4");
+        let toml = receipt.to_toml().unwrap();
+        assert_eq!(Receipt::parse_toml(&toml).unwrap(), receipt);
+    }
+
+    #[test]
+    fn insert_query_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let receipts = Receipts::new(dir.path().join("receipts"));
+        assert!(receipts.all().unwrap().is_empty());
+
+        let old = receipt("core/wumbo", "1.0.0");
+        let new = receipt("core/wumbo", "2.0.0");
+        let other = receipt("other/thing", "r1234");
+        for receipt in &[&old, &other, &new] {
+            receipts.insert(receipt).unwrap();
+        }
+
+        let wumbo = ScopedName::new("core", "wumbo");
+        assert_eq!(receipts.all().unwrap(), [new.clone(), old.clone(), other]);
+        assert_eq!(
+            receipts.versions(wumbo).unwrap(),
+            [new.clone(), old.clone()]
+        );
+
+        let updated = receipts
+            .update(wumbo, &old.version, |receipt| {
+                receipt.reason = InstallReason::Dependency;
+            })
+            .unwrap()
+            .unwrap();
+        assert!(!updated.is_explicit());
+        assert_eq!(receipts.get(wumbo, &old.version).unwrap(), Some(updated));
+
+        assert!(receipts.remove(wumbo, &old.version).unwrap());
+        assert!(!receipts.remove(wumbo, &old.version).unwrap());
+        assert_eq!(receipts.versions(wumbo).unwrap(), [new]);
+    }
+
+    #[test]
+    fn scan_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("bin")).unwrap();
+        fs::write(dir.path().join("bin/wumbo"), "hi").unwrap();
+        fs::write(dir.path().join("README"), "...").unwrap();
+
+        let files = InstalledFile::scan(dir.path()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["README", "bin/wumbo"]);
+        assert!(files[1].is_intact(dir.path()).unwrap());
+
+        fs::write(dir.path().join("bin/wumbo"), "bye").unwrap();
+        assert!(!files[1].is_intact(dir.path()).unwrap());
+        fs::remove_file(dir.path().join("README")).unwrap();
+        assert!(!files[0].is_intact(dir.path()).unwrap());
+    }
+}
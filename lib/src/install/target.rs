@@ -1,6 +1,11 @@
-use super::DirError;
+use super::{AccessError, DirError};
 use shared::ext::PathBufExt;
-use std::path::PathBuf;
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 
 /// Indicates where to (un)install a drop.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -27,6 +32,31 @@ impl Default for InstallTarget {
 }
 
 impl InstallTarget {
+    /// Returns the directory where drops, receipts, and binaries for the
+    /// installation target are stored.
+    ///
+    /// # Examples
+    ///
+    /// - `CurrentUser`:  `/home/alice/.ocean`
+    /// - `SpecificUser`: `/home/bob/.ocean`
+    /// - `Global`:       `/usr/local/Ocean` (`/Library/Ocean` on macOS)
+    pub fn home_dir(&self) -> Result<Cow<'static, Path>, DirError> {
+        match self {
+            Self::CurrentUser => dirs::home_dir()
+                .ok_or(DirError::CurrentUserHome)
+                .map(|home| Cow::Owned(home.pushing(".ocean"))),
+            Self::SpecificUser(username) => user_home(username)
+                .map(|home| Cow::Owned(home.pushing(".ocean"))),
+            Self::Global => {
+                if cfg!(target_os = "macos") {
+                    Ok(Cow::Borrowed("/Library/Ocean".as_ref()))
+                } else {
+                    Ok(Cow::Borrowed("/usr/local/Ocean".as_ref()))
+                }
+            }
+        }
+    }
+
     /// Returns the configuration files directory for the installation target.
     ///
     /// # Examples
@@ -35,18 +65,155 @@ impl InstallTarget {
     ///
     /// - Windows: `C:\Users\Alice\AppData\Roaming\Ocean`
     /// - macOS:   `/Users/Alice/Library/Preferences/Ocean`
-    /// - Linux:   `/home/alice/.config`
+    /// - Linux:   `/home/alice/.config/Ocean`
+    ///
+    /// Other users' directories are found the same way from their home
+    /// directory. For `InstallTarget::Global`, the directory is `/etc/ocean`,
+    /// or `/Library/Preferences/Ocean` on macOS.
     pub fn cfg_dir(&self) -> Result<PathBuf, DirError> {
         match self {
             Self::CurrentUser => dirs::config_dir()
                 .ok_or(DirError::CurrentUserConfigDir)
                 .map(|cfg| cfg.pushing("Ocean")),
             Self::SpecificUser(username) => {
-                unimplemented!("TODO: Get config directory for {:?}", username);
+                let home = user_home(username)?;
+                if cfg!(target_os = "macos") {
+                    Ok(home.pushing("Library/Preferences/Ocean"))
+                } else {
+                    Ok(home.pushing(".config/Ocean"))
+                }
             }
             Self::Global => {
-                unimplemented!("TODO: Get global config directory");
+                if cfg!(target_os = "macos") {
+                    Ok("/Library/Preferences/Ocean".into())
+                } else {
+                    Ok("/etc/ocean".into())
+                }
+            }
+        }
+    }
+
+    /// Checks that the current process may write to `dir` on behalf of the
+    /// installation target, creating `dir` if it doesn't exist.
+    ///
+    /// This is meant to be called before making any changes, so that a
+    /// missing `sudo` is reported up front rather than partway through.
+    pub fn check_access(&self, dir: &Path) -> Result<(), AccessError> {
+        let probe = dir.join(format!(".access-{}", process::id()));
+        fs::create_dir_all(dir)
+            .and_then(|()| {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&probe)
+            })
+            .and_then(|_| fs::remove_file(&probe))
+            .map_err(|error| AccessError {
+                target: self.clone(),
+                path: dir.to_path_buf(),
+                error,
+            })
+    }
+}
+
+/// Returns the home directory of `username` from the passwd database.
+#[cfg(unix)]
+fn user_home(username: &str) -> Result<PathBuf, DirError> {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        mem,
+        os::unix::ffi::OsStrExt,
+        ptr,
+    };
+
+    let unknown = || DirError::UnknownUser(username.to_owned());
+    let name = CString::new(username).map_err(|_| unknown())?;
+
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        // SAFETY: `passwd` is plain data that `getpwnam_r` fills in with
+        // pointers into `buf`, which outlives its use below.
+        let mut passwd: libc::passwd = unsafe { mem::zeroed() };
+        let mut result = ptr::null_mut();
+        let code = unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                &mut passwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match code {
+            0 if result.is_null() => return Err(unknown()),
+            0 => {}
+            libc::ERANGE if buf.len() < 1 << 20 => {
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+            errno => {
+                return Err(DirError::UserLookup {
+                    username: username.to_owned(),
+                    errno,
+                });
             }
         }
+
+        let home = unsafe { CStr::from_ptr(passwd.pw_dir) }.to_bytes();
+        if home.is_empty() {
+            return Err(DirError::UserHome(username.to_owned()));
+        }
+        return Ok(OsStr::from_bytes(home).into());
+    }
+}
+
+/// Other users' home directories can't be looked up on Windows yet.
+#[cfg(windows)]
+fn user_home(username: &str) -> Result<PathBuf, DirError> {
+    Err(DirError::Unsupported(InstallTarget::SpecificUser(
+        username.to_owned(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_dirs() {
+        let root = InstallTarget::SpecificUser("root".to_owned());
+        if cfg!(target_os = "linux") {
+            assert_eq!(root.home_dir().unwrap(), Path::new("/root/.ocean"));
+            assert_eq!(
+                root.cfg_dir().unwrap(),
+                Path::new("/root/.config/Ocean")
+            );
+        }
+
+        let missing = "no-such-ocean-user";
+        let target = InstallTarget::SpecificUser(missing.to_owned());
+        assert_eq!(
+            target.home_dir(),
+            Err(DirError::UnknownUser(missing.to_owned())),
+        );
+        assert_eq!(
+            InstallTarget::SpecificUser("nul\0".to_owned()).cfg_dir(),
+            Err(DirError::UnknownUser("nul\0".to_owned())),
+        );
+    }
+
+    #[test]
+    fn check_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let drops = dir.path().join("Ocean/drops");
+        InstallTarget::Global.check_access(&drops).unwrap();
+        assert!(drops.is_dir());
+        assert_eq!(fs::read_dir(&drops).unwrap().count(), 0);
+
+        fs::write(dir.path().join("file"), "").unwrap();
+        let error = InstallTarget::Global
+            .check_access(&dir.path().join("file/drops"))
+            .unwrap_err();
+        assert_eq!(error.target, InstallTarget::Global);
     }
 }
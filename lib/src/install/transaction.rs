@@ -0,0 +1,673 @@
+//! Installing several drops at once, all or nothing.
+
+use super::{bin, current, InstalledFile, LinkError, Receipt, Receipts};
+use crate::{
+    archive,
+    drop::{name::ScopedName, version::Version, Manifest},
+};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+const JOURNAL_FILE: &str = "journal.toml";
+
+const LOCK_FILE: &str = "transaction.lock";
+
+/// Drops staged to be installed together.
+///
+/// Drops are unpacked into a staging directory next to the drops directory.
+/// Nothing outside of it changes until [`commit`](#method.commit), which moves
+/// the drops into place with renames. If committing fails, every change is
+/// rolled back. The changes are journaled beforehand so that an interrupted
+/// commit can be rolled back by [`recover`](#method.recover).
+///
+/// A transaction holds an exclusive lock on a file next to the drops directory
+/// for as long as it lives, so only one can be in progress at a time.
+///
+/// Dropping a transaction without committing it discards the staged drops.
+#[derive(Debug)]
+pub struct Transaction {
+    dir: PathBuf,
+    drops_dir: PathBuf,
+    bin_dir: PathBuf,
+    receipts: Receipts,
+    staged: Vec<Staged>,
+    finished: bool,
+    // Declared last so that it's released after `Drop` cleans up.
+    _lock: fs::File,
+}
+
+/// A drop unpacked by [`Transaction::stage`](struct.Transaction.html#method.stage).
+#[derive(Clone, Debug)]
+pub struct Staged {
+    /// Where the drop was unpacked to.
+    pub path: PathBuf,
+    /// The drop's manifest.
+    pub manifest: Manifest,
+    /// The receipt to record once the drop is installed.
+    pub receipt: Receipt,
+}
+
+/// What a commit changes for a drop, and what it takes to undo it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Entry {
+    name: ScopedName,
+    version: Version,
+    /// Whether the same version was already installed.
+    replaced: bool,
+    /// Whether the same version already had a receipt.
+    had_receipt: bool,
+    /// The previously active version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous: Option<Version>,
+    /// The binary that gets linked, if it didn't already exist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_binary: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Journal {
+    #[serde(default)]
+    drops: Vec<Entry>,
+}
+
+impl Transaction {
+    /// Returns the directory used by transactions for `drops_dir`, which is
+    /// next to it.
+    pub fn dir_for(drops_dir: &Path) -> PathBuf {
+        drops_dir.with_file_name("transaction")
+    }
+
+    /// Starts a transaction for installing drops into `drops_dir`, with
+    /// receipts in `receipts` and binaries linked into `bin_dir`.
+    ///
+    /// This fails if another transaction is in progress, or if one was
+    /// interrupted and needs to be [recovered](#method.recover).
+    pub fn begin<A, B>(
+        drops_dir: A,
+        bin_dir: B,
+        receipts: Receipts,
+    ) -> io::Result<Self>
+    where
+        A: Into<PathBuf>,
+        B: Into<PathBuf>,
+    {
+        let drops_dir = drops_dir.into();
+        let lock = match lock(&drops_dir)? {
+            Some(lock) => lock,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "another installation is in progress",
+                ));
+            }
+        };
+        let dir = Self::dir_for(&drops_dir);
+        match fs::create_dir(&dir) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "an interrupted installation needs to be rolled \
+                         back (\"{}\" exists)",
+                        dir.display(),
+                    ),
+                ));
+            }
+            result => result?,
+        }
+        Ok(Self {
+            dir,
+            drops_dir,
+            bin_dir: bin_dir.into(),
+            receipts,
+            staged: Vec::new(),
+            finished: false,
+            _lock: lock,
+        })
+    }
+
+    /// Returns the drops staged so far.
+    #[inline]
+    pub fn staged(&self) -> &[Staged] {
+        &self.staged
+    }
+
+    /// Unpacks the drop in `tarball` into the staging directory, to be
+    /// installed with `receipt`.
+    ///
+    /// The drop must contain its executable. The files and binaries of
+    /// `receipt` are filled in by the transaction.
+    pub fn stage<R: io::Read>(
+        &mut self,
+        tarball: R,
+        mut receipt: Receipt,
+    ) -> io::Result<&Staged> {
+        let name = &receipt.name;
+        let scope_dir = self.dir.join("staging").join(&name.scope);
+        let path = scope_dir.join(format!("{}@{}", name.name, receipt.version));
+        if path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("\"{}@{}\" is already staged", name, receipt.version),
+            ));
+        }
+
+        // Unpack into a fresh directory since the archive's own directory
+        // name isn't necessarily the one we want.
+        let unpack_dir = scope_dir.join(format!(".{}.unpack", name.name));
+        let unpacked = archive::unpack_tarball(tarball, &unpack_dir);
+        let moved = unpacked.and_then(|unpacked| {
+            let meta = &unpacked.manifest.meta;
+            if meta.name != name.name || meta.version != receipt.version {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "expected \"{}@{}\" but found \"{}@{}\"",
+                        name, receipt.version, meta.name, meta.version,
+                    ),
+                ));
+            }
+            match unpacked.manifest_path.parent() {
+                Some(dir) => fs::rename(dir, &path)?,
+                None => return Err(io::ErrorKind::InvalidData.into()),
+            }
+            Ok(unpacked.manifest)
+        });
+        let _ = fs::remove_dir_all(&unpack_dir);
+        let manifest = moved?;
+
+        let exe_path = path.join(manifest.meta.exe_path());
+        if !exe_path.is_file() {
+            let _ = fs::remove_dir_all(&path);
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("file not found: \"{}\"", exe_path.display()),
+            ));
+        }
+
+        receipt.files = InstalledFile::scan(&path)?;
+        receipt.binaries = Vec::new();
+        self.staged.push(Staged {
+            path,
+            manifest,
+            receipt,
+        });
+        Ok(self.staged.last().unwrap())
+    }
+
+    /// Installs the staged drops, making each one the active version.
+    ///
+    /// Binaries that can't be linked because of other drops are reported
+    /// without failing the commit. On failure, all changes are rolled back.
+    pub fn commit(mut self) -> io::Result<Vec<LinkError>> {
+        let journal = self.journal()?;
+        self.write_journal(&journal)?;
+        // From here on, the journal determines how to clean up.
+        self.finished = true;
+
+        let mut link_errors = Vec::new();
+        let result = self.apply(&journal, &mut link_errors);
+        match result {
+            Ok(()) => {
+                fs::remove_file(self.dir.join(JOURNAL_FILE))?;
+                fs::remove_dir_all(&self.dir)?;
+                Ok(link_errors)
+            }
+            Err(error) => {
+                rollback(
+                    &self.dir,
+                    &self.drops_dir,
+                    &self.bin_dir,
+                    &self.receipts,
+                    &journal,
+                )?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Rolls back a transaction for `drops_dir` that was interrupted,
+    /// returning whether there was one.
+    ///
+    /// This should be called before starting another transaction. A
+    /// transaction that's still in progress holds the lock, so it's left
+    /// alone.
+    pub fn recover(
+        drops_dir: &Path,
+        bin_dir: &Path,
+        receipts: &Receipts,
+    ) -> io::Result<bool> {
+        let dir = Self::dir_for(drops_dir);
+        if !dir.exists() {
+            return Ok(false);
+        }
+        let _lock = match lock(drops_dir)? {
+            Some(lock) => lock,
+            None => return Ok(false),
+        };
+        // The transaction may have finished while waiting for the lock.
+        if !dir.exists() {
+            return Ok(false);
+        }
+        match fs::read_to_string(dir.join(JOURNAL_FILE)) {
+            Ok(toml) => {
+                let journal: Journal =
+                    toml::from_str(&toml).map_err(|error| {
+                        io::Error::new(io::ErrorKind::InvalidData, error)
+                    })?;
+                rollback(&dir, drops_dir, bin_dir, receipts, &journal)?;
+            }
+            // The commit never started, so only staged files are left.
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                fs::remove_dir_all(&dir)?;
+            }
+            Err(error) => return Err(error),
+        }
+        Ok(true)
+    }
+
+    fn drop_path(&self, drop: ScopedName<&str>, version: &Version) -> PathBuf {
+        let mut path = self.drops_dir.join(drop.scope);
+        path.push(format!("{}@{}", drop.name, version));
+        path
+    }
+
+    /// Records the state that committing changes.
+    fn journal(&self) -> io::Result<Journal> {
+        let mut journal = Journal::default();
+        for staged in &self.staged {
+            let name = staged.receipt.name.to_ref();
+            let version = &staged.receipt.version;
+            let new_binary = bin::binary_name(staged.manifest.meta.exe_path())
+                .filter(|binary| {
+                    fs::symlink_metadata(self.bin_dir.join(binary)).is_err()
+                })
+                .map(str::to_owned);
+            journal.drops.push(Entry {
+                name: name.to_owned(),
+                version: version.clone(),
+                replaced: self.drop_path(name, version).exists(),
+                had_receipt: self.receipts.path(name, version).exists(),
+                previous: current::current_version(&self.drops_dir, name)?,
+                new_binary,
+            });
+        }
+        Ok(journal)
+    }
+
+    fn write_journal(&self, journal: &Journal) -> io::Result<()> {
+        let toml = toml::to_string(journal).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, error)
+        })?;
+        let tmp_path = self.dir.join(format!("{}.tmp", JOURNAL_FILE));
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(toml.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(tmp_path, self.dir.join(JOURNAL_FILE))
+    }
+
+    fn apply(
+        &self,
+        journal: &Journal,
+        link_errors: &mut Vec<LinkError>,
+    ) -> io::Result<()> {
+        for (entry, staged) in journal.drops.iter().zip(&self.staged) {
+            let name = entry.name.to_ref();
+            let version = &entry.version;
+
+            let path = self.drop_path(name, version);
+            if entry.replaced {
+                let backup = backup_path(&self.dir, name, version);
+                create_parent(&backup)?;
+                fs::rename(&path, backup)?;
+            }
+            create_parent(&path)?;
+            fs::rename(&staged.path, &path)?;
+
+            let mut receipt = staged.receipt.clone();
+            let activated = current::activate(
+                &self.drops_dir,
+                &self.bin_dir,
+                &self.receipts,
+                name,
+                &staged.manifest,
+            );
+            match activated {
+                Ok(binaries) => receipt.binaries = binaries,
+                Err(LinkError::Io(error)) => return Err(error),
+                Err(error) => link_errors.push(error),
+            }
+
+            // The old receipt is only backed up now, since it's what shows
+            // that the binaries being relinked belong to this drop.
+            if entry.had_receipt {
+                let backup = receipt_backup_path(&self.dir, name, version);
+                create_parent(&backup)?;
+                fs::rename(self.receipts.path(name, version), backup)?;
+            }
+            self.receipts.insert(&receipt)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// Takes the lock for transactions on `drops_dir`, returning `None` if it's
+/// held by another transaction.
+///
+/// The lock is released when the returned file is closed, which the OS also
+/// does if the process dies.
+fn lock(drops_dir: &Path) -> io::Result<Option<fs::File>> {
+    let path = drops_dir.with_file_name(LOCK_FILE);
+    if let Some(parent) = path.parent() {
+        fs::DirBuilder::new().recursive(true).create(parent)?;
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    if try_lock_exclusive(&file)? {
+        Ok(Some(file))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(unix)]
+fn try_lock_exclusive(file: &fs::File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: `flock` only operates on the file descriptor, which `file`
+    // keeps open.
+    let code =
+        unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if code == 0 {
+        return Ok(true);
+    }
+    let error = io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::EWOULDBLOCK) => Ok(false),
+        _ => Err(error),
+    }
+}
+
+/// Installing isn't supported on Windows yet, so there's nothing to lock.
+#[cfg(not(unix))]
+fn try_lock_exclusive(_file: &fs::File) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Undoes the changes described by `journal`, in reverse, and then removes
+/// the transaction's directory.
+///
+/// Every step checks what has already been done, so this is safe to run
+/// again if it gets interrupted.
+fn rollback(
+    dir: &Path,
+    drops_dir: &Path,
+    bin_dir: &Path,
+    receipts: &Receipts,
+    journal: &Journal,
+) -> io::Result<()> {
+    for entry in journal.drops.iter().rev() {
+        let name = entry.name.to_ref();
+        let version = &entry.version;
+
+        let mut path = drops_dir.join(name.scope);
+        path.push(format!("{}@{}", name.name, version));
+        let backup = backup_path(dir, name, version);
+        if backup.exists() {
+            remove_dir_all(&path)?;
+            fs::rename(&backup, &path)?;
+        } else if !entry.replaced {
+            remove_dir_all(&path)?;
+        }
+
+        let receipt_backup = receipt_backup_path(dir, name, version);
+        if receipt_backup.exists() {
+            fs::rename(&receipt_backup, receipts.path(name, version))?;
+        } else if !entry.had_receipt {
+            receipts.remove(name, version)?;
+        }
+
+        match &entry.previous {
+            Some(previous) => current::set_current(drops_dir, name, previous)?,
+            None => current::clear_current(drops_dir, name)?,
+        }
+
+        if let Some(binary) = &entry.new_binary {
+            let link = bin_dir.join(binary);
+            let is_link = fs::symlink_metadata(&link)
+                .is_ok_and(|metadata| metadata.file_type().is_symlink());
+            if is_link {
+                fs::remove_file(link)?;
+            }
+        }
+
+        // Clean up the scope's directories if they were created for this.
+        let receipt_path = receipts.path(name, version);
+        for scope_dir in [path.parent(), receipt_path.parent()].iter().flatten()
+        {
+            let _ = fs::remove_dir(scope_dir);
+        }
+    }
+
+    // Removing the journal marks the rollback as done.
+    match fs::remove_file(dir.join(JOURNAL_FILE)) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            return Err(error);
+        }
+        _ => {}
+    }
+    fs::remove_dir_all(dir)
+}
+
+fn backup_path(
+    dir: &Path,
+    drop: ScopedName<&str>,
+    version: &Version,
+) -> PathBuf {
+    let mut path = dir.join("backup");
+    path.push(drop.scope);
+    path.push(format!("{}@{}", drop.name, version));
+    path
+}
+
+fn receipt_backup_path(
+    dir: &Path,
+    drop: ScopedName<&str>,
+    version: &Version,
+) -> PathBuf {
+    let mut path = dir.join("backup-receipts");
+    path.push(drop.scope);
+    path.push(format!("{}@{}.toml", drop.name, version));
+    path
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::DirBuilder::new().recursive(true).create(parent),
+        None => Ok(()),
+    }
+}
+
+fn remove_dir_all(path: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{drop::Source, install::InstallReason};
+    use flate2::{write::GzEncoder, Compression};
+
+    struct Dirs {
+        _root: tempfile::TempDir,
+        drops: PathBuf,
+        bin: PathBuf,
+        receipts: Receipts,
+    }
+
+    fn dirs() -> Dirs {
+        let root = tempfile::tempdir().unwrap();
+        Dirs {
+            drops: root.path().join("drops"),
+            bin: root.path().join("bin"),
+            receipts: Receipts::new(root.path().join("receipts")),
+            _root: root,
+        }
+    }
+
+    /// Creates a `.tar.gz` of a drop whose executable prints `contents`.
+    fn tarball(name: &str, version: &str, contents: &str) -> Vec<u8> {
+        let manifest = format!(
+            "[meta]\nname = {:?}\ndescription = \"\"\nversion = {:?}\n",
+            name, version,
+        );
+        let gz = GzEncoder::new(Vec::new(), Compression::fast());
+        let mut builder = tar::Builder::new(gz);
+        let dir = format!("{}@{}", name, version);
+        for (path, data) in &[("Ocean.toml", &*manifest), (name, contents)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            let path = format!("{}/{}", dir, path);
+            builder
+                .append_data(&mut header, path, data.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn receipt(name: &str, version: &str) -> Receipt {
+        Receipt {
+            name: ScopedName::new("core", name),
+            version: Version::parse(version),
+            requirement: None,
+            source: Source::main_registry().clone(),
+            installed_at: 0,
+            reason: InstallReason::Explicit,
+            dependencies: Vec::new(),
+            binaries: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn begin(dirs: &Dirs, drops: &[(&str, &str, &str)]) -> Transaction {
+        let receipts = dirs.receipts.clone();
+        let mut txn =
+            Transaction::begin(&dirs.drops, &dirs.bin, receipts).unwrap();
+        for &(name, version, contents) in drops {
+            let tarball = tarball(name, version, contents);
+            txn.stage(tarball.as_slice(), receipt(name, version))
+                .unwrap();
+        }
+        txn
+    }
+
+    fn run(dirs: &Dirs, binary: &str) -> String {
+        fs::read_to_string(dirs.bin.join(binary)).unwrap()
+    }
+
+    #[test]
+    fn commits_all_drops() {
+        let dirs = dirs();
+        let txn = begin(&dirs, &[("a", "1.0.0", "a1"), ("b", "1.0.0", "b1")]);
+        assert!(txn.commit().unwrap().is_empty());
+
+        assert_eq!(run(&dirs, "a"), "a1");
+        assert_eq!(run(&dirs, "b"), "b1");
+        let receipts = dirs.receipts.all().unwrap();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].binaries, ["a"]);
+        assert!(!Transaction::dir_for(&dirs.drops).exists());
+
+        // Reinstalling the same version relinks its binary.
+        let txn = begin(&dirs, &[("a", "1.0.0", "a2")]);
+        assert!(txn.commit().unwrap().is_empty());
+        assert_eq!(run(&dirs, "a"), "a2");
+        assert_eq!(dirs.receipts.all().unwrap()[0].binaries, ["a"]);
+    }
+
+    #[test]
+    fn rolls_back_failed_commit() {
+        let dirs = dirs();
+        begin(&dirs, &[("a", "1.0.0", "a1")]).commit().unwrap();
+        let before = dirs.receipts.all().unwrap();
+
+        // Reinstall `a` and install `b`, which fails since its scope's
+        // directory can't be created.
+        let txn = begin(&dirs, &[("a", "1.0.0", "a2"), ("b", "1.0.0", "b1")]);
+        let other = ScopedName::<&str>::new("other", "b");
+        fs::write(dirs.drops.join(other.scope), "").unwrap();
+        let mut txn = txn;
+        txn.staged[1].receipt.name = other.to_owned();
+        assert!(txn.commit().is_err());
+
+        assert_eq!(run(&dirs, "a"), "a1");
+        assert!(fs::symlink_metadata(dirs.bin.join("b")).is_err());
+        assert_eq!(dirs.receipts.all().unwrap(), before);
+        assert!(!Transaction::dir_for(&dirs.drops).exists());
+    }
+
+    #[test]
+    fn recovers_interrupted_commit() {
+        let dirs = dirs();
+        begin(&dirs, &[("a", "1.0.0", "a1")]).commit().unwrap();
+        let before = dirs.receipts.all().unwrap();
+
+        // Apply only the first of the journaled changes, as if the process
+        // was killed midway.
+        let mut txn =
+            begin(&dirs, &[("a", "2.0.0", "a2"), ("b", "1.0.0", "b1")]);
+        let journal = txn.journal().unwrap();
+        txn.write_journal(&journal).unwrap();
+        txn.finished = true;
+        txn.staged.truncate(1);
+        txn.apply(&journal, &mut Vec::new()).unwrap();
+        drop(txn);
+        assert_eq!(run(&dirs, "a"), "a2");
+
+        let (drops, bin) = (&dirs.drops, &dirs.bin);
+        assert!(Transaction::recover(drops, bin, &dirs.receipts).unwrap());
+        assert_eq!(run(&dirs, "a"), "a1");
+        assert!(!drops.join("core/a@2.0.0").exists());
+        assert_eq!(dirs.receipts.all().unwrap(), before);
+        assert!(!Transaction::recover(drops, bin, &dirs.receipts).unwrap());
+    }
+
+    #[test]
+    fn leaves_transaction_in_progress_alone() {
+        let dirs = dirs();
+        let txn = begin(&dirs, &[("a", "1.0.0", "a1")]);
+        let staged = txn.staged()[0].path.clone();
+
+        let (drops, bin) = (&dirs.drops, &dirs.bin);
+        assert!(!Transaction::recover(drops, bin, &dirs.receipts).unwrap());
+        assert!(staged.exists());
+        let receipts = dirs.receipts.clone();
+        let error = Transaction::begin(drops, bin, receipts).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+
+        assert!(txn.commit().unwrap().is_empty());
+        assert_eq!(run(&dirs, "a"), "a1");
+    }
+}
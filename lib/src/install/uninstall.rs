@@ -0,0 +1,469 @@
+//! Removing installed drops.
+
+use super::{bin, current, InstalledDrop, LinkError, Receipt, Receipts};
+use crate::drop::{name::ScopedName, version::Version, Manifest};
+use std::{fs, io, path::Path};
+
+/// What uninstalling a set of requested drops entails.
+#[derive(Clone, Debug)]
+pub struct UninstallPlan {
+    /// The drops to remove, starting with the requested ones.
+    pub remove: Vec<Removal>,
+    /// The requested drops that remaining drops still depend on.
+    pub needed: Vec<Needed>,
+}
+
+/// An installed drop that is to be removed.
+#[derive(Clone, Debug)]
+pub struct Removal {
+    /// The drop to remove.
+    pub drop: InstalledDrop,
+    /// The drop's receipt, if it has one.
+    pub receipt: Option<Receipt>,
+    /// Whether the drop wasn't requested but is no longer needed.
+    pub orphaned: bool,
+}
+
+/// A requested drop that other drops depend on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Needed {
+    /// The requested drop.
+    pub name: ScopedName,
+    /// The requested drop's version.
+    pub version: Version,
+    /// The remaining drops that depend on it.
+    pub dependents: Vec<(ScopedName, Version)>,
+}
+
+impl UninstallPlan {
+    /// Plans the removal of the drops in `installed` that are `requested`.
+    ///
+    /// A drop is needed if a remaining drop depends on it and no other
+    /// version of it remains. If `autoremove` is `true`, dependencies of
+    /// removed drops that were not explicitly installed are also removed once
+    /// nothing else depends on them.
+    pub fn new<F>(
+        installed: Vec<(InstalledDrop, Option<Receipt>)>,
+        mut requested: F,
+        autoremove: bool,
+    ) -> Self
+    where
+        F: FnMut(&InstalledDrop) -> bool,
+    {
+        let deps: Vec<Vec<ScopedName>> = installed
+            .iter()
+            .map(|(drop, receipt)| dependencies(drop, receipt.as_ref()))
+            .collect();
+
+        // The order in which drops get removed, as indexes into `installed`.
+        let mut order: Vec<usize> = (0..installed.len())
+            .filter(|&i| requested(&installed[i].0))
+            .collect();
+        let mut removed: Vec<bool> = vec![false; installed.len()];
+        for &i in &order {
+            removed[i] = true;
+        }
+
+        let dependents = |removed: &[bool], name: &ScopedName| -> Vec<usize> {
+            let remains = (0..installed.len())
+                .any(|i| !removed[i] && installed[i].0.name == *name);
+            if remains {
+                return Vec::new();
+            }
+            (0..installed.len())
+                .filter(|&i| !removed[i] && deps[i].contains(name))
+                .collect()
+        };
+
+        let needed = order
+            .iter()
+            .filter_map(|&i| {
+                let drop = &installed[i].0;
+                let dependents = dependents(&removed, &drop.name);
+                if dependents.is_empty() {
+                    return None;
+                }
+                Some(Needed {
+                    name: drop.name.clone(),
+                    version: drop.manifest.meta.version.clone(),
+                    dependents: dependents
+                        .into_iter()
+                        .map(|i| {
+                            let drop = &installed[i].0;
+                            let version = drop.manifest.meta.version.clone();
+                            (drop.name.clone(), version)
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        let requested_count = order.len();
+        if autoremove {
+            // Breadth-first through the dependencies of removed drops.
+            let mut next = 0;
+            while next < order.len() {
+                let removed_deps = deps[order[next]].clone();
+                next += 1;
+                for (i, (drop, receipt)) in installed.iter().enumerate() {
+                    let is_dependency =
+                        receipt.as_ref().is_some_and(|r| !r.is_explicit());
+                    if removed[i]
+                        || !is_dependency
+                        || !removed_deps.contains(&drop.name)
+                    {
+                        continue;
+                    }
+                    removed[i] = true;
+                    if dependents(&removed, &drop.name).is_empty() {
+                        order.push(i);
+                    } else {
+                        removed[i] = false;
+                    }
+                }
+            }
+        }
+
+        let mut installed: Vec<Option<(InstalledDrop, Option<Receipt>)>> =
+            installed.into_iter().map(Some).collect();
+        let remove = order
+            .iter()
+            .enumerate()
+            .filter_map(|(position, &i)| {
+                let (drop, receipt) = installed[i].take()?;
+                Some(Removal {
+                    drop,
+                    receipt,
+                    orphaned: position >= requested_count,
+                })
+            })
+            .collect();
+
+        Self { remove, needed }
+    }
+
+    /// Returns whether removing the requested drops would break drops that
+    /// remain installed.
+    #[inline]
+    pub fn is_blocked(&self) -> bool {
+        !self.needed.is_empty()
+    }
+}
+
+impl Removal {
+    /// Removes the drop's files from `drops_dir` and its receipt within
+    /// `receipts`.
+    ///
+    /// If the drop is the active version, the newest remaining version
+    /// becomes active instead. If there is none, the binaries in `bin_dir`
+    /// that link to the drop are removed too.
+    pub fn remove(
+        &self,
+        drops_dir: &Path,
+        bin_dir: &Path,
+        receipts: &Receipts,
+    ) -> io::Result<()> {
+        let name = self.drop.name.to_ref();
+        let version = &self.drop.manifest.meta.version;
+        let is_current = current::current_version(drops_dir, name)?.as_ref()
+            == Some(version);
+
+        match fs::remove_dir_all(&self.drop.path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                return Err(error);
+            }
+            _ => {}
+        }
+        receipts.remove(name, version)?;
+
+        if is_current {
+            let remaining = super::installed_drops(drops_dir)?
+                .into_iter()
+                .find(|drop| drop.name == self.drop.name);
+            match remaining {
+                Some(drop) => activate_remaining(
+                    drops_dir,
+                    bin_dir,
+                    receipts,
+                    &drop.manifest,
+                    name,
+                )?,
+                None => {
+                    let current_path = current::current_path(drops_dir, name);
+                    let binaries =
+                        self.receipt.iter().flat_map(|r| &r.binaries);
+                    for binary in binaries {
+                        bin::unlink_binary(bin_dir, binary, &current_path)?;
+                    }
+                    current::clear_current(drops_dir, name)?;
+                }
+            }
+        }
+
+        // Clean up the scope's directories if this was its last drop.
+        let receipt_path = receipts.path(name, version);
+        let scope_dirs = [self.drop.path.parent(), receipt_path.parent()];
+        for dir in scope_dirs.iter().flatten() {
+            let _ = fs::remove_dir(dir);
+        }
+        Ok(())
+    }
+}
+
+/// Activates another installed version of `drop` after the active one was
+/// removed.
+fn activate_remaining(
+    drops_dir: &Path,
+    bin_dir: &Path,
+    receipts: &Receipts,
+    manifest: &Manifest,
+    drop: ScopedName<&str>,
+) -> io::Result<()> {
+    let version = &manifest.meta.version;
+    match current::activate(drops_dir, bin_dir, receipts, drop, manifest) {
+        Ok(binaries) => {
+            receipts.update(drop, version, |r| r.binaries = binaries)?;
+            Ok(())
+        }
+        Err(LinkError::Io(error)) => Err(error),
+        // The binary now belongs to something else, so leave it be.
+        Err(_) => Ok(()),
+    }
+}
+
+/// Returns the names of the drops that `drop` depends on, preferring those
+/// resolved at install time.
+fn dependencies(
+    drop: &InstalledDrop,
+    receipt: Option<&Receipt>,
+) -> Vec<ScopedName> {
+    if let Some(receipt) = receipt {
+        return receipt.dependencies.clone();
+    }
+    drop.manifest
+        .deps
+        .iter()
+        .flatten()
+        .map(|(query, _)| {
+            let scope = query.scope.as_deref().unwrap_or("core");
+            ScopedName::new(scope, query.name.as_str())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drop::Source;
+    use crate::install::InstallReason;
+
+    /// Creates an installed drop that depends on `deps`, along with a
+    /// receipt unless `reason` is `None`.
+    fn drop(
+        name: &str,
+        version: &str,
+        deps: &[&str],
+        reason: Option<InstallReason>,
+    ) -> (InstalledDrop, Option<Receipt>) {
+        let name = ScopedName::parse(name).unwrap();
+        let mut toml = format!(
+            "[meta]\nname = {:?}\ndescription = \"\"\nversion = {:?}\n",
+            name.name, version,
+        );
+        toml.push_str("[dependencies]\n");
+        for dep in deps {
+            toml.push_str(&format!("{:?} = \"*\"\n", dep));
+        }
+        let manifest = Manifest::parse_toml(&toml).unwrap();
+        let receipt = reason.map(|reason| Receipt {
+            name: name.clone(),
+            version: manifest.meta.version.clone(),
+            requirement: None,
+            source: Source::main_registry().clone(),
+            installed_at: 0,
+            reason,
+            dependencies: deps
+                .iter()
+                .map(|dep| ScopedName::parse(*dep).unwrap())
+                .collect(),
+            binaries: Vec::new(),
+            files: Vec::new(),
+        });
+        let drop = InstalledDrop {
+            path: format!("drops/{}@{}", name, version).into(),
+            name,
+            manifest,
+        };
+        (drop, receipt)
+    }
+
+    fn uninstall(
+        installed: Vec<(InstalledDrop, Option<Receipt>)>,
+        requested: &[&str],
+        autoremove: bool,
+    ) -> UninstallPlan {
+        UninstallPlan::new(
+            installed,
+            |drop| requested.contains(&&*drop.name.to_string()),
+            autoremove,
+        )
+    }
+
+    fn removed(plan: &UninstallPlan) -> Vec<(String, bool)> {
+        plan.remove
+            .iter()
+            .map(|r| (r.drop.name.to_string(), r.orphaned))
+            .collect()
+    }
+
+    fn installed() -> Vec<(InstalledDrop, Option<Receipt>)> {
+        use InstallReason::*;
+        vec![
+            drop(
+                "core/app",
+                "1.0.0",
+                &["core/lib", "core/util"],
+                Some(Explicit),
+            ),
+            drop("core/tool", "1.0.0", &["core/util"], Some(Explicit)),
+            drop("core/lib", "1.0.0", &["core/zlib"], Some(Dependency)),
+            drop("core/util", "1.0.0", &[], Some(Dependency)),
+            drop("core/zlib", "1.0.0", &[], Some(Dependency)),
+        ]
+    }
+
+    #[test]
+    fn refuses_needed_drops() {
+        let plan = uninstall(installed(), &["core/lib"], false);
+        assert!(plan.is_blocked());
+        assert_eq!(
+            plan.needed,
+            [Needed {
+                name: ScopedName::new("core", "lib"),
+                version: Version::parse("1.0.0"),
+                dependents: vec![(
+                    ScopedName::new("core", "app"),
+                    Version::parse("1.0.0"),
+                )],
+            }]
+        );
+
+        let plan = uninstall(installed(), &["core/app", "core/lib"], false);
+        assert!(!plan.is_blocked());
+    }
+
+    #[test]
+    fn keeps_needed_versions_while_others_remain() {
+        let mut installed = installed();
+        installed.push(drop(
+            "core/lib",
+            "2.0.0",
+            &[],
+            Some(InstallReason::Dependency),
+        ));
+        let plan = UninstallPlan::new(
+            installed,
+            |drop| {
+                drop.name.name == "lib"
+                    && drop.manifest.meta.version == *"1.0.0"
+            },
+            false,
+        );
+        assert!(!plan.is_blocked());
+        assert_eq!(removed(&plan), [("core/lib".to_owned(), false)]);
+    }
+
+    #[test]
+    fn autoremoves_orphans() {
+        let plan = uninstall(installed(), &["core/app"], true);
+        assert!(!plan.is_blocked());
+        assert_eq!(
+            removed(&plan),
+            [
+                ("core/app".to_owned(), false),
+                ("core/lib".to_owned(), true),
+                ("core/zlib".to_owned(), true),
+            ]
+        );
+
+        let plan = uninstall(installed(), &["core/app"], false);
+        assert_eq!(removed(&plan), [("core/app".to_owned(), false)]);
+    }
+
+    #[test]
+    fn autoremove_keeps_explicit_and_unknown_drops() {
+        use InstallReason::*;
+        let installed = vec![
+            drop(
+                "core/app",
+                "1.0.0",
+                &["core/lib", "core/util"],
+                Some(Explicit),
+            ),
+            drop("core/lib", "1.0.0", &[], Some(Explicit)),
+            drop("core/util", "1.0.0", &[], None),
+        ];
+        let plan = uninstall(installed, &["core/app"], true);
+        assert_eq!(removed(&plan), [("core/app".to_owned(), false)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn removes_files_binaries_and_receipt() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let drops_dir = dir.path().join("drops");
+        let bin_dir = dir.path().join("bin");
+        let receipts = Receipts::new(dir.path().join("receipts"));
+        let app = ScopedName::new("core", "app");
+
+        let removals: Vec<Removal> = ["1.0.0", "2.0.0"]
+            .iter()
+            .map(|version| {
+                let (mut installed, receipt) = drop(
+                    "core/app",
+                    version,
+                    &[],
+                    Some(InstallReason::Explicit),
+                );
+                installed.path =
+                    drops_dir.join(format!("core/app@{}", version));
+                fs::create_dir_all(&installed.path).unwrap();
+                fs::write(installed.path.join("app"), version).unwrap();
+                let toml = installed.manifest.to_toml(false).unwrap();
+                fs::write(installed.path.join(Manifest::FILE_NAME), toml)
+                    .unwrap();
+
+                let mut receipt = receipt.unwrap();
+                receipt.binaries = current::activate(
+                    &drops_dir,
+                    &bin_dir,
+                    &receipts,
+                    app,
+                    &installed.manifest,
+                )
+                .unwrap();
+                receipts.insert(&receipt).unwrap();
+                Removal {
+                    drop: installed,
+                    receipt: Some(receipt),
+                    orphaned: false,
+                }
+            })
+            .collect();
+        symlink("/elsewhere", bin_dir.join("other")).unwrap();
+
+        // Removing the active version switches to the remaining one.
+        removals[1].remove(&drops_dir, &bin_dir, &receipts).unwrap();
+        let current = current::current_version(&drops_dir, app).unwrap();
+        assert_eq!(current, Some(Version::parse("1.0.0")));
+        assert_eq!(fs::read(bin_dir.join("app")).unwrap(), b"1.0.0");
+
+        removals[0].remove(&drops_dir, &bin_dir, &receipts).unwrap();
+        assert!(!drops_dir.join("core").exists());
+        assert!(fs::symlink_metadata(bin_dir.join("app")).is_err());
+        assert!(fs::symlink_metadata(bin_dir.join("other")).is_ok());
+        assert!(!receipts.dir().join("core").exists());
+    }
+}
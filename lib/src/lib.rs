@@ -29,10 +29,13 @@ pub(crate) mod flexible;
 pub mod api;
 pub mod archive;
 pub mod auth;
+#[cfg(feature = "toml")]
+pub mod cache;
 pub mod config;
 pub mod drop;
 pub mod env;
 pub mod install;
+pub mod resolve;
 pub mod system;
 
 mod path;
@@ -0,0 +1,316 @@
+//! Dependency resolution.
+//!
+//! Given a set of requirements, this selects a version for every drop that is
+//! transitively depended upon such that all requirements are satisfied. If no
+//! such selection exists, a [`Report`] explains why.
+//!
+//! [`Report`]: struct.Report.html
+
+use crate::{
+    drop::{
+        manifest::{Deps, Manifest},
+        name::ScopedName,
+        version::{ReqParseError, Version},
+        Lockfile,
+    },
+    system::Platform,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt,
+};
+
+mod report;
+mod set;
+mod solver;
+#[cfg(feature = "toml")]
+mod source;
+mod term;
+
+#[cfg(test)]
+mod tests;
+
+#[doc(inline)]
+pub use self::report::Report;
+
+#[cfg(feature = "toml")]
+#[doc(inline)]
+pub use self::source::{SourceError, SourceRegistry, SourcedDrop};
+
+/// A source of drop versions and their manifests.
+pub trait Registry {
+    /// The error returned when querying the registry fails.
+    type Error;
+
+    /// Returns all versions of `drop` that can be selected.
+    ///
+    /// An empty list means that the drop does not exist.
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error>;
+
+    /// Returns the manifest of `drop` at `version`.
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error>;
+}
+
+impl<R: Registry + ?Sized> Registry for &mut R {
+    type Error = R::Error;
+
+    #[inline]
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error> {
+        (**self).versions(drop)
+    }
+
+    #[inline]
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error> {
+        (**self).manifest(drop, version)
+    }
+}
+
+/// A [`Registry`](trait.Registry.html) whose manifests are all held in memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRegistry {
+    drops: BTreeMap<ScopedName, Vec<Manifest>>,
+}
+
+impl MemoryRegistry {
+    /// Creates an empty registry.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `manifest` as a version of the drop it names within `scope`,
+    /// replacing any manifest with the same version.
+    pub fn insert(&mut self, scope: &str, manifest: Manifest) {
+        let name =
+            ScopedName::<String>::new(scope, manifest.meta.name.as_str());
+        let manifests = self.drops.entry(name).or_default();
+        let version = &manifest.meta.version;
+        match manifests.iter_mut().find(|m| m.meta.version == *version) {
+            Some(existing) => *existing = manifest,
+            None => manifests.push(manifest),
+        }
+    }
+}
+
+impl Registry for MemoryRegistry {
+    type Error = MissingManifest;
+
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error> {
+        let versions = self.drops.get(&drop.to_owned());
+        Ok(versions
+            .into_iter()
+            .flatten()
+            .map(|m| m.meta.version.clone())
+            .collect())
+    }
+
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error> {
+        let drop = drop.to_owned();
+        let manifest = self.drops.get(&drop).and_then(|manifests| {
+            manifests.iter().find(|m| m.meta.version == *version)
+        });
+        match manifest {
+            Some(manifest) => Ok(manifest.clone()),
+            None => Err(MissingManifest {
+                drop,
+                version: version.clone(),
+            }),
+        }
+    }
+}
+
+/// The error returned when a [`MemoryRegistry`](struct.MemoryRegistry.html)
+/// has no manifest for a drop version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingManifest {
+    /// The drop that was queried.
+    pub drop: ScopedName,
+    /// The version that was queried.
+    pub version: Version,
+}
+
+impl fmt::Display for MissingManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No manifest for {} version {}", self.drop, self.version)
+    }
+}
+
+impl Error for MissingManifest {}
+
+/// The versions selected for a dependency graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    /// The drops directly depended upon by the root.
+    pub root: Vec<ScopedName>,
+    /// Every drop in the graph, excluding the root.
+    pub drops: BTreeMap<ScopedName, ResolvedDrop>,
+}
+
+/// A drop within a [`Resolution`](struct.Resolution.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedDrop {
+    /// The selected version.
+    pub version: Version,
+    /// The drops directly depended upon by this one.
+    pub deps: Vec<ScopedName>,
+}
+
+/// Options that affect which versions get selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Versions to select over newer ones, as long as they are allowed.
+    pub preferred: BTreeMap<ScopedName, Version>,
+    /// The manifests of drops that are already installed.
+    ///
+    /// These are never selected, but the conflicts that they declare, and
+    /// that are declared against them, are respected.
+    pub installed: BTreeMap<ScopedName, Vec<Manifest>>,
+    /// The names of optional dependencies to include.
+    ///
+    /// Like `without`, this only applies to dependencies of the root and of
+    /// the drops that it directly depends on.
+    pub with: BTreeSet<String>,
+    /// The names of recommended dependencies to exclude.
+    pub without: BTreeSet<String>,
+    /// The platform to resolve for.
+    ///
+    /// Dependencies restricted to other operating systems or architectures
+    /// are skipped. If `None`, all dependencies are followed.
+    pub platform: Option<Platform>,
+}
+
+impl Options {
+    /// Creates options that prefer the versions recorded in `lockfile`.
+    pub fn locked(lockfile: &Lockfile) -> Self {
+        let preferred = lockfile
+            .drops
+            .iter()
+            .map(|drop| (drop.name.clone(), drop.version.clone()))
+            .collect();
+        Self {
+            preferred,
+            ..Self::default()
+        }
+    }
+}
+
+/// Resolves the dependencies of `root`.
+pub fn resolve<R: Registry>(
+    root: &Manifest,
+    registry: R,
+) -> Result<Resolution, ResolveError<R::Error>> {
+    resolve_with(root, registry, &Options::default())
+}
+
+/// Resolves the dependencies of `root` according to `options`.
+pub fn resolve_with<R: Registry>(
+    root: &Manifest,
+    registry: R,
+    options: &Options,
+) -> Result<Resolution, ResolveError<R::Error>> {
+    let deps = root.deps.clone().unwrap_or_default();
+    resolve_deps_with(&root.meta.name, &deps, registry, options)
+}
+
+/// Resolves `deps` on behalf of a root named `root`.
+///
+/// The name is only used when explaining failures.
+pub fn resolve_deps<R: Registry>(
+    root: &str,
+    deps: &Deps,
+    registry: R,
+) -> Result<Resolution, ResolveError<R::Error>> {
+    resolve_deps_with(root, deps, registry, &Options::default())
+}
+
+/// Resolves `deps` on behalf of a root named `root` according to `options`.
+pub fn resolve_deps_with<R: Registry>(
+    root: &str,
+    deps: &Deps,
+    mut registry: R,
+    options: &Options,
+) -> Result<Resolution, ResolveError<R::Error>> {
+    solver::Solver::new(&mut registry, root, deps, options).solve()
+}
+
+/// The error returned when resolving dependencies fails.
+#[derive(Debug)]
+pub enum ResolveError<E> {
+    /// No set of versions satisfies every requirement.
+    NoSolution(Report),
+    /// A conflict's version requirement could not be parsed.
+    InvalidRequirement {
+        /// The drop that declared the conflict.
+        drop: String,
+        /// The name of the conflicting drop.
+        dependency: String,
+        /// Why the requirement is invalid.
+        error: ReqParseError,
+    },
+    /// A dependency named in `Options::with` or `Options::without` is not
+    /// declared by any requested drop.
+    UndeclaredDependency {
+        /// The name of the dependency.
+        name: String,
+    },
+    /// The registry failed to provide versions or manifests.
+    Registry(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ResolveError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoSolution(report) => {
+                writeln!(f, "Failed to resolve dependencies:")?;
+                report.fmt(f)
+            }
+            Self::InvalidRequirement {
+                drop,
+                dependency,
+                error,
+            } => write!(
+                f,
+                "Invalid version requirement for \"{}\" in {}: {}",
+                dependency, drop, error
+            ),
+            Self::UndeclaredDependency { name } => write!(
+                f,
+                "No requested drop declares a dependency on \"{}\"",
+                name
+            ),
+            Self::Registry(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ResolveError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoSolution(_) | Self::UndeclaredDependency { .. } => None,
+            Self::InvalidRequirement { error, .. } => Some(error),
+            Self::Registry(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,534 @@
+//! Explanations for why version solving failed.
+
+use super::{
+    solver::{Cause, Incompat, IncompatId, Package, ROOT},
+    term::Term,
+};
+use std::{collections::HashMap, fmt};
+
+/// An explanation of why no set of versions satisfies every requirement.
+///
+/// This walks through the chain of conflicts that led to the failure, with
+/// each step building upon previous ones. Steps that are referred to more than
+/// once are numbered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    lines: Vec<(String, Option<usize>)>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let last_number = self.lines.iter().filter_map(|&(_, n)| n).max();
+        let padding = match last_number {
+            Some(number) => format!("({}) ", number).len(),
+            None => 0,
+        };
+
+        for (index, (line, number)) in self.lines.iter().enumerate() {
+            if index != 0 {
+                writeln!(f)?;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match number {
+                Some(number) => {
+                    let number = format!("({})", number);
+                    write!(f, "{:width$}", number, width = padding)?;
+                }
+                None => write!(f, "{:width$}", "", width = padding)?,
+            }
+            f.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Report {
+    pub(super) fn new(
+        packages: &[Package],
+        incompats: &[Incompat],
+        failure: IncompatId,
+    ) -> Self {
+        let mut writer = Writer {
+            packages,
+            incompats,
+            derivations: HashMap::new(),
+            line_numbers: HashMap::new(),
+            lines: Vec::new(),
+        };
+        writer.count_derivations(failure);
+
+        if let Cause::Derived(..) = incompats[failure].cause {
+            writer.visit(failure, false);
+        } else {
+            let line = format!(
+                "Because {}, version solving failed.",
+                writer.describe(failure),
+            );
+            writer.write(failure, line, false);
+        }
+
+        Report {
+            lines: writer.lines,
+        }
+    }
+
+    /// Returns the lines of the report, each with its number if it has one.
+    pub fn lines(&self) -> impl Iterator<Item = (&str, Option<usize>)> {
+        self.lines
+            .iter()
+            .map(|(line, number)| (line.as_str(), *number))
+    }
+}
+
+struct Writer<'a> {
+    packages: &'a [Package],
+    incompats: &'a [Incompat],
+    /// How many times each incompatibility is used to derive the failure.
+    derivations: HashMap<IncompatId, usize>,
+    line_numbers: HashMap<IncompatId, usize>,
+    lines: Vec<(String, Option<usize>)>,
+}
+
+impl Writer<'_> {
+    fn count_derivations(&mut self, id: IncompatId) {
+        let count = self.derivations.entry(id).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            if let Cause::Derived(a, b) = self.incompats[id].cause {
+                self.count_derivations(a);
+                self.count_derivations(b);
+            }
+        }
+    }
+
+    fn is_derived(&self, id: IncompatId) -> bool {
+        matches!(self.incompats[id].cause, Cause::Derived(..))
+    }
+
+    fn causes(&self, id: IncompatId) -> (IncompatId, IncompatId) {
+        match self.incompats[id].cause {
+            Cause::Derived(a, b) => (a, b),
+            cause => unreachable!("{:?} is not derived", cause),
+        }
+    }
+
+    fn write(&mut self, id: IncompatId, line: String, numbered: bool) {
+        if numbered {
+            let number = self.line_numbers.len() + 1;
+            self.line_numbers.insert(id, number);
+            self.lines.push((line, Some(number)));
+        } else {
+            self.lines.push((line, None));
+        }
+    }
+
+    /// Writes the lines that explain how `id` was derived.
+    fn visit(&mut self, id: IncompatId, conclusion: bool) {
+        let numbered = conclusion || self.derivations[&id] > 1;
+        let conjunction = if conclusion || id == self.root_failure() {
+            "So,"
+        } else {
+            "And"
+        };
+        let described = self.describe(id);
+
+        let (conflict, other) = self.causes(id);
+
+        if self.is_derived(conflict) && self.is_derived(other) {
+            let conflict_line = self.line_numbers.get(&conflict).copied();
+            let other_line = self.line_numbers.get(&other).copied();
+
+            match (conflict_line, other_line) {
+                (Some(_), Some(_)) => {
+                    let and =
+                        self.and(conflict, other, conflict_line, other_line);
+                    let line = format!("Because {}, {}.", and, described);
+                    self.write(id, line, numbered);
+                }
+                (Some(line), None) | (None, Some(line)) => {
+                    let (with_line, without_line) = if conflict_line.is_some() {
+                        (conflict, other)
+                    } else {
+                        (other, conflict)
+                    };
+                    self.visit(without_line, false);
+                    let line = format!(
+                        "{} because {} ({}), {}.",
+                        conjunction,
+                        self.describe(with_line),
+                        line,
+                        described,
+                    );
+                    self.write(id, line, numbered);
+                }
+                (None, None) => {
+                    let single_conflict = self.is_single_line(conflict);
+                    let single_other = self.is_single_line(other);
+
+                    if single_conflict || single_other {
+                        let (first, second) = if single_other {
+                            (conflict, other)
+                        } else {
+                            (other, conflict)
+                        };
+                        self.visit(first, false);
+                        self.visit(second, false);
+                        let line = format!("Thus, {}.", described);
+                        self.write(id, line, numbered);
+                    } else {
+                        self.visit(conflict, true);
+                        self.lines.push((String::new(), None));
+                        self.visit(other, false);
+                        let line = format!(
+                            "{} because {} ({}), {}.",
+                            conjunction,
+                            self.describe(conflict),
+                            self.line_numbers[&conflict],
+                            described,
+                        );
+                        self.write(id, line, numbered);
+                    }
+                }
+            }
+        } else if self.is_derived(conflict) || self.is_derived(other) {
+            let (derived, external) = if self.is_derived(conflict) {
+                (conflict, other)
+            } else {
+                (other, conflict)
+            };
+
+            if let Some(&derived_line) = self.line_numbers.get(&derived) {
+                let and = self.and(external, derived, None, Some(derived_line));
+                let line = format!("Because {}, {}.", and, described);
+                self.write(id, line, numbered);
+            } else if self.is_collapsible(derived) {
+                let (a, b) = self.causes(derived);
+                let (collapsed_derived, collapsed_external) =
+                    if self.is_derived(a) { (a, b) } else { (b, a) };
+                self.visit(collapsed_derived, false);
+                let line = format!(
+                    "{} because {}, {}.",
+                    conjunction,
+                    self.and(collapsed_external, external, None, None),
+                    described,
+                );
+                self.write(id, line, numbered);
+            } else {
+                self.visit(derived, false);
+                let line = format!(
+                    "{} because {}, {}.",
+                    conjunction,
+                    self.describe(external),
+                    described,
+                );
+                self.write(id, line, numbered);
+            }
+        } else {
+            let line = format!(
+                "Because {}, {}.",
+                self.and(conflict, other, None, None),
+                described,
+            );
+            self.write(id, line, numbered);
+        }
+    }
+
+    /// The failure is always the last incompatibility counted.
+    fn root_failure(&self) -> IncompatId {
+        *self.derivations.keys().max().unwrap()
+    }
+
+    fn is_single_line(&self, id: IncompatId) -> bool {
+        let (a, b) = self.causes(id);
+        !self.is_derived(a) && !self.is_derived(b)
+    }
+
+    /// Returns whether `id` can be explained together with the step that uses
+    /// it, rather than on its own line.
+    fn is_collapsible(&self, id: IncompatId) -> bool {
+        if self.derivations[&id] > 1 {
+            return false;
+        }
+        let (a, b) = self.causes(id);
+        if self.is_derived(a) == self.is_derived(b) {
+            return false;
+        }
+        let complex = if self.is_derived(a) { a } else { b };
+        !self.line_numbers.contains_key(&complex)
+    }
+
+    /// Describes `a` and `b` as a single clause.
+    fn and(
+        &self,
+        a: IncompatId,
+        b: IncompatId,
+        a_line: Option<usize>,
+        b_line: Option<usize>,
+    ) -> String {
+        if let Some(both) = self.depends_on_both(a, b, a_line, b_line) {
+            return both;
+        }
+        if let Some(through) = self.depends_through(a, b, a_line, b_line) {
+            return through;
+        }
+        if let Some(through) = self.depends_through(b, a, b_line, a_line) {
+            return through;
+        }
+        if let Some(missing) = self.depends_on_missing(a, b, a_line, b_line) {
+            return missing;
+        }
+        if let Some(missing) = self.depends_on_missing(b, a, b_line, a_line) {
+            return missing;
+        }
+
+        let mut result = self.describe(a);
+        if let Some(line) = a_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        result.push_str(" and ");
+        result.push_str(&self.describe(b));
+        if let Some(line) = b_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        result
+    }
+
+    /// "a depends on both x and y"
+    fn depends_on_both(
+        &self,
+        a: IncompatId,
+        b: IncompatId,
+        a_line: Option<usize>,
+        b_line: Option<usize>,
+    ) -> Option<String> {
+        let (a_depender, a_dep) = self.dependency(a)?;
+        let (b_depender, b_dep) = self.dependency(b)?;
+        if a_depender != b_depender {
+            return None;
+        }
+
+        let mut result = format!(
+            "{} depends on both {}",
+            self.subject(a_depender),
+            self.object(&a_dep.negate()),
+        );
+        if let Some(line) = a_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        result.push_str(" and ");
+        result.push_str(&self.object(&b_dep.negate()));
+        if let Some(line) = b_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        Some(result)
+    }
+
+    /// "a depends on x which depends on y"
+    fn depends_through(
+        &self,
+        a: IncompatId,
+        b: IncompatId,
+        a_line: Option<usize>,
+        b_line: Option<usize>,
+    ) -> Option<String> {
+        let (a_depender, a_dep) = self.dependency(a)?;
+        let (b_depender, b_dep) = self.dependency(b)?;
+
+        // Every version that `a` depends on must depend on `b`'s dependency.
+        let through = a_dep.pkg == b_depender.pkg
+            && a_dep.set.candidates().count() > 0
+            && a_dep
+                .set
+                .candidates()
+                .all(|v| b_depender.set.candidates().any(|w| v == w));
+        if !through {
+            return None;
+        }
+
+        let mut result = format!(
+            "{} depends on {}",
+            self.subject(a_depender),
+            self.object(&a_dep.negate()),
+        );
+        if let Some(line) = a_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        result.push_str(&format!(
+            " which depends on {}",
+            self.object(&b_dep.negate()),
+        ));
+        if let Some(line) = b_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        Some(result)
+    }
+
+    /// "a depends on x which doesn't match any versions"
+    fn depends_on_missing(
+        &self,
+        a: IncompatId,
+        b: IncompatId,
+        a_line: Option<usize>,
+        b_line: Option<usize>,
+    ) -> Option<String> {
+        let (depender, dep) = self.dependency(a)?;
+        let missing = &self.incompats[b];
+        let term = match (missing.cause, missing.terms.as_slice()) {
+            (Cause::NoVersions, [term]) if term.pkg == dep.pkg => term,
+            _ => return None,
+        };
+        if !dep.set.is_subset(&term.set) {
+            return None;
+        }
+
+        let mut result = format!(
+            "{} depends on {}",
+            self.subject(depender),
+            self.object(&dep.negate()),
+        );
+        if let Some(line) = a_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        if self.packages[dep.pkg].versions.is_empty() {
+            result.push_str(" which doesn't exist");
+        } else {
+            result.push_str(" which doesn't match any versions");
+        }
+        if let Some(line) = b_line {
+            result.push_str(&format!(" ({})", line));
+        }
+        Some(result)
+    }
+
+    /// Returns the depender and negated dependency terms of `id` if it is a
+    /// dependency.
+    fn dependency(&self, id: IncompatId) -> Option<(&Term, &Term)> {
+        let incompat = &self.incompats[id];
+        match (incompat.cause, incompat.terms.as_slice()) {
+            (Cause::Dependency, [depender, dep]) => Some((depender, dep)),
+            _ => None,
+        }
+    }
+
+    /// Describes `term` when used as the subject of a sentence.
+    fn subject(&self, term: &Term) -> String {
+        let package = &self.packages[term.pkg];
+        if term.pkg == ROOT {
+            package.name.clone()
+        } else if package.is_any(&term.set) {
+            format!("every version of {}", package.name)
+        } else {
+            format!("{} {}", package.name, term.label)
+        }
+    }
+
+    /// Describes `term` when used as the object of a sentence.
+    fn object(&self, term: &Term) -> String {
+        let package = &self.packages[term.pkg];
+        if term.pkg == ROOT || term.label == "*" {
+            package.name.clone()
+        } else {
+            format!("{} {}", package.name, term.label)
+        }
+    }
+
+    /// Describes what `id` means in English.
+    fn describe(&self, id: IncompatId) -> String {
+        let incompat = &self.incompats[id];
+        let terms = incompat.terms.as_slice();
+
+        match incompat.cause {
+            Cause::Dependency => {
+                return format!(
+                    "{} depends on {}",
+                    self.subject(&terms[0]),
+                    self.object(&terms[1].negate()),
+                );
+            }
+            Cause::Conflict => {
+                return format!(
+                    "{} conflicts with {}",
+                    self.subject(&terms[0]),
+                    self.object(&terms[1]),
+                );
+            }
+            Cause::Installed => {
+                let package = &self.packages[terms[1].pkg];
+                return format!(
+                    "{} {} is installed",
+                    package.name, package.versions[0],
+                );
+            }
+            Cause::NoVersions => {
+                let package = &self.packages[terms[0].pkg];
+                return if package.versions.is_empty() {
+                    format!("{} does not exist", package.name)
+                } else {
+                    format!(
+                        "no versions of {} match {}",
+                        package.name, terms[0].label,
+                    )
+                };
+            }
+            Cause::Root | Cause::Derived(..) => {}
+        }
+
+        match terms {
+            [] => return "version solving failed".to_owned(),
+            [term] if term.pkg == ROOT && term.positive => {
+                return "version solving failed".to_owned();
+            }
+            [term] if term.positive => {
+                let package = &self.packages[term.pkg];
+                return if package.is_any(&term.set) {
+                    format!("{} is forbidden", package.name)
+                } else {
+                    format!("{} is forbidden", self.subject(term))
+                };
+            }
+            [term] => {
+                return format!("{} is required", self.object(&term.negate()));
+            }
+            [a, b] if a.positive && b.positive => {
+                return format!(
+                    "{} is incompatible with {}",
+                    self.subject(a),
+                    self.object(b),
+                );
+            }
+            _ => {}
+        }
+
+        let positive: Vec<&Term> =
+            terms.iter().filter(|t| t.positive).collect();
+        let negative: Vec<String> = terms
+            .iter()
+            .filter(|t| !t.positive)
+            .map(|t| self.object(&t.negate()))
+            .collect();
+
+        match positive.as_slice() {
+            [] => format!("one of {} must be true", negative.join(" or ")),
+            [term] => format!(
+                "{} requires {}",
+                self.subject(term),
+                negative.join(" or "),
+            ),
+            _ => {
+                let positive: Vec<String> =
+                    positive.iter().map(|t| self.subject(t)).collect();
+                if negative.is_empty() {
+                    format!("one of {} must be false", positive.join(" or "))
+                } else {
+                    format!(
+                        "if {} then {}",
+                        positive.join(" and "),
+                        negative.join(" or "),
+                    )
+                }
+            }
+        }
+    }
+}
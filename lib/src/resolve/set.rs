@@ -0,0 +1,122 @@
+//! Sets of candidate versions.
+
+use std::fmt;
+
+/// A set over the candidate versions of a single drop.
+///
+/// Index `0` is reserved for versions that the registry does not provide. This
+/// allows for requirements like `^5` to be non-empty even if no candidate
+/// matches them, which is what lets the solver explain that no such version
+/// exists. Every other index `i` refers to the `i - 1`th candidate.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct VersionSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl fmt::Debug for VersionSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl VersionSet {
+    /// The index used for versions that are not provided by the registry.
+    pub const UNAVAILABLE: usize = 0;
+
+    /// Creates an empty set over `candidates` versions.
+    pub fn empty(candidates: usize) -> Self {
+        let len = candidates + 1;
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    /// Creates a set containing only the candidate at `index`.
+    pub fn exact(candidates: usize, index: usize) -> Self {
+        let mut set = Self::empty(candidates);
+        set.insert(index + 1);
+        set
+    }
+
+    /// Creates a set containing every candidate for which `matches` returns
+    /// `true`, or only unavailable versions if there are none.
+    pub fn matching<F>(candidates: usize, mut matches: F) -> Self
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let mut set = Self::empty(candidates);
+        for index in 0..candidates {
+            if matches(index) {
+                set.insert(index + 1);
+            }
+        }
+        if set.is_empty() {
+            set.insert(Self::UNAVAILABLE);
+        }
+        set
+    }
+
+    fn insert(&mut self, bit: usize) {
+        self.words[bit / 64] |= 1 << (bit % 64);
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        self.words[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    fn zip_with<F>(&self, other: &Self, f: F) -> Self
+    where
+        F: Fn(u64, u64) -> u64,
+    {
+        debug_assert_eq!(self.len, other.len);
+        Self {
+            words: (self.words.iter().zip(&other.words))
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+            len: self.len,
+        }
+    }
+
+    /// Returns the versions in either `self` or `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a | b)
+    }
+
+    /// Returns the versions in both `self` and `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & b)
+    }
+
+    /// Returns the versions in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & !b)
+    }
+
+    /// Returns whether `self` contains no versions.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Returns whether every version in `self` is also in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    /// Returns whether `self` and `other` share no versions.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).is_empty()
+    }
+
+    /// Returns the indices of the candidates in `self`, in order.
+    pub fn candidates(&self) -> impl Iterator<Item = usize> + '_ {
+        (1..self.len)
+            .filter(move |&bit| self.contains(bit))
+            .map(|bit| bit - 1)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(move |&bit| self.contains(bit))
+    }
+}
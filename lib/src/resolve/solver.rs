@@ -0,0 +1,911 @@
+//! The version solving algorithm.
+//!
+//! This is an implementation of [PubGrub], which is the algorithm used by
+//! Dart's `pub`. Its main benefit over simple backtracking is that it learns
+//! from every conflict, which is what allows it to explain why resolution
+//! failed.
+//!
+//! [PubGrub]: https://github.com/dart-lang/pub/blob/master/doc/solver.md
+
+use super::{
+    report::Report,
+    set::VersionSet,
+    term::{PkgId, Relation, Term},
+    Options, Registry, Resolution, ResolveError, ResolvedDrop,
+};
+use crate::{
+    drop::{
+        manifest::{Deps, Manifest},
+        name::{Query, ScopedName},
+        version::{SemVer, Version, VersionReq},
+    },
+    system::Platform,
+};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+/// Index of an incompatibility within the solver.
+pub(super) type IncompatId = usize;
+
+/// The package for the root of the dependency graph.
+pub(super) const ROOT: PkgId = 0;
+
+/// A drop that the solver knows about.
+#[derive(Debug)]
+pub(super) struct Package {
+    /// The name shown to users.
+    pub name: String,
+    /// The drop's name in the registry, or `None` for the root.
+    pub id: Option<ScopedName>,
+    /// The versions available in the registry, newest first.
+    pub versions: Vec<Version>,
+    /// The index of the version to try first, if not the newest.
+    pub preferred: Option<usize>,
+    /// Whether this is an installed drop rather than a candidate.
+    pub installed: bool,
+}
+
+impl Package {
+    /// Returns whether `set` contains every available version.
+    pub fn is_any(&self, set: &VersionSet) -> bool {
+        !self.installed
+            && !self.versions.is_empty()
+            && set.candidates().count() == self.versions.len()
+    }
+
+    /// Returns a requirement-like description of `set`.
+    pub fn describe(&self, set: &VersionSet) -> String {
+        if self.is_any(set) {
+            return "*".to_owned();
+        }
+
+        let indices: Vec<usize> = set.candidates().collect();
+        let (newest, oldest) = match (indices.first(), indices.last()) {
+            (Some(&newest), Some(&oldest)) => (newest, oldest),
+            _ => return "<unavailable>".to_owned(),
+        };
+        let version = |index: usize| &self.versions[index];
+
+        if newest == oldest {
+            version(newest).to_string()
+        } else if oldest - newest + 1 != indices.len() {
+            let versions: Vec<String> =
+                indices.iter().map(|&i| version(i).to_string()).collect();
+            versions.join(" || ")
+        } else if newest == 0 {
+            format!(">={}", version(oldest))
+        } else if oldest == self.versions.len() - 1 {
+            format!("<={}", version(newest))
+        } else {
+            format!(">={}, <={}", version(oldest), version(newest))
+        }
+    }
+}
+
+/// A set of terms that must not all be true at the same time.
+#[derive(Debug)]
+pub(super) struct Incompat {
+    pub terms: Vec<Term>,
+    pub cause: Cause,
+}
+
+/// Why an incompatibility was added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Cause {
+    /// The root package must be selected.
+    Root,
+    /// The first term depends on the inverse of the second.
+    Dependency,
+    /// The first term declares a conflict with the second.
+    Conflict,
+    /// The inverse of the second term is installed.
+    Installed,
+    /// The registry provides no versions that match the only term.
+    NoVersions,
+    /// Derived from two other incompatibilities during conflict resolution.
+    Derived(IncompatId, IncompatId),
+}
+
+/// How the partial solution relates to an incompatibility.
+enum IncompatRelation {
+    Satisfied,
+    /// Every term is satisfied except for the one at the given index.
+    AlmostSatisfied(usize),
+    Contradicted,
+    Inconclusive,
+}
+
+/// The terms and cause of an incompatibility that has yet to be added.
+type Constraint = (Vec<Term>, Cause);
+
+/// A requirement declared by a package version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Requirement {
+    name: ScopedName,
+    version: VersionReq,
+    kind: RequirementKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RequirementKind {
+    /// Versions matching the requirement must be selected.
+    Dependency,
+    /// Like `Dependency`, but only if requested via `Options::with`.
+    Optional,
+    /// Like `Dependency`, unless excluded via `Options::without`.
+    Recommended,
+    /// Versions matching the requirement must not be selected or installed.
+    Conflict,
+}
+
+/// A decision or derivation made by the solver.
+#[derive(Debug)]
+struct Assignment {
+    term: Term,
+    level: usize,
+    /// The incompatibility from which `term` was derived, or `None` if this
+    /// is a decision.
+    cause: Option<IncompatId>,
+}
+
+pub(super) struct Solver<'a, R> {
+    registry: &'a mut R,
+    root_deps: &'a Deps,
+    options: &'a Options,
+    pub packages: Vec<Package>,
+    ids: HashMap<ScopedName, PkgId>,
+    pub incompats: Vec<Incompat>,
+    /// The incompatibilities that refer to each package.
+    by_pkg: Vec<Vec<IncompatId>>,
+    assignments: Vec<Assignment>,
+    /// The intersection of all assignments for each package.
+    accumulated: Vec<Option<Term>>,
+    decisions: Vec<Option<usize>>,
+    level: usize,
+    /// The packages for installed drops.
+    installed: HashMap<ScopedName, Vec<PkgId>>,
+    /// The requirements declared by each package version.
+    requirements: HashMap<(PkgId, usize), Vec<Requirement>>,
+    /// The dependencies of each package version that has been decided on.
+    deps: HashMap<(PkgId, usize), Vec<PkgId>>,
+}
+
+impl<'a, R: Registry> Solver<'a, R> {
+    pub fn new(
+        registry: &'a mut R,
+        root: &str,
+        root_deps: &'a Deps,
+        options: &'a Options,
+    ) -> Self {
+        Self {
+            registry,
+            root_deps,
+            options,
+            packages: vec![Package {
+                name: root.to_owned(),
+                id: None,
+                versions: vec![Version::SemVer(SemVer::new(0, 0, 0))],
+                preferred: None,
+                installed: false,
+            }],
+            ids: HashMap::new(),
+            incompats: Vec::new(),
+            by_pkg: vec![Vec::new()],
+            assignments: Vec::new(),
+            accumulated: vec![None],
+            decisions: vec![None],
+            level: 0,
+            installed: HashMap::new(),
+            requirements: HashMap::new(),
+            deps: HashMap::new(),
+        }
+    }
+
+    pub fn solve(mut self) -> Result<Resolution, ResolveError<R::Error>> {
+        let root = Term::new(ROOT, false, VersionSet::exact(1, 0), "*".into());
+        self.add_incompat(vec![root], Cause::Root);
+
+        let mut next = Some(ROOT);
+        while let Some(pkg) = next {
+            if let Err(failure) = self.propagate(pkg) {
+                let report =
+                    Report::new(&self.packages, &self.incompats, failure);
+                return Err(ResolveError::NoSolution(report));
+            }
+            next = self.decide()?;
+        }
+
+        self.check_included()?;
+        Ok(self.resolution())
+    }
+
+    /// Checks that every name in `Options::with` and `Options::without` is
+    /// declared as a dependency by the root or a drop it directly depends on.
+    fn check_included(&self) -> Result<(), ResolveError<R::Error>> {
+        let requested = self.deps[&(ROOT, 0)].iter().chain(Some(&ROOT));
+        let declared: HashSet<&str> = requested
+            .filter_map(|&pkg| {
+                self.requirements.get(&(pkg, self.decisions[pkg]?))
+            })
+            .flatten()
+            .filter(|req| req.kind != RequirementKind::Conflict)
+            .map(|req| req.name.name.as_str())
+            .collect();
+
+        let options = self.options;
+        let mut names = options.with.iter().chain(&options.without);
+        match names.find(|name| !declared.contains(name.as_str())) {
+            Some(name) => {
+                Err(ResolveError::UndeclaredDependency { name: name.clone() })
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn resolution(&self) -> Resolution {
+        let id = |pkg: &PkgId| self.packages[*pkg].id.clone().unwrap();
+
+        let mut drops = BTreeMap::new();
+        for (pkg, package) in self.packages.iter().enumerate().skip(1) {
+            if package.installed {
+                continue;
+            }
+            if let Some(version) = self.decisions[pkg] {
+                let deps = &self.deps[&(pkg, version)];
+                drops.insert(
+                    id(&pkg),
+                    ResolvedDrop {
+                        version: package.versions[version].clone(),
+                        deps: deps.iter().map(id).collect(),
+                    },
+                );
+            }
+        }
+
+        Resolution {
+            root: self.deps[&(ROOT, 0)].iter().map(id).collect(),
+            drops,
+        }
+    }
+
+    /// Returns the package for `name`, fetching its versions if it has not
+    /// been seen before.
+    fn intern(&mut self, name: ScopedName) -> Result<PkgId, R::Error> {
+        if let Some(&pkg) = self.ids.get(&name) {
+            return Ok(pkg);
+        }
+
+        let mut versions = self.registry.versions(name.to_ref())?;
+        versions.sort_by(|a, b| b.cmp_precedence(a));
+        versions.dedup();
+
+        let preferred = self
+            .options
+            .preferred
+            .get(&name)
+            .and_then(|version| versions.iter().position(|v| v == version));
+
+        let pkg = self.push_package(Package {
+            name: display_name(&name),
+            id: Some(name.clone()),
+            versions,
+            preferred,
+            installed: false,
+        });
+        self.ids.insert(name, pkg);
+        Ok(pkg)
+    }
+
+    fn push_package(&mut self, package: Package) -> PkgId {
+        self.packages.push(package);
+        self.by_pkg.push(Vec::new());
+        self.accumulated.push(None);
+        self.decisions.push(None);
+        self.packages.len() - 1
+    }
+
+    /// Adds packages for the drops in `Options::installed`, returning
+    /// incompatibilities that require each one.
+    fn intern_installed(
+        &mut self,
+    ) -> Result<Vec<Constraint>, ResolveError<R::Error>> {
+        let root = Term::new(ROOT, true, VersionSet::exact(1, 0), "*".into());
+        let mut constraints = Vec::new();
+
+        for (name, manifests) in &self.options.installed {
+            for manifest in manifests {
+                let version = manifest.meta.version.clone();
+                let label = version.to_string();
+                let pkg = self.push_package(Package {
+                    name: display_name(name),
+                    id: None,
+                    versions: vec![version],
+                    preferred: None,
+                    installed: true,
+                });
+                self.installed.entry(name.clone()).or_default().push(pkg);
+
+                let conflicts = conflicts(&display_name(name), manifest)?;
+                self.requirements.insert((pkg, 0), conflicts);
+
+                let installed =
+                    Term::new(pkg, false, VersionSet::exact(1, 0), label);
+                constraints
+                    .push((vec![root.clone(), installed], Cause::Installed));
+            }
+        }
+
+        Ok(constraints)
+    }
+
+    /// Returns the requirements declared by `pkg` at the version at `index`.
+    fn requirements(
+        &mut self,
+        pkg: PkgId,
+        index: usize,
+    ) -> Result<Vec<Requirement>, ResolveError<R::Error>> {
+        if let Some(reqs) = self.requirements.get(&(pkg, index)) {
+            return Ok(reqs.clone());
+        }
+
+        let package = &self.packages[pkg];
+        let reqs: Vec<Requirement> = match &package.id {
+            None => {
+                let deps = self.root_deps.clone();
+                dependencies(deps, self.options.platform.as_ref()).collect()
+            }
+            Some(id) => {
+                let version = &package.versions[index];
+                let manifest = self
+                    .registry
+                    .manifest(id.to_ref(), version)
+                    .map_err(ResolveError::Registry)?;
+                let conflicts = conflicts(&package.name, &manifest)?;
+                let deps = manifest.deps.unwrap_or_default();
+                dependencies(deps, self.options.platform.as_ref())
+                    .chain(conflicts)
+                    .collect()
+            }
+        };
+
+        self.requirements.insert((pkg, index), reqs.clone());
+        Ok(reqs)
+    }
+
+    /// Returns whether `req`, as declared by `pkg`, is to be followed.
+    ///
+    /// `Options::with` and `Options::without` only apply to the root and the
+    /// drops that it directly depends on.
+    fn is_included(&self, pkg: PkgId, req: &Requirement) -> bool {
+        let requested = pkg == ROOT
+            || self.deps.get(&(ROOT, 0)).is_some_and(|d| d.contains(&pkg));
+        let name = &req.name.name;
+        match req.kind {
+            RequirementKind::Optional => {
+                requested && self.options.with.contains(name)
+            }
+            RequirementKind::Recommended => {
+                !(requested && self.options.without.contains(name))
+            }
+            RequirementKind::Dependency | RequirementKind::Conflict => true,
+        }
+    }
+
+    /// Returns the incompatibilities for the requirements of `pkg` at the
+    /// version at `index`, along with their causes.
+    ///
+    /// Each incompatibility starts with a term for `pkg`. Neighboring versions
+    /// with the same requirement are included in that term, which allows for
+    /// ruling them out all at once.
+    fn constraints(
+        &mut self,
+        pkg: PkgId,
+        index: usize,
+    ) -> Result<Vec<Constraint>, ResolveError<R::Error>> {
+        let mut constraints = if pkg == ROOT {
+            self.intern_installed()?
+        } else {
+            Vec::new()
+        };
+
+        let reqs = self.requirements(pkg, index)?;
+        let count = self.packages[pkg].versions.len();
+
+        for req in reqs {
+            if !self.is_included(pkg, &req) {
+                continue;
+            }
+
+            let dep = self
+                .intern(req.name.clone())
+                .map_err(ResolveError::Registry)?;
+            if dep == pkg {
+                continue;
+            }
+
+            let same = |solver: &mut Self, index| {
+                solver
+                    .requirements(pkg, index)
+                    .map(|reqs| reqs.contains(&req))
+            };
+            let mut newest = index;
+            while newest > 0 && same(self, newest - 1)? {
+                newest -= 1;
+            }
+            let mut oldest = index;
+            while oldest + 1 < count && same(self, oldest + 1)? {
+                oldest += 1;
+            }
+
+            let package = &self.packages[pkg];
+            let set =
+                VersionSet::matching(count, |i| newest <= i && i <= oldest);
+            let depender =
+                Term::new(pkg, true, set.clone(), package.describe(&set));
+
+            let versions = &self.packages[dep].versions;
+            let set = VersionSet::matching(versions.len(), |index| {
+                req.version.matches(&versions[index])
+            });
+
+            match req.kind {
+                RequirementKind::Dependency
+                | RequirementKind::Optional
+                | RequirementKind::Recommended => {
+                    let label = req.version.to_string();
+                    let dep = Term::new(dep, false, set, label);
+                    constraints.push((vec![depender, dep], Cause::Dependency));
+                }
+                RequirementKind::Conflict => {
+                    let installed = self.installed.get(&req.name);
+                    for &other in installed.into_iter().flatten() {
+                        let version = &self.packages[other].versions[0];
+                        if !req.version.matches(version) {
+                            continue;
+                        }
+                        let set = VersionSet::exact(1, 0);
+                        let label = req.version.to_string();
+                        let other = Term::new(other, true, set, label);
+                        constraints.push((
+                            vec![depender.clone(), other],
+                            Cause::Conflict,
+                        ));
+                    }
+
+                    if set.candidates().next().is_some() {
+                        let label = req.version.to_string();
+                        let dep = Term::new(dep, true, set, label);
+                        constraints
+                            .push((vec![depender, dep], Cause::Conflict));
+                    }
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Adds an incompatibility and returns its index.
+    fn add_incompat(&mut self, terms: Vec<Term>, cause: Cause) -> IncompatId {
+        let id = self.push_incompat(terms, cause);
+        self.index_incompat(id);
+        id
+    }
+
+    /// Adds an incompatibility without making it visible to unit propagation.
+    fn push_incompat(&mut self, terms: Vec<Term>, cause: Cause) -> IncompatId {
+        let mut terms = terms;
+
+        // Derived incompatibilities that refer to the root only as a positive
+        // term are true regardless of the root, since it is always selected.
+        if let Cause::Derived(..) = cause {
+            if terms.len() != 1 {
+                terms.retain(|term| !(term.positive && term.pkg == ROOT));
+            }
+        }
+
+        // Merge terms that refer to the same package.
+        let mut merged: Vec<Term> = Vec::with_capacity(terms.len());
+        for term in terms {
+            match merged.iter_mut().find(|t| t.pkg == term.pkg) {
+                Some(existing) => {
+                    let package = &self.packages[term.pkg];
+                    *existing = existing.intersect(&term, package);
+                }
+                None => merged.push(term),
+            }
+        }
+
+        self.incompats.push(Incompat {
+            terms: merged,
+            cause,
+        });
+        self.incompats.len() - 1
+    }
+
+    fn index_incompat(&mut self, id: IncompatId) {
+        for term in &self.incompats[id].terms {
+            self.by_pkg[term.pkg].push(id);
+        }
+    }
+
+    fn relation(&self, id: IncompatId) -> IncompatRelation {
+        let mut unsatisfied = None;
+        for (index, term) in self.incompats[id].terms.iter().enumerate() {
+            match term.relation(self.accumulated[term.pkg].as_ref()) {
+                Relation::Satisfied => {}
+                Relation::Contradicted => {
+                    return IncompatRelation::Contradicted;
+                }
+                Relation::Inconclusive => {
+                    if unsatisfied.is_some() {
+                        return IncompatRelation::Inconclusive;
+                    }
+                    unsatisfied = Some(index);
+                }
+            }
+        }
+        match unsatisfied {
+            Some(index) => IncompatRelation::AlmostSatisfied(index),
+            None => IncompatRelation::Satisfied,
+        }
+    }
+
+    /// Derives everything that can be derived from changes to `pkg`.
+    ///
+    /// Returns the incompatibility that proves resolution is impossible.
+    fn propagate(&mut self, pkg: PkgId) -> Result<(), IncompatId> {
+        let mut changed = vec![pkg];
+
+        while let Some(pkg) = changed.pop() {
+            let ids = self.by_pkg[pkg].clone();
+            for &id in ids.iter().rev() {
+                match self.relation(id) {
+                    IncompatRelation::Satisfied => {
+                        let root_cause = self.resolve_conflict(id)?;
+                        changed.clear();
+                        match self.relation(root_cause) {
+                            IncompatRelation::AlmostSatisfied(index) => {
+                                changed.push(self.derive(root_cause, index));
+                            }
+                            _ => unreachable!(
+                                "root cause of a conflict must be almost \
+                                 satisfied after backtracking"
+                            ),
+                        }
+                        break;
+                    }
+                    IncompatRelation::AlmostSatisfied(index) => {
+                        let pkg = self.derive(id, index);
+                        if !changed.contains(&pkg) {
+                            changed.push(pkg);
+                        }
+                    }
+                    IncompatRelation::Contradicted
+                    | IncompatRelation::Inconclusive => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Derives the inverse of the term at `index` in `id`, returning its
+    /// package.
+    fn derive(&mut self, id: IncompatId, index: usize) -> PkgId {
+        let term = self.incompats[id].terms[index].negate();
+        let pkg = term.pkg;
+        self.assign(term, Some(id));
+        pkg
+    }
+
+    fn assign(&mut self, term: Term, cause: Option<IncompatId>) {
+        let pkg = term.pkg;
+        self.accumulated[pkg] = Some(match self.accumulated[pkg].take() {
+            Some(acc) => acc.intersect(&term, &self.packages[pkg]),
+            None => term.clone(),
+        });
+        self.assignments.push(Assignment {
+            term,
+            level: self.level,
+            cause,
+        });
+    }
+
+    /// Selects the candidate at `index` for `pkg`.
+    fn select(&mut self, pkg: PkgId, index: usize) {
+        let package = &self.packages[pkg];
+        let set = VersionSet::exact(package.versions.len(), index);
+        let label = package.versions[index].to_string();
+
+        self.level += 1;
+        self.decisions[pkg] = Some(index);
+        self.assign(Term::new(pkg, true, set, label), None);
+    }
+
+    /// Undoes all assignments made after `level`.
+    fn backtrack(&mut self, level: usize) {
+        while let Some(assignment) = self.assignments.last() {
+            if assignment.level <= level {
+                break;
+            }
+            self.assignments.pop();
+        }
+        self.level = level;
+
+        for acc in &mut self.accumulated {
+            *acc = None;
+        }
+        for decision in &mut self.decisions {
+            *decision = None;
+        }
+        for assignment in &self.assignments {
+            let term = &assignment.term;
+            let pkg = term.pkg;
+            self.accumulated[pkg] = Some(match self.accumulated[pkg].take() {
+                Some(acc) => acc.intersect(term, &self.packages[pkg]),
+                None => term.clone(),
+            });
+            if assignment.cause.is_none() {
+                self.decisions[pkg] = term.set.candidates().next();
+            }
+        }
+    }
+
+    /// Returns the index of the earliest assignment after which `term` is
+    /// satisfied, or `None` if it is satisfied without any.
+    fn satisfier(&self, term: &Term) -> Option<usize> {
+        if term.relation(None) == Relation::Satisfied {
+            return None;
+        }
+
+        let package = &self.packages[term.pkg];
+        let mut acc: Option<Term> = None;
+        for (index, assignment) in self.assignments.iter().enumerate() {
+            if assignment.term.pkg != term.pkg {
+                continue;
+            }
+            acc = Some(match acc {
+                Some(acc) => acc.intersect(&assignment.term, package),
+                None => assignment.term.clone(),
+            });
+            if term.relation(acc.as_ref()) == Relation::Satisfied {
+                return Some(index);
+            }
+        }
+
+        unreachable!("{:?} is not satisfied by the partial solution", term)
+    }
+
+    fn level_of(&self, assignment: Option<usize>) -> usize {
+        assignment.map_or(0, |index| self.assignments[index].level)
+    }
+
+    fn is_failure(&self, id: IncompatId) -> bool {
+        match self.incompats[id].terms.as_slice() {
+            [] => true,
+            [term] => term.positive && term.pkg == ROOT,
+            _ => false,
+        }
+    }
+
+    /// Given an incompatibility that is satisfied by the partial solution,
+    /// backtracks until it is no longer satisfied and returns the
+    /// incompatibility that caused the conflict.
+    ///
+    /// Returns the incompatibility that proves resolution is impossible.
+    fn resolve_conflict(
+        &mut self,
+        mut id: IncompatId,
+    ) -> Result<IncompatId, IncompatId> {
+        let mut is_new = false;
+
+        while !self.is_failure(id) {
+            // The term whose satisfier comes last, along with that satisfier.
+            let mut most_recent: Option<(usize, Option<usize>)> = None;
+            // How `most_recent`'s satisfier goes beyond its term, if at all.
+            let mut difference: Option<Term> = None;
+            // The decision level to backtrack to. The root's decision is never
+            // undone.
+            let mut previous_level = 1;
+
+            let terms = self.incompats[id].terms.clone();
+            for (index, term) in terms.iter().enumerate() {
+                let satisfier = self.satisfier(term);
+                match most_recent {
+                    Some((_, recent)) if recent >= satisfier => {
+                        previous_level =
+                            cmp::max(previous_level, self.level_of(satisfier));
+                    }
+                    _ => {
+                        if let Some((_, recent)) = most_recent {
+                            previous_level =
+                                cmp::max(previous_level, self.level_of(recent));
+                        }
+                        most_recent = Some((index, satisfier));
+                        difference = None;
+                    }
+                }
+
+                if most_recent.map(|(i, _)| i) == Some(index) {
+                    if let Some(satisfier) = satisfier {
+                        let package = &self.packages[term.pkg];
+                        difference = self.assignments[satisfier]
+                            .term
+                            .difference(term, package);
+                        if let Some(difference) = &difference {
+                            let before = self.satisfier(&difference.negate());
+                            previous_level =
+                                cmp::max(previous_level, self.level_of(before));
+                        }
+                    }
+                }
+            }
+
+            let (term_index, satisfier) = match most_recent {
+                Some((index, Some(satisfier))) => (index, satisfier),
+                // Satisfied without any assignments; nothing to undo.
+                _ => return Err(id),
+            };
+            let satisfier = &self.assignments[satisfier];
+
+            let cause = match satisfier.cause {
+                Some(cause) if previous_level >= satisfier.level => cause,
+                _ => {
+                    self.backtrack(previous_level);
+                    if is_new {
+                        self.index_incompat(id);
+                    }
+                    return Ok(id);
+                }
+            };
+
+            let satisfier_pkg = satisfier.term.pkg;
+            let mut new_terms: Vec<Term> = terms
+                .into_iter()
+                .enumerate()
+                .filter(|&(index, _)| index != term_index)
+                .map(|(_, term)| term)
+                .collect();
+            new_terms.extend(
+                self.incompats[cause]
+                    .terms
+                    .iter()
+                    .filter(|term| term.pkg != satisfier_pkg)
+                    .cloned(),
+            );
+            if let Some(difference) = difference {
+                new_terms.push(difference.negate());
+            }
+
+            id = self.push_incompat(new_terms, Cause::Derived(id, cause));
+            is_new = true;
+        }
+
+        Err(id)
+    }
+
+    /// Selects a version for the next undecided package, returning that
+    /// package or `None` if every package has been decided on.
+    fn decide(&mut self) -> Result<Option<PkgId>, ResolveError<R::Error>> {
+        // Deciding on packages with fewer options first reduces backtracking.
+        let next = (0..self.packages.len())
+            .filter(|&pkg| self.decisions[pkg].is_none())
+            .filter_map(|pkg| match &self.accumulated[pkg] {
+                Some(acc) if acc.positive => {
+                    Some((acc.set.candidates().count(), pkg))
+                }
+                _ => None,
+            })
+            .min();
+        let pkg = match next {
+            Some((_, pkg)) => pkg,
+            None => return Ok(None),
+        };
+
+        let allowed = self.accumulated[pkg].clone().unwrap();
+
+        // Candidates are ordered newest first.
+        let preferred = self.packages[pkg].preferred;
+        let first = match preferred {
+            Some(index) if allowed.set.candidates().any(|i| i == index) => {
+                Some(index)
+            }
+            _ => allowed.set.candidates().next(),
+        };
+        let index = match first {
+            Some(index) => index,
+            None => {
+                self.add_incompat(vec![allowed], Cause::NoVersions);
+                return Ok(Some(pkg));
+            }
+        };
+
+        // Dependencies only need to be added once per version.
+        if self.deps.contains_key(&(pkg, index)) {
+            self.select(pkg, index);
+            return Ok(Some(pkg));
+        }
+
+        let mut conflict = false;
+        let mut deps = Vec::new();
+        for (terms, cause) in self.constraints(pkg, index)? {
+            // Selecting `pkg` would satisfy the incompatibility.
+            conflict |= terms[1..].iter().all(|term| {
+                let acc = self.accumulated[term.pkg].as_ref();
+                term.relation(acc) == Relation::Satisfied
+            });
+            if cause == Cause::Dependency {
+                deps.push(terms[1].pkg);
+            }
+            self.add_incompat(terms, cause);
+        }
+        self.deps.insert((pkg, index), deps);
+
+        if !conflict {
+            self.select(pkg, index);
+        }
+        Ok(Some(pkg))
+    }
+}
+
+/// Returns the name shown to users for `name`, which omits the default scope.
+fn display_name(name: &ScopedName) -> String {
+    if name.scope == "core" {
+        name.name.clone()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the requirements for `deps` that are needed on `platform`.
+fn dependencies(
+    deps: Deps,
+    platform: Option<&Platform>,
+) -> impl Iterator<Item = Requirement> + '_ {
+    let deps = deps.into_iter().filter(move |(_, info)| {
+        platform.is_none_or(|platform| info.is_needed_on(platform))
+    });
+    deps.map(|(query, info)| {
+        let scope = query.scope.unwrap_or_else(|| "core".to_owned());
+        Requirement {
+            name: ScopedName::new(scope, query.name),
+            version: info.version,
+            kind: if info.recommended {
+                RequirementKind::Recommended
+            } else if info.optional {
+                RequirementKind::Optional
+            } else {
+                RequirementKind::Dependency
+            },
+        }
+    })
+}
+
+/// Returns the conflicts declared by `manifest` for the drop named `drop`.
+fn conflicts<E>(
+    drop: &str,
+    manifest: &Manifest,
+) -> Result<Vec<Requirement>, ResolveError<E>> {
+    let conflicts = manifest.meta.conflicts.iter().flatten();
+    conflicts
+        .map(|(name, version)| {
+            let query = Query::<&str>::parse_liberal(name);
+            let scope = query.scope.unwrap_or("core");
+            let name = ScopedName::<String>::new(scope, query.name);
+            let version = VersionReq::parse(version).map_err(|error| {
+                ResolveError::InvalidRequirement {
+                    drop: drop.to_owned(),
+                    dependency: name.to_string(),
+                    error,
+                }
+            })?;
+            Ok(Requirement {
+                name,
+                version,
+                kind: RequirementKind::Conflict,
+            })
+        })
+        .collect()
+}
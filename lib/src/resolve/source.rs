@@ -0,0 +1,268 @@
+use super::{MemoryRegistry, Registry};
+use crate::drop::{
+    manifest::{DepInfo, Deps, Manifest},
+    name::ScopedName,
+    source::{
+        self,
+        git::{self, Ref},
+    },
+    version::Version,
+    Lockfile, Source,
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A [`Registry`](trait.Registry.html) that provides drops from the sources
+/// declared by dependencies, such as git repositories or local directories,
+/// before falling back to another registry.
+///
+/// A drop with such a source has exactly one version: the one declared by the
+/// manifest found at that source.
+#[derive(Debug)]
+pub struct SourceRegistry<R> {
+    registry: R,
+    cache_dir: PathBuf,
+    local: MemoryRegistry,
+    fetched: BTreeMap<ScopedName, SourcedDrop>,
+    locked: BTreeMap<ScopedName, Source>,
+    offline: bool,
+}
+
+/// A drop provided by a [`SourceRegistry`](struct.SourceRegistry.html).
+#[derive(Clone, Debug)]
+pub struct SourcedDrop {
+    /// The directory containing the drop's manifest.
+    pub path: PathBuf,
+    /// Where the drop was found, pointing to its exact git revision if
+    /// applicable.
+    pub source: Source,
+    /// The drop's manifest.
+    pub manifest: Manifest,
+}
+
+impl<R: Registry> SourceRegistry<R> {
+    /// Creates a registry that falls back to `registry` and caches fetched
+    /// sources within `cache_dir`.
+    pub fn new<P: Into<PathBuf>>(registry: R, cache_dir: P) -> Self {
+        Self {
+            registry,
+            cache_dir: cache_dir.into(),
+            local: MemoryRegistry::new(),
+            fetched: BTreeMap::new(),
+            locked: BTreeMap::new(),
+            offline: false,
+        }
+    }
+
+    /// Sets whether git repositories are only checked out from their clones
+    /// within the cache directory, rather than fetched.
+    #[inline]
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Prefers the git revisions recorded in `lockfile`, so that new commits
+    /// upstream aren't picked up until the drop is updated.
+    ///
+    /// A revision is only kept while it's reachable from the reference that
+    /// the dependency declares.
+    pub fn set_locked(&mut self, lockfile: &Lockfile) {
+        self.locked = lockfile
+            .drops
+            .iter()
+            .filter(|drop| matches!(drop.source.kind(), source::Kind::Git(_)))
+            .map(|drop| (drop.name.clone(), drop.source.clone()))
+            .collect();
+    }
+
+    /// Fetches the sources declared by `deps`, where `base_dir` is the
+    /// directory of the manifest that declares them.
+    ///
+    /// This must be called with the root's dependencies before resolving. The
+    /// dependencies of drops provided by this registry are fetched as their
+    /// manifests are requested. Path dependencies of drops from the fallback
+    /// registry are ignored, since they have no directory to be relative to.
+    pub fn add_deps<P: AsRef<Path>>(
+        &mut self,
+        deps: &Deps,
+        base_dir: P,
+    ) -> Result<(), SourceError<R::Error>> {
+        self.add_deps_at(deps, Some(base_dir.as_ref()))
+    }
+
+    fn add_deps_at(
+        &mut self,
+        deps: &Deps,
+        base_dir: Option<&Path>,
+    ) -> Result<(), SourceError<R::Error>> {
+        for (query, info) in deps {
+            let scope = query.scope.as_deref().unwrap_or("core");
+            let name = ScopedName::<String>::new(scope, query.name.as_str());
+            if self.fetched.contains_key(&name) {
+                continue;
+            }
+
+            let drop = match self.fetch(&name, info, base_dir) {
+                None => continue,
+                Some(Ok(drop)) if drop.manifest.meta.name == name.name => drop,
+                Some(Ok(drop)) => {
+                    let error = io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} provides \"{}\"",
+                            drop.source, drop.manifest.meta.name
+                        ),
+                    );
+                    return Err(SourceError::Fetch { drop: name, error });
+                }
+                Some(Err(error)) => {
+                    return Err(SourceError::Fetch { drop: name, error });
+                }
+            };
+
+            self.local.insert(scope, drop.manifest.clone());
+            let deps = drop.manifest.deps.clone().unwrap_or_default();
+            let path = drop.path.clone();
+            self.fetched.insert(name, drop);
+            self.add_deps_at(&deps, Some(&path))?;
+        }
+        Ok(())
+    }
+
+    /// Fetches the drop from the source declared by `info`, if any.
+    fn fetch(
+        &self,
+        name: &ScopedName,
+        info: &DepInfo,
+        base_dir: Option<&Path>,
+    ) -> Option<io::Result<SourcedDrop>> {
+        match (&info.path, base_dir, &info.git) {
+            (Some(path), Some(base_dir), _) => {
+                Some(fetch_path(&base_dir.join(path)))
+            }
+            (_, _, Some(git)) => {
+                let locked = self.locked.get(name).and_then(|locked| {
+                    let url = git::repo_url(&git.repo).ok()?;
+                    match locked.kind() {
+                        source::Kind::Git(Ref::Rev(rev))
+                            if *locked.url() == url =>
+                        {
+                            Some(rev.as_str())
+                        }
+                        _ => None,
+                    }
+                });
+                let checkout =
+                    git.checkout_impl(&self.cache_dir, !self.offline, locked);
+                Some(checkout.map(|checkout| SourcedDrop {
+                    source: checkout.source(),
+                    path: checkout.path,
+                    manifest: checkout.manifest,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the registry that drops without a source are requested from.
+    #[inline]
+    pub fn registry(&self) -> &R {
+        &self.registry
+    }
+
+    /// Returns the fetched source that provides `drop`, if any.
+    pub fn sourced(&self, drop: ScopedName<&str>) -> Option<&SourcedDrop> {
+        self.fetched.get(&drop.to_owned())
+    }
+
+    /// Returns the fetched sources that provide drops, by name.
+    pub fn into_sourced(self) -> BTreeMap<ScopedName, SourcedDrop> {
+        self.fetched
+    }
+}
+
+fn fetch_path(path: &Path) -> io::Result<SourcedDrop> {
+    let path = fs::canonicalize(path)?;
+    let url = url::Url::from_directory_path(&path).map_err(|()| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("\"{}\" is not a valid directory", path.display()),
+        )
+    })?;
+    Ok(SourcedDrop {
+        manifest: Manifest::read_toml_file(path.join(Manifest::FILE_NAME))?,
+        source: Source::from_path(url),
+        path,
+    })
+}
+
+impl<R: Registry> Registry for SourceRegistry<R> {
+    type Error = SourceError<R::Error>;
+
+    fn versions(
+        &mut self,
+        drop: ScopedName<&str>,
+    ) -> Result<Vec<Version>, Self::Error> {
+        if self.sourced(drop).is_some() {
+            let versions = self.local.versions(drop);
+            return Ok(versions.unwrap_or_default());
+        }
+        self.registry.versions(drop).map_err(SourceError::Registry)
+    }
+
+    fn manifest(
+        &mut self,
+        drop: ScopedName<&str>,
+        version: &Version,
+    ) -> Result<Manifest, Self::Error> {
+        if let Some(sourced) = self.sourced(drop) {
+            return Ok(sourced.manifest.clone());
+        }
+        let manifest = self
+            .registry
+            .manifest(drop, version)
+            .map_err(SourceError::Registry)?;
+        if let Some(deps) = &manifest.deps {
+            self.add_deps_at(deps, None)?;
+        }
+        Ok(manifest)
+    }
+}
+
+/// The error returned by a [`SourceRegistry`](struct.SourceRegistry.html).
+#[derive(Debug)]
+pub enum SourceError<E> {
+    /// A dependency's source could not be fetched or read.
+    Fetch {
+        /// The drop that the source provides.
+        drop: ScopedName,
+        /// Why fetching failed.
+        error: io::Error,
+    },
+    /// The fallback registry failed.
+    Registry(E),
+}
+
+impl<E: fmt::Display> fmt::Display for SourceError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fetch { drop, error } => {
+                write!(f, "Failed to fetch \"{}\": {}", drop, error)
+            }
+            Self::Registry(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for SourceError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Fetch { error, .. } => Some(error),
+            Self::Registry(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Statements about which versions of a drop get selected.
+
+use super::{set::VersionSet, solver::Package};
+
+/// Index of a package within the solver.
+pub(super) type PkgId = usize;
+
+/// A statement that a drop is selected at a version within `set`, or, if not
+/// `positive`, that it is _not_ selected at any version within `set`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Term {
+    pub pkg: PkgId,
+    pub positive: bool,
+    pub set: VersionSet,
+    /// How `set` is presented to users; e.g. `^1.0`.
+    pub label: String,
+}
+
+/// How the partial solution relates to a term.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Relation {
+    /// Every assignment allowed by the solution also satisfies the term.
+    Satisfied,
+    /// No assignment allowed by the solution satisfies the term.
+    Contradicted,
+    /// Neither of the above.
+    Inconclusive,
+}
+
+impl Term {
+    pub fn new(
+        pkg: PkgId,
+        positive: bool,
+        set: VersionSet,
+        label: String,
+    ) -> Self {
+        Self {
+            pkg,
+            positive,
+            set,
+            label,
+        }
+    }
+
+    /// Returns the inverse of `self`.
+    pub fn negate(&self) -> Self {
+        Self {
+            positive: !self.positive,
+            ..self.clone()
+        }
+    }
+
+    /// Returns whether no selection can satisfy `self`.
+    pub fn is_empty(&self) -> bool {
+        self.positive && self.set.is_empty()
+    }
+
+    /// Returns a term that is satisfied only if both `self` and `other` are.
+    pub fn intersect(&self, other: &Self, package: &Package) -> Self {
+        debug_assert_eq!(self.pkg, other.pkg);
+
+        let (positive, set) = match (self.positive, other.positive) {
+            (true, true) => (true, self.set.intersection(&other.set)),
+            (true, false) => (true, self.set.difference(&other.set)),
+            (false, true) => (true, other.set.difference(&self.set)),
+            (false, false) => (false, self.set.union(&other.set)),
+        };
+
+        let label = if set == self.set && positive == self.positive {
+            self.label.clone()
+        } else if set == other.set && positive == other.positive {
+            other.label.clone()
+        } else if self.positive && other.positive {
+            format!("{}, {}", self.label, other.label)
+        } else if !self.positive && !other.positive {
+            format!("{} || {}", self.label, other.label)
+        } else {
+            package.describe(&set)
+        };
+
+        Self::new(self.pkg, positive, set, label)
+    }
+
+    /// Returns a term that is satisfied only if `self` is satisfied and
+    /// `other` is not, or `None` if no selection satisfies that.
+    pub fn difference(&self, other: &Self, package: &Package) -> Option<Self> {
+        Some(self.intersect(&other.negate(), package)).filter(|t| !t.is_empty())
+    }
+
+    /// Returns how `accumulated`, the intersection of all assignments for the
+    /// package of `self`, relates to `self`.
+    pub fn relation(&self, accumulated: Option<&Term>) -> Relation {
+        use Relation::*;
+
+        let acc = match accumulated {
+            Some(acc) => acc,
+            // Every version is allowed, as well as not selecting the package.
+            None => {
+                return match (self.positive, self.set.is_empty()) {
+                    (true, true) => Contradicted,
+                    (false, true) => Satisfied,
+                    _ => Inconclusive,
+                };
+            }
+        };
+
+        match (acc.positive, self.positive) {
+            (true, true) => {
+                if acc.set.is_subset(&self.set) {
+                    Satisfied
+                } else if acc.set.is_disjoint(&self.set) {
+                    Contradicted
+                } else {
+                    Inconclusive
+                }
+            }
+            (true, false) => {
+                if acc.set.is_disjoint(&self.set) {
+                    Satisfied
+                } else if acc.set.is_subset(&self.set) {
+                    Contradicted
+                } else {
+                    Inconclusive
+                }
+            }
+            // Not selecting the package is allowed, so a positive term can
+            // never be satisfied.
+            (false, true) => {
+                if self.set.is_subset(&acc.set) {
+                    Contradicted
+                } else {
+                    Inconclusive
+                }
+            }
+            // Not selecting the package is allowed, so a negative term can
+            // never be contradicted.
+            (false, false) => {
+                if self.set.is_subset(&acc.set) {
+                    Satisfied
+                } else {
+                    Inconclusive
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,590 @@
+use super::*;
+use crate::drop::{
+    manifest::{DepInfo, Meta},
+    name::Query,
+    version::{Version, VersionReq},
+};
+
+fn deps(deps: &[(&str, &str)]) -> Deps {
+    deps.iter()
+        .map(|&(name, version)| {
+            let query = Query::<&str>::parse_liberal(name).to_owned();
+            (query, DepInfo::from(VersionReq::parse(version).unwrap()))
+        })
+        .collect()
+}
+
+fn manifest(
+    name: &str,
+    version: &str,
+    dependencies: &[(&str, &str)],
+) -> Manifest {
+    Manifest {
+        meta: Meta {
+            name: name.to_owned(),
+            display_name: None,
+            description: String::new(),
+            exe_path: None,
+            kind: None,
+            version: Version::parse(version),
+            conflicts: None,
+            license: None,
+            authors: None,
+            readme: None,
+            changelog: None,
+            git: None,
+            homepage: None,
+            documentation: None,
+        },
+        deps: Some(deps(dependencies)),
+    }
+}
+
+/// A drop's name, version, and dependencies.
+type Release<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+fn registry(drops: &[Release]) -> MemoryRegistry {
+    let mut registry = MemoryRegistry::new();
+    for &(name, version, deps) in drops {
+        registry.insert("core", manifest(name, version, deps));
+    }
+    registry
+}
+
+fn solve(
+    root: &[(&str, &str)],
+    registry: &mut MemoryRegistry,
+) -> Result<Vec<(String, String)>, ResolveError<MissingManifest>> {
+    let resolution = resolve_deps("root", &deps(root), registry)?;
+    Ok(resolution
+        .drops
+        .into_iter()
+        .map(|(name, drop)| (name.name, drop.version.to_string()))
+        .collect())
+}
+
+fn selected(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|&(name, version)| (name.to_owned(), version.to_owned()))
+        .collect()
+}
+
+fn report(root: &[(&str, &str)], registry: &mut MemoryRegistry) -> String {
+    match solve(root, registry) {
+        Err(ResolveError::NoSolution(report)) => report.to_string(),
+        result => panic!("expected no solution, got {:?}", result),
+    }
+}
+
+#[test]
+fn no_conflicts() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[("bar", "^1.0.0")]),
+        ("bar", "1.0.0", &[]),
+        ("bar", "2.0.0", &[]),
+    ]);
+    let result = solve(&[("foo", "^1.0.0")], &mut registry).unwrap();
+    assert_eq!(result, selected(&[("bar", "1.0.0"), ("foo", "1.0.0")]));
+}
+
+#[test]
+fn avoids_conflict_during_decision_making() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[]),
+        ("foo", "1.1.0", &[("bar", "^2.0.0")]),
+        ("bar", "1.0.0", &[]),
+        ("bar", "1.1.0", &[]),
+        ("bar", "2.0.0", &[]),
+    ]);
+    let root = [("foo", "^1.0.0"), ("bar", "^1.0.0")];
+    let result = solve(&root, &mut registry).unwrap();
+    assert_eq!(result, selected(&[("bar", "1.1.0"), ("foo", "1.0.0")]));
+}
+
+#[test]
+fn performs_conflict_resolution() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[]),
+        ("foo", "2.0.0", &[("bar", "^1.0.0")]),
+        ("bar", "1.0.0", &[("foo", "^1.0.0")]),
+    ]);
+    let result = solve(&[("foo", ">=1.0.0")], &mut registry).unwrap();
+    assert_eq!(result, selected(&[("foo", "1.0.0")]));
+}
+
+#[test]
+fn partial_satisfier() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[]),
+        ("foo", "1.1.0", &[("left", "^1.0.0"), ("right", "^1.0.0")]),
+        ("left", "1.0.0", &[("shared", ">=1.0.0")]),
+        ("right", "1.0.0", &[("shared", "<2.0.0")]),
+        ("shared", "1.0.0", &[("target", "^1.0.0")]),
+        ("shared", "2.0.0", &[]),
+        ("target", "1.0.0", &[]),
+        ("target", "2.0.0", &[]),
+    ]);
+    let root = [("foo", "^1.0.0"), ("target", "^2.0.0")];
+    let result = solve(&root, &mut registry).unwrap();
+    assert_eq!(result, selected(&[("foo", "1.0.0"), ("target", "2.0.0")]));
+}
+
+#[test]
+fn reports_linear_failure() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[("bar", "^2.0.0")]),
+        ("bar", "2.0.0", &[("baz", "^3.0.0")]),
+        ("baz", "1.0.0", &[]),
+        ("baz", "3.0.0", &[]),
+    ]);
+    let root = [("foo", "^1.0.0"), ("baz", "^1.0.0")];
+    assert_eq!(
+        report(&root, &mut registry),
+        "Because every version of foo depends on bar ^2.0.0 which depends \
+         on baz ^3.0.0, every version of foo requires baz ^3.0.0.\n\
+         So, because root depends on both baz ^1.0.0 and foo ^1.0.0, \
+         version solving failed.",
+    );
+}
+
+#[test]
+fn reports_branching_failure() {
+    let mut registry = registry(&[
+        ("a", "1.0.0", &[("shared", ">=2.0.0")]),
+        ("b", "1.0.0", &[("shared", "<1.0.0")]),
+        ("shared", "0.9.0", &[]),
+        ("shared", "2.0.0", &[]),
+    ]);
+    let root = [("a", "*"), ("b", "*")];
+    assert_eq!(
+        report(&root, &mut registry),
+        "Because every version of a depends on shared >=2.0.0 and every \
+         version of b depends on shared <1.0.0, every version of a is \
+         incompatible with b.\n\
+         So, because root depends on both a and b, version solving failed.",
+    );
+}
+
+#[test]
+fn reports_missing_drop() {
+    let mut registry = registry(&[("foo", "1.0.0", &[("bar", "^1.0.0")])]);
+    assert_eq!(
+        report(&[("foo", "^1.0.0")], &mut registry),
+        "Because every version of foo depends on bar ^1.0.0 which doesn't \
+         exist, foo is forbidden.\n\
+         So, because root depends on foo ^1.0.0, version solving failed.",
+    );
+}
+
+#[test]
+fn invalid_requirement() {
+    let mut registry = registry(&[]);
+    let foo = manifest("foo", "1.0.0", &[]);
+    registry.insert("core", conflicting(foo, &[("bar", ">=^1")]));
+    match solve(&[("foo", "*")], &mut registry) {
+        Err(ResolveError::InvalidRequirement {
+            drop, dependency, ..
+        }) => {
+            assert_eq!(drop, "foo");
+            assert_eq!(dependency, "core/bar");
+        }
+        result => panic!("expected invalid requirement, got {:?}", result),
+    }
+}
+
+#[test]
+fn reports_numbered_failure() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[("a", "^1.0.0"), ("b", "^1.0.0")]),
+        ("foo", "1.1.0", &[("x", "^1.0.0"), ("y", "^1.0.0")]),
+        ("a", "1.0.0", &[("b", "^2.0.0")]),
+        ("b", "1.0.0", &[]),
+        ("b", "2.0.0", &[]),
+        ("x", "1.0.0", &[("y", "^2.0.0")]),
+        ("y", "1.0.0", &[]),
+        ("y", "2.0.0", &[]),
+    ]);
+    let expected = [
+        "    Because foo 1.0.0 depends on a ^1.0.0 which depends on b ^2.0.0, \
+         foo 1.0.0 requires b ^2.0.0.",
+        "(1) So, because foo 1.0.0 depends on b ^1.0.0, foo 1.0.0 is \
+         forbidden.",
+        "",
+        "    Because foo 1.1.0 depends on x ^1.0.0 which depends on y ^2.0.0, \
+         foo 1.1.0 requires y ^2.0.0.",
+        "    And because foo 1.1.0 depends on y ^1.0.0, foo 1.1.0 is \
+         forbidden.",
+        "    And because foo 1.0.0 is forbidden (1), foo is forbidden.",
+        "    So, because root depends on foo ^1.0.0, version solving failed.",
+    ];
+    assert_eq!(
+        report(&[("foo", "^1.0.0")], &mut registry),
+        expected.join("\n"),
+    );
+}
+
+#[test]
+fn resolves_other_version_schemes() {
+    let mut registry = registry(&[
+        ("foo", "1.2.3.4", &[]),
+        ("foo", "1.2.10.0", &[]),
+        ("foo", "1.3.0.0", &[]),
+        ("bar", "r1234", &[]),
+        ("bar", "r1235", &[]),
+    ]);
+    let root = [("foo", ">=1.2, <1.3"), ("bar", "=r1234")];
+    let result = solve(&root, &mut registry).unwrap();
+    assert_eq!(result, selected(&[("bar", "r1234"), ("foo", "1.2.10.0")]));
+}
+
+#[test]
+fn only_selects_pinned_custom_versions() {
+    let mut registry = registry(&[("foo", "r999", &[]), ("foo", "r1234", &[])]);
+    let result = solve(&[("foo", "=r1234")], &mut registry).unwrap();
+    assert_eq!(result, selected(&[("foo", "r1234")]));
+    let result = solve(&[("foo", "=r999")], &mut registry).unwrap();
+    assert_eq!(result, selected(&[("foo", "r999")]));
+
+    // Neither is newer than the other, so nothing is picked for `*`.
+    assert!(solve(&[("foo", "*")], &mut registry).is_err());
+}
+
+#[test]
+fn prefers_locked_versions() {
+    let mut registry = registry(&[
+        ("foo", "1.0.0", &[]),
+        ("foo", "1.1.0", &[]),
+        ("foo", "2.0.0", &[]),
+    ]);
+    let mut options = Options::default();
+    let foo = ScopedName::new("core", "foo");
+    options.preferred.insert(foo, Version::parse("1.0.0"));
+
+    let deps = deps(&[("foo", "^1.0.0")]);
+    let resolution =
+        resolve_deps_with("root", &deps, &mut registry, &options).unwrap();
+    let version = &resolution.drops.values().next().unwrap().version;
+    assert_eq!(version.to_string(), "1.0.0");
+
+    // Locked versions that are no longer allowed are ignored.
+    let deps = self::deps(&[("foo", "^2.0.0")]);
+    let resolution =
+        resolve_deps_with("root", &deps, &mut registry, &options).unwrap();
+    let version = &resolution.drops.values().next().unwrap().version;
+    assert_eq!(version.to_string(), "2.0.0");
+}
+
+fn conflicting(mut manifest: Manifest, conflicts: &[(&str, &str)]) -> Manifest {
+    let conflicts = conflicts
+        .iter()
+        .map(|&(name, range)| (name.to_owned(), range.to_owned()))
+        .collect();
+    manifest.meta.conflicts = Some(conflicts);
+    manifest
+}
+
+#[test]
+fn avoids_conflicts() {
+    let mut registry =
+        registry(&[("foo", "1.0.0", &[]), ("bar", "1.0.0", &[])]);
+    let foo = manifest("foo", "2.0.0", &[]);
+    registry.insert("core", conflicting(foo, &[("bar", "<2.0.0")]));
+
+    let root = [("foo", "*"), ("bar", "^1.0.0")];
+    let result = solve(&root, &mut registry).unwrap();
+    assert_eq!(result, selected(&[("bar", "1.0.0"), ("foo", "1.0.0")]));
+}
+
+#[test]
+fn reports_conflict() {
+    let mut registry = registry(&[("bar", "1.0.0", &[])]);
+    let foo = manifest("foo", "1.0.0", &[]);
+    registry.insert("core", conflicting(foo, &[("bar", "<2.0.0")]));
+
+    let root = [("foo", "*"), ("bar", "^1.0.0")];
+    assert_eq!(
+        report(&root, &mut registry),
+        "Because every version of foo conflicts with bar <2.0.0 and root \
+         depends on bar ^1.0.0, foo is forbidden.\n\
+         So, because root depends on foo, version solving failed.",
+    );
+}
+
+#[test]
+fn reports_installed_conflict() {
+    let mut registry = registry(&[]);
+    let foo = manifest("foo", "1.0.0", &[]);
+    registry.insert("core", conflicting(foo, &[("bar", "<2.0.0")]));
+
+    let mut options = Options::default();
+    let bar = ScopedName::new("core", "bar");
+    options
+        .installed
+        .insert(bar, vec![manifest("bar", "1.2.0", &[])]);
+
+    let deps = deps(&[("foo", "*")]);
+    match resolve_deps_with("root", &deps, &mut registry, &options) {
+        Err(ResolveError::NoSolution(report)) => assert_eq!(
+            report.to_string(),
+            "Because every version of foo conflicts with bar <2.0.0 and bar \
+             1.2.0 is installed, foo is forbidden.\n\
+             So, because root depends on foo, version solving failed.",
+        ),
+        result => panic!("expected no solution, got {:?}", result),
+    }
+}
+
+#[test]
+fn respects_installed_conflicts() {
+    let mut registry =
+        registry(&[("foo", "1.0.0", &[]), ("foo", "1.1.0", &[])]);
+
+    let mut options = Options::default();
+    let bar = conflicting(manifest("bar", "1.2.0", &[]), &[("foo", ">=1.1.0")]);
+    options
+        .installed
+        .insert(ScopedName::new("core", "bar"), vec![bar]);
+
+    let deps = deps(&[("foo", "*")]);
+    let resolution =
+        resolve_deps_with("root", &deps, &mut registry, &options).unwrap();
+    assert_eq!(resolution.drops.len(), 1);
+    let version = &resolution.drops.values().next().unwrap().version;
+    assert_eq!(version.to_string(), "1.0.0");
+}
+
+fn marked(
+    mut manifest: Manifest,
+    optional: &[&str],
+    recommended: &[&str],
+) -> Manifest {
+    for (query, info) in manifest.deps.iter_mut().flatten() {
+        info.optional = optional.contains(&query.name.as_str());
+        info.recommended = recommended.contains(&query.name.as_str());
+    }
+    manifest
+}
+
+fn solve_including(
+    root: &[(&str, &str)],
+    registry: &mut MemoryRegistry,
+    with: &[&str],
+    without: &[&str],
+) -> Result<Vec<String>, ResolveError<MissingManifest>> {
+    let options = Options {
+        with: with.iter().map(|&name| name.to_owned()).collect(),
+        without: without.iter().map(|&name| name.to_owned()).collect(),
+        ..Options::default()
+    };
+    let resolution =
+        resolve_deps_with("root", &deps(root), registry, &options)?;
+    Ok(resolution.drops.into_keys().map(|name| name.name).collect())
+}
+
+#[test]
+fn includes_optional_dependencies() {
+    let mut registry = registry(&[
+        ("opt", "1.0.0", &[]),
+        ("rec", "1.0.0", &[]),
+        ("nested", "1.0.0", &[]),
+    ]);
+    let foo = manifest("foo", "1.0.0", &[("opt", "*"), ("rec", "*")]);
+    registry.insert("core", marked(foo, &["opt"], &["rec"]));
+    let bar = manifest("bar", "1.0.0", &[("nested", "*")]);
+    registry.insert("core", marked(bar, &["nested"], &[]));
+    let opt = manifest("opt", "2.0.0", &[("bar", "*")]);
+    registry.insert("core", opt);
+
+    let root = [("foo", "*")];
+    let result = solve_including(&root, &mut registry, &[], &[]).unwrap();
+    assert_eq!(result, ["foo", "rec"]);
+
+    let result =
+        solve_including(&root, &mut registry, &["opt"], &["rec"]).unwrap();
+    assert_eq!(result, ["bar", "foo", "opt"]);
+
+    // Only dependencies of requested drops can be included.
+    let result = solve_including(&root, &mut registry, &["nested"], &[]);
+    match result {
+        Err(ResolveError::UndeclaredDependency { name }) => {
+            assert_eq!(name, "nested");
+        }
+        result => panic!("expected undeclared dependency, got {:?}", result),
+    }
+}
+
+#[test]
+fn skips_dependencies_for_other_platforms() {
+    let mut registry = registry(&[
+        ("linux-helper", "1.0.0", &[]),
+        ("arm-helper", "1.0.0", &[]),
+    ]);
+    let mut foo = manifest(
+        "foo",
+        "1.0.0",
+        &[("linux-helper", "*"), ("arm-helper", "*")],
+    );
+    for (query, info) in foo.deps.iter_mut().flatten() {
+        match query.name.as_str() {
+            "linux-helper" => info.os = vec!["Linux".to_owned()],
+            _ => info.arch = vec!["aarch64".to_owned()],
+        }
+    }
+    registry.insert("core", foo);
+
+    let mut resolve_on = |platform: Option<Platform>| {
+        let options = Options {
+            platform,
+            ..Options::default()
+        };
+        let deps = deps(&[("foo", "*")]);
+        let resolution =
+            resolve_deps_with("root", &deps, &mut registry, &options).unwrap();
+        let names = resolution.drops.into_keys().map(|name| name.name);
+        names.collect::<Vec<_>>()
+    };
+
+    let linux = Platform::new("linux", "x86_64");
+    assert_eq!(resolve_on(Some(linux)), ["foo", "linux-helper"]);
+
+    let macos = Platform::new("macOS", "aarch64");
+    assert_eq!(resolve_on(Some(macos)), ["arm-helper", "foo"]);
+
+    let all = ["arm-helper", "foo", "linux-helper"];
+    assert_eq!(resolve_on(None), all);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn resolves_git_dependencies() {
+    use crate::drop::source::git::{tests::bare_repo, Git, Ref};
+
+    let dir = tempfile::tempdir().unwrap();
+    let repo = bare_repo(dir.path(), "tool", &["1.0.0", "2.0.0"]);
+    let git = Git::new(repo.to_string_lossy(), Ref::tag("v1.0.0"));
+
+    // The registry's versions of `tool` are ignored in favor of git.
+    let registry = self::registry(&[("tool", "1.5.0", &[])]);
+    let mut registry = SourceRegistry::new(registry, dir.path().join("cache"));
+
+    let mut root = deps(&[("tool", "*")]);
+    root.values_mut()
+        .for_each(|info| info.git = Some(git.clone()));
+    registry.add_deps(&root, dir.path()).unwrap();
+
+    let resolution = resolve_deps("root", &root, &mut registry).unwrap();
+    let tool = ScopedName::new("core", "tool");
+    assert_eq!(resolution.drops[&tool].version.to_string(), "1.0.0");
+
+    let sourced = registry.sourced(tool.to_ref()).unwrap();
+    assert!(sourced.path.join("Ocean.toml").is_file());
+    assert!(sourced.source.to_string().starts_with("git+file://"));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn keeps_locked_git_revisions() {
+    use crate::drop::{
+        lock::LockedDrop,
+        source::git::{
+            tests::{advance_repo, bare_repo},
+            Git,
+        },
+        Lockfile,
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let repo = bare_repo(dir.path(), "tool", &["1.0.0"]);
+    let mut root = deps(&[("tool", "*")]);
+    root.values_mut().for_each(|info| {
+        info.git = Some(Git::new(repo.to_string_lossy(), None));
+    });
+    let tool = ScopedName::new("core", "tool");
+
+    let fetch = |lockfile: Option<&Lockfile>| {
+        let registry = self::registry(&[]);
+        let mut registry =
+            SourceRegistry::new(registry, dir.path().join("cache"));
+        if let Some(lockfile) = lockfile {
+            registry.set_locked(lockfile);
+        }
+        registry.add_deps(&root, dir.path()).unwrap();
+        let resolution = resolve_deps("root", &root, &mut registry).unwrap();
+        let sourced = registry.sourced(tool.to_ref()).unwrap().clone();
+        (resolution.drops[&tool].version.clone(), sourced.source)
+    };
+
+    let (version, source) = fetch(None);
+    assert_eq!(version.to_string(), "1.0.0");
+    let lockfile = Lockfile::new(vec![LockedDrop {
+        name: tool.to_owned(),
+        version: version.clone(),
+        source: source.clone(),
+        checksum: None,
+        dependencies: Vec::new(),
+    }]);
+
+    advance_repo(dir.path(), "tool", "1.1.0");
+    assert_eq!(fetch(Some(&lockfile)), (version, source.clone()));
+
+    let (version, updated) = fetch(None);
+    assert_eq!(version.to_string(), "1.1.0");
+    assert_ne!(updated, source);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn resolves_path_dependencies() {
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, version: &str, deps: &str| {
+        let manifest = format!(
+            "[meta]\nname = \"{}\"\ndescription = \"\"\nversion = \"{}\"\n\
+             [dependencies]\n{}",
+            name, version, deps,
+        );
+        fs::create_dir_all(dir.path().join(name)).unwrap();
+        fs::write(dir.path().join(name).join("Ocean.toml"), manifest).unwrap();
+    };
+    write(
+        "tool",
+        "0.1.0",
+        "shared = { version = \"*\", path = \"../shared\" }",
+    );
+    write("shared", "0.2.0", "");
+
+    let registry = self::registry(&[("shared", "1.0.0", &[])]);
+    let mut registry = SourceRegistry::new(registry, dir.path().join("cache"));
+
+    let mut root = deps(&[("tool", "*")]);
+    root.values_mut()
+        .for_each(|info| info.path = Some("tool".to_owned()));
+    registry.add_deps(&root, dir.path()).unwrap();
+
+    let result = resolve_deps("root", &root, &mut registry).unwrap();
+    let versions: Vec<String> = result
+        .drops
+        .values()
+        .map(|drop| drop.version.to_string())
+        .collect();
+    assert_eq!(versions, ["0.2.0", "0.1.0"]);
+
+    let shared = ScopedName::<String>::new("core", "shared");
+    let sourced = registry.sourced(shared.to_ref()).unwrap();
+    assert_eq!(
+        sourced.path,
+        dir.path().join("shared").canonicalize().unwrap()
+    );
+    assert!(sourced.source.to_string().starts_with("path+file://"));
+
+    let mut missing = deps(&[("other", "*")]);
+    missing
+        .values_mut()
+        .for_each(|info| info.path = Some("missing".to_owned()));
+    match registry.add_deps(&missing, dir.path()) {
+        Err(SourceError::Fetch { drop, .. }) => assert_eq!(drop, "core/other"),
+        result => panic!("expected fetch error, got {:?}", result),
+    }
+}
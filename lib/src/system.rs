@@ -2,7 +2,7 @@
 
 use std::{
     ffi::OsStr,
-    io,
+    fmt, io,
     process::{Child, Command, ExitStatus},
 };
 
@@ -40,10 +40,10 @@ where
         if status.success() {
             Ok(())
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Opening finished with status {}", status),
-            ))
+            Err(io::Error::other(format!(
+                "Opening finished with status {}",
+                status
+            )))
         }
     }
 
@@ -80,3 +80,57 @@ where
         Ok(())
     }
 }
+
+/// An operating system and CPU architecture, such as `linux` on `x86_64`.
+///
+/// Names are compared case-insensitively, so `macOS` matches `macos`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Platform {
+    /// The operating system, e.g. `linux`, `macOS`, or `windows`.
+    pub os: String,
+    /// The CPU architecture, e.g. `x86_64` or `aarch64`.
+    pub arch: String,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.arch, self.os)
+    }
+}
+
+impl Platform {
+    /// Creates a new instance from an operating system and architecture.
+    #[inline]
+    pub fn new<O, A>(os: O, arch: A) -> Self
+    where
+        O: Into<String>,
+        A: Into<String>,
+    {
+        Self {
+            os: os.into(),
+            arch: arch.into(),
+        }
+    }
+
+    /// Returns the platform that this library was compiled for.
+    pub fn current() -> Self {
+        Self::new(std::env::consts::OS, std::env::consts::ARCH)
+    }
+
+    /// Returns whether `self` runs any of `os`, where an empty list means
+    /// all operating systems.
+    pub fn matches_os<S: AsRef<str>>(&self, os: &[S]) -> bool {
+        matches_any(&self.os, os)
+    }
+
+    /// Returns whether `self` has any of `arch`, where an empty list means
+    /// all architectures.
+    pub fn matches_arch<S: AsRef<str>>(&self, arch: &[S]) -> bool {
+        matches_any(&self.arch, arch)
+    }
+}
+
+fn matches_any<S: AsRef<str>>(value: &str, list: &[S]) -> bool {
+    list.is_empty()
+        || list.iter().any(|s| s.as_ref().eq_ignore_ascii_case(value))
+}